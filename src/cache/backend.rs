@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use cacache::Metadata;
+
+use super::{Cacache, Cache, CacheOptions, MemoryCache, Stats};
+
+use crate::{
+    config::{args::CacheBackend, CONF},
+    error::Result,
+    request::Headers,
+};
+
+/// The cache selected with `--cache-backend`.
+pub enum Backend {
+    Disk(Cacache),
+    Memory(MemoryCache),
+}
+
+impl Backend {
+    /// Create the cache of the given `kind`.
+    ///
+    /// The `dir` is only used, and created if missing, by the disk cache.
+    pub fn new(kind: CacheBackend, dir: PathBuf, options: CacheOptions) -> Result<Self> {
+        let backend = match kind {
+            CacheBackend::Disk => Self::Disk(Cacache::open(dir)?.with_options(options)),
+            CacheBackend::Memory => Self::Memory(MemoryCache::init()?.with_options(options)),
+        };
+        Ok(backend)
+    }
+}
+
+impl Cache for Backend {
+    fn init() -> Result<Self> {
+        let options = CacheOptions {
+            verbose: CONF.args.verbose,
+            not_found_ttl: CONF.not_found_ttl(),
+        };
+        Self::new(CONF.args.cache_backend, CONF.cache_dir(), options)
+    }
+
+    fn read(&self, meta: &Metadata) -> Result<String> {
+        match self {
+            Self::Disk(cache) => cache.read(meta),
+            Self::Memory(cache) => cache.read(meta),
+        }
+    }
+
+    fn write(&self, headers: &Headers, url: &str, text: &str) -> Result<()> {
+        match self {
+            Self::Disk(cache) => cache.write(headers, url, text),
+            Self::Memory(cache) => cache.write(headers, url, text),
+        }
+    }
+
+    fn meta(&self, url: &str) -> Result<Option<Metadata>> {
+        match self {
+            Self::Disk(cache) => cache.meta(url),
+            Self::Memory(cache) => cache.meta(url),
+        }
+    }
+
+    fn clear(&self) -> Result<()> {
+        match self {
+            Self::Disk(cache) => cache.clear(),
+            Self::Memory(cache) => cache.clear(),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<Metadata>> {
+        match self {
+            Self::Disk(cache) => cache.list(),
+            Self::Memory(cache) => cache.list(),
+        }
+    }
+
+    fn stats(&self) -> &Stats {
+        match self {
+            Self::Disk(cache) => cache.stats(),
+            Self::Memory(cache) => cache.stats(),
+        }
+    }
+
+    fn options(&self) -> &CacheOptions {
+        match self {
+            Self::Disk(cache) => cache.options(),
+            Self::Memory(cache) => cache.options(),
+        }
+    }
+}
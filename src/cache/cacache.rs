@@ -1,31 +1,54 @@
-use std::{io::Write, path::PathBuf};
+use std::{fs, io::Write, path::PathBuf};
 
 use cacache::Metadata;
 use itertools::Itertools;
-use lazy_static::lazy_static;
 use tracing::info;
 
-use super::Cache;
+use super::{Cache, CacheOptions, Stats};
 
 use crate::{
+    config::CONF,
     error::{Error, Result},
     request::Headers,
-    DIR,
 };
 
-lazy_static! {
+pub struct Cacache {
     /// Path to the cache.
-    static ref CACHE: PathBuf  = DIR.cache_dir().into();
+    path: PathBuf,
+    stats: Stats,
+    options: CacheOptions,
 }
 
-pub struct Cacache;
+impl Cacache {
+    /// Use the cache located at `path` instead of the default location.
+    pub fn at<P: Into<PathBuf>>(path: P) -> Self {
+        Cacache {
+            path: path.into(),
+            stats: Stats::default(),
+            options: CacheOptions::default(),
+        }
+    }
+
+    /// Use the cache located at `path`, creating the directory if missing.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        // `cacache::Error` cannot be built from an io error
+        fs::create_dir_all(&path).map_err(|why| Error::Io(why, "creating cache directory"))?;
+        Ok(Cacache::at(path))
+    }
+
+    /// Replace the default [`CacheOptions`].
+    pub fn with_options(self, options: CacheOptions) -> Self {
+        Cacache { options, ..self }
+    }
+}
 
 impl Cache for Cacache
 where
     Self: Sized,
 {
     fn init() -> Result<Self> {
-        Ok(Cacache)
+        Cacache::open(CONF.cache_dir())
     }
 
     fn write(&self, headers: &Headers, url: &str, text: &str) -> Result<()> {
@@ -33,7 +56,7 @@ where
             .map_err(|why| Error::Serializing(why, "writing headers to cache"))?;
         let mut writer = cacache::WriteOpts::new()
             .metadata(header_serialized)
-            .open_sync(&*CACHE, url)
+            .open_sync(&self.path, url)
             .map_err(|why| Error::Cache(why, "opening for write"))?;
         writer
             .write_all(text.as_bytes())
@@ -46,22 +69,30 @@ where
     }
 
     fn read(&self, meta: &Metadata) -> Result<String> {
-        cacache::read_hash_sync(&*CACHE, &meta.integrity)
+        cacache::read_hash_sync(&self.path, &meta.integrity)
             .map_err(|why| Error::Cache(why, "reading value"))
             .and_then(|raw| String::from_utf8(raw).map_err(Error::DecodingUtf8))
     }
 
     fn meta(&self, url: &str) -> Result<Option<Metadata>> {
-        cacache::metadata_sync(&*CACHE, url).map_err(|why| Error::Cache(why, "reading metadata"))
+        cacache::metadata_sync(&self.path, url).map_err(|why| Error::Cache(why, "reading metadata"))
     }
 
     fn clear(&self) -> Result<()> {
-        cacache::clear_sync(&*CACHE).map_err(|why| Error::Cache(why, "clearing"))
+        cacache::clear_sync(&self.path).map_err(|why| Error::Cache(why, "clearing"))
     }
 
     fn list(&self) -> Result<Vec<Metadata>> {
-        cacache::list_sync(&*CACHE)
+        cacache::list_sync(&self.path)
             .map(|res| res.map_err(|why| Error::Cache(why, "listing")))
             .try_collect()
     }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn options(&self) -> &CacheOptions {
+        &self.options
+    }
 }
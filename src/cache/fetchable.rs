@@ -1,14 +1,22 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// How often a failed fetch returns the remembered error before retrying.
+pub const MAX_CACHED_FAILURES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(from = "T", untagged)]
 pub enum Fetchable<T> {
     /// The value does not exist, but can be fetched.
     None,
     /// The value has been fetched.
     Fetched(T),
+    /// Fetching the value failed.
+    ///
+    /// The error is returned `remaining` more times before fetching again.
+    /// This state is transient and serialized like [`Fetchable::None`].
+    Failed { reason: String, remaining: usize },
 }
 
 impl<T> Fetchable<T> {
@@ -16,29 +24,55 @@ impl<T> Fetchable<T> {
     where
         F: FnOnce() -> Result<T>,
     {
+        self.ensure_fetched(f)?;
+        // This is safe, since we've just fetched successfully
+        Ok(self.assume_fetched())
+    }
+
+    pub fn fetch_mut<F>(&mut self, f: F) -> Result<&mut T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        self.ensure_fetched(f)?;
+        // This is safe, since we've just fetched successfully
+        Ok(self.assume_fetched_mut())
+    }
+
+    /// The value, if it has been fetched.
+    pub fn get(&self) -> Option<&T> {
         match self {
-            Self::Fetched(ref value) => Ok(value),
-            Self::None => {
-                let value = f()?;
-                *self = Self::Fetched(value);
-                // This is safe, since we've just fetched successfully
-                Ok(self.assume_fetched())
-            }
+            Self::Fetched(value) => Some(value),
+            Self::None | Self::Failed { .. } => None,
         }
     }
 
-    pub fn fetch_mut<F>(&mut self, f: F) -> Result<&mut T>
+    /// Fetch the value using `f`, unless it has been fetched already.
+    ///
+    /// After a failure, the next [`MAX_CACHED_FAILURES`] calls return
+    /// the remembered error without calling `f`.
+    fn ensure_fetched<F>(&mut self, f: F) -> Result<()>
     where
         F: FnOnce() -> Result<T>,
     {
         match self {
-            Self::Fetched(ref mut value) => Ok(value),
-            Self::None => {
-                let value = f()?;
-                *self = Self::Fetched(value);
-                // This is safe, since we've just fetched successfully
-                Ok(self.assume_fetched_mut())
+            Self::Fetched(_) => Ok(()),
+            Self::Failed { reason, remaining } if *remaining > 0 => {
+                *remaining -= 1;
+                Err(Error::FetchFailedBefore(reason.clone()))
             }
+            Self::None | Self::Failed { .. } => match f() {
+                Ok(value) => {
+                    *self = Self::Fetched(value);
+                    Ok(())
+                }
+                Err(why) => {
+                    *self = Self::Failed {
+                        reason: why.to_string(),
+                        remaining: MAX_CACHED_FAILURES,
+                    };
+                    Err(why)
+                }
+            },
         }
     }
 
@@ -46,7 +80,7 @@ impl<T> Fetchable<T> {
     fn assume_fetched(&self) -> &T {
         match self {
             Self::Fetched(value) => value,
-            Self::None => panic!("Called .unwrap() on a Fetchable that is not fetched!"),
+            _ => panic!("Called .unwrap() on a Fetchable that is not fetched!"),
         }
     }
 
@@ -54,7 +88,19 @@ impl<T> Fetchable<T> {
     fn assume_fetched_mut(&mut self) -> &mut T {
         match self {
             Self::Fetched(value) => value,
-            Self::None => panic!("Called .unwrap() on a Fetchable that is not fetched!"),
+            _ => panic!("Called .unwrap() on a Fetchable that is not fetched!"),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Fetchable<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Fetched(value) => value.serialize(serializer),
+            Self::None | Self::Failed { .. } => serializer.serialize_unit(),
         }
     }
 }
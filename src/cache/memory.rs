@@ -3,7 +3,7 @@ use std::{collections::BTreeMap, sync::RwLock};
 use cacache::Metadata;
 use ssri::Integrity;
 
-use super::Cache;
+use super::{Cache, CacheOptions, Stats};
 
 use crate::{
     error::{Error, Result},
@@ -16,19 +16,33 @@ struct Entry {
     text: String,
 }
 
-pub struct DummyCache {
+/// A cache keeping all entries in memory.
+///
+/// Nothing is persisted, this is used for tests and `--cache-backend memory`.
+pub struct MemoryCache {
     /// The real, cacache-based implementation takes only immutable references
     /// and the API is adopted to handle that. Thus we'll have to do our
     /// own interior mutability here.
     ///
     /// This maps paths to entries.
     content: RwLock<BTreeMap<String, Entry>>,
+    stats: Stats,
+    options: CacheOptions,
 }
 
-impl Cache for DummyCache {
+impl MemoryCache {
+    /// Replace the default [`CacheOptions`].
+    pub fn with_options(self, options: CacheOptions) -> Self {
+        MemoryCache { options, ..self }
+    }
+}
+
+impl Cache for MemoryCache {
     fn init() -> Result<Self> {
-        Ok(DummyCache {
+        Ok(MemoryCache {
             content: RwLock::new(BTreeMap::new()),
+            stats: Stats::default(),
+            options: CacheOptions::default(),
         })
     }
 
@@ -78,6 +92,14 @@ impl Cache for DummyCache {
             .collect();
         Ok(list)
     }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn options(&self) -> &CacheOptions {
+        &self.options
+    }
 }
 
 fn path_from_key(key: &str) -> String {
@@ -28,34 +28,48 @@
 //! - `fetch` functions are generalized over web requests and cache loading.
 //! - `get` functions only operate on requests.
 //! - `load`, `update` functions only operate on the cache.
+use std::collections::BTreeSet;
+
 use ::cacache::Metadata;
 use chrono::{Duration, TimeZone};
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use reqwest::{StatusCode, Url};
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 use tracing::{info, warn};
 
 mod fetchable;
+mod memory;
+mod stats;
 #[cfg(test)]
 mod tests;
 
-#[cfg(not(test))]
+mod backend;
 mod cacache;
+
 #[cfg(not(test))]
-use self::cacache::Cacache as DefaultCache;
+pub use self::backend::Backend as DefaultCache;
 
 #[cfg(test)]
-mod dummy;
-#[cfg(test)]
-use self::dummy::DummyCache as DefaultCache;
+pub use self::memory::MemoryCache as DefaultCache;
 
-pub use fetchable::Fetchable;
+pub use self::{
+    backend::Backend,
+    cacache::Cacache,
+    fetchable::Fetchable,
+    memory::MemoryCache,
+    stats::{Stats, StatsSnapshot},
+};
 
 use crate::{
     error::{Error, Result, ResultExt},
-    request::{Api, DefaultApi, Headers, Response},
+    request::{Api, DefaultApi, Response},
+    TTL_NOT_FOUND,
 };
 
+/// Metadata stored with every cache entry.
+pub use crate::request::Headers;
+
 /// Returned by most functions in this module.
 type TextAndHeaders = (String, Headers);
 
@@ -64,20 +78,50 @@ lazy_static! {
     pub static ref API: DefaultApi = DefaultApi::create().expect("Failed to create API");
 }
 
+/// Where a fetched value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Read from the cache, no request was sent.
+    Cache,
+    /// A request was sent, even if it only revalidated the cached value.
+    Network,
+}
+
 /// Possible results from a cache load.
 #[derive(Debug, PartialEq)]
 enum CacheResult<T> {
     /// Missed, no entry exists.
     Miss,
     /// Entry exists, but exceeded it's local TTL.
-    Stale(Headers, Metadata),
+    Stale(Headers, Box<Metadata>),
     /// Entry exists and is fresh.
     Hit(T),
+    /// The resource did not exist when it was requested recently.
+    NotFound,
+}
+
+/// Settings of a [`Cache`] that don't depend on where entries are stored.
+#[derive(Debug, Clone)]
+pub struct CacheOptions {
+    /// Report fields of json responses that are not understood.
+    pub verbose: bool,
+    /// How long resources that were not found are remembered as missing.
+    pub not_found_ttl: Duration,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        CacheOptions {
+            verbose: false,
+            not_found_ttl: *TTL_NOT_FOUND,
+        }
+    }
 }
 
 /// Cache trait
 ///
-/// Generalized over the default Cacache and a DummyCache used for tests.
+/// Generalized over the on-disk Cacache and the in-memory MemoryCache.
 pub trait Cache
 where
     Self: Sized,
@@ -101,17 +145,25 @@ where
     fn clear(&self) -> Result<()>;
 
     /// List all cache entries.
+    #[allow(dead_code)]
     fn list(&self) -> Result<Vec<Metadata>>;
 
+    /// Counters of this cache's activity, updated by [`Cache::fetch`].
+    fn stats(&self) -> &Stats;
+
+    /// Settings this cache was created with.
+    fn options(&self) -> &CacheOptions;
+
     /// Wrapper around [`Cache::fetch`] for responses that contain json.
-    fn fetch_json<S, T>(&self, url: S, local_ttl: Duration) -> Result<T>
+    fn fetch_json<S, T>(&self, url: S, local_ttl: Duration, refresh: bool) -> Result<T>
     where
         S: AsRef<str>,
         T: DeserializeOwned,
     {
-        self.fetch(url, local_ttl, |text, _| {
+        let key = url.as_ref().to_owned();
+        self.fetch(url, local_ttl, refresh, |text, _, _| {
             // TODO: Check content header?
-            serde_json::from_str(&text).map_err(|why| Error::Deserializing(why, "fetching json"))
+            from_json_str(&key, &text, "fetching json", self.options().verbose)
         })
     }
 
@@ -119,10 +171,13 @@ where
     ///
     /// This is the preferred way to access the cache, as the requested value
     /// will be fetched from the inter-webs if the cache misses.
-    fn fetch<Map, S, T>(&self, url: S, local_ttl: Duration, map: Map) -> Result<T>
+    /// With `refresh`, cached values are ignored, but updated.
+    /// Stale values are used if the resource can't be requested at all.
+    /// The `map` is told whether the value was read from the cache.
+    fn fetch<Map, S, T>(&self, url: S, local_ttl: Duration, refresh: bool, map: Map) -> Result<T>
     where
         S: AsRef<str>,
-        Map: FnOnce(String, Headers) -> Result<T>,
+        Map: FnOnce(String, Headers, Source) -> Result<T>,
     {
         // Normalize the url at this point since we're using it
         // as the cache key
@@ -130,27 +185,54 @@ where
         let url = url.as_ref();
         info!("Fetching {:?}", url);
         // Try getting the value from cache, if that fails, query the web
-        let (text, headers) = match try_load_cache(self, url, local_ttl) {
+        let ((text, headers), source) = match try_load_cache(self, url, local_ttl, refresh) {
             Ok(CacheResult::Hit(text_and_headers)) => {
                 info!("Hit cache on {:?}", url);
-                text_and_headers
+                self.stats().hit();
+                (text_and_headers, Source::Cache)
+            }
+            Ok(CacheResult::NotFound) => {
+                info!("Hit cache on {:?}, which was not found recently", url);
+                self.stats().hit();
+                return Err(Error::NonSuccessStatusCode(
+                    url.to_owned(),
+                    StatusCode::NOT_FOUND,
+                ));
             }
             Ok(CacheResult::Miss) => {
                 info!("Missed cache on {:?}", url);
-                get_and_update_cache(self, url, None, None)?
+                self.stats().miss();
+                (
+                    get_and_update_cache(self, url, None, None)?,
+                    Source::Network,
+                )
             }
             Ok(CacheResult::Stale(old_headers, meta)) => {
-                info!("Stale cache on {:?}", url);
+                info!("Stale cache on {:?}, cached {}", url, relative_age(&meta));
+                self.stats().stale();
                 // The cache is stale but may still be valid
-                // Request the resource with set IF_NONE_MATCH tag and update
-                // the caches metadata or value
-                match get_and_update_cache(self, url, old_headers.etag, Some(meta)) {
-                    Ok(tah) => tah,
+                // Request the resource with set IF_NONE_MATCH and IF_MODIFIED_SINCE
+                // headers and update the caches metadata or value
+                match get_and_update_cache(self, url, Some(&old_headers), Some(*meta)) {
+                    Ok(tah) => (tah, Source::Network),
                     Err(why) => {
                         warn!("{}", why);
                         // Fetching and updating failed for some reason, retry
-                        // without the IF_NONE_MATCH tag and fail if unsuccessful
-                        get_and_update_cache(self, url, None, None)?
+                        // without the conditional headers
+                        match get_and_update_cache(self, url, None, None) {
+                            Ok(tah) => (tah, Source::Network),
+                            // The request could not be sent at all, likely due to
+                            // a missing network. The stale value is better than nothing
+                            Err(Error::Reqwest(why)) => match self.meta(url)? {
+                                Some(meta) => {
+                                    warn!("Using stale cache on {:?}: {}", url, why);
+                                    let text = self.read(&meta)?;
+                                    (to_text_and_headers(text, &meta.metadata)?, Source::Cache)
+                                }
+                                None => return Err(Error::Reqwest(why)),
+                            },
+                            Err(why) => return Err(why),
+                        }
                     }
                 }
             }
@@ -158,34 +240,99 @@ where
                 // Fetching from the cache failed for some reason, just
                 // request the resource and update the cache
                 warn!("{}", why);
-                get_and_update_cache(self, url, None, None)?
+                self.stats().miss();
+                (
+                    get_and_update_cache(self, url, None, None)?,
+                    Source::Network,
+                )
             }
         };
         // Apply the map and return the result
-        map(text, headers)
+        map(text, headers, source)
     }
 }
 
+/// Deserialize the json `text` received from `url`.
+///
+/// If `verbose`, fields that are not understood are reported, since
+/// they hint at changes of the API. They are ignored otherwise.
+pub(crate) fn from_json_str<T>(
+    url: &str,
+    text: &str,
+    context: &'static str,
+    verbose: bool,
+) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    if !verbose {
+        return serde_json::from_str(text).map_err(|why| Error::Deserializing(why, context));
+    }
+    let (value, unknown) =
+        from_json_str_with_unknown(text).map_err(|why| Error::Deserializing(why, context))?;
+    if !unknown.is_empty() {
+        warn!(
+            "Unknown fields in the response from {:?}: {}",
+            url,
+            unknown.iter().join(", ")
+        );
+    }
+    Ok(value)
+}
+
+/// Deserialize `text`, collecting the names of all fields that were ignored.
+fn from_json_str_with_unknown<T>(text: &str) -> serde_json::Result<(T, BTreeSet<String>)>
+where
+    T: DeserializeOwned,
+{
+    let mut unknown = BTreeSet::new();
+    let mut de = serde_json::Deserializer::from_str(text);
+    let value = serde_ignored::deserialize(&mut de, |path| {
+        // Report every field once, instead of once per list element
+        let name = match path {
+            serde_ignored::Path::Map { key, .. } => key,
+            other => other.to_string(),
+        };
+        unknown.insert(name);
+    })?;
+    de.end()?;
+    Ok((value, unknown))
+}
+
 /// Try loading the cache content.
 ///
 /// This can fail due to errors, but also exits with a [`CacheResult`].
+/// With `refresh`, this always misses.
 fn try_load_cache<C: Cache>(
     cache: &C,
     url: &str,
     local_ttl: Duration,
+    refresh: bool,
 ) -> Result<CacheResult<TextAndHeaders>> {
+    if refresh {
+        return Ok(CacheResult::Miss);
+    }
     // Try reading the cache's metadata
     match cache.meta(url)? {
+        Some(meta) if headers_from_metadata(&meta)?.not_found => {
+            // Only a marker for a missing resource exists
+            if is_fresh(&meta, &cache.options().not_found_ttl) {
+                Ok(CacheResult::NotFound)
+            } else {
+                Ok(CacheResult::Miss)
+            }
+        }
         Some(meta) => {
             // Metadata exists
             if is_fresh(&meta, &local_ttl) {
+                info!("Cached {:?} {}", url, relative_age(&meta));
                 // Fresh, try to fetch from cache
                 let text = cache.read(&meta)?;
                 to_text_and_headers(text, &meta.metadata).map(CacheResult::Hit)
             } else {
                 // Local check failed, but the value may still be valid
                 let old_headers = headers_from_metadata(&meta)?;
-                Ok(CacheResult::Stale(old_headers, meta))
+                Ok(CacheResult::Stale(old_headers, Box::new(meta)))
             }
         }
         None => {
@@ -199,20 +346,22 @@ fn try_load_cache<C: Cache>(
 ///
 /// This should only be called if the cache load already failed.
 ///
-/// If an optional `etag` is provided, add the If-None-Match header, and thus
-/// only get an update if the new ETAG differs from the given `etag`.
+/// If the `old` headers are provided, add the If-None-Match and
+/// If-Modified-Since headers, and thus only get an update if the
+/// resource changed since it was cached.
 fn get_and_update_cache<C: Cache>(
     cache: &C,
     url: &str,
-    etag: Option<String>,
+    old: Option<&Headers>,
     meta: Option<Metadata>,
 ) -> Result<TextAndHeaders> {
-    // Send request with optional ETag header
-    let resp = API.get(url, etag)?;
+    // Send request with optional conditional headers
+    let resp = API.get(url, old)?;
     info!("Request to {:?} returned {}", url, resp.status);
+    cache.stats().response(resp.body.len());
     match meta {
         Some(meta) if resp.status == StatusCode::NOT_MODIFIED => {
-            // If we received code 304 NOT MODIFIED (after adding the If-None-Match)
+            // If we received code 304 NOT MODIFIED (after adding the conditional headers)
             // our cache is actually fresh and it's timestamp should be updated
             touch_and_load_cache(cache, url, &meta, resp.headers)
         }
@@ -220,6 +369,15 @@ fn get_and_update_cache<C: Cache>(
             // Request returned successfully, now update the cache with that
             update_cache_from_response(cache, resp)
         }
+        _ if resp.status == StatusCode::NOT_FOUND => {
+            // Remember the missing resource for a while, to not ask again
+            let headers = Headers {
+                not_found: true,
+                ..resp.headers
+            };
+            cache.write(&headers, url, "").log_warn();
+            Err(Error::NonSuccessStatusCode(url.to_string(), resp.status))
+        }
         _ => {
             // Some error occured, just error out
             // TODO: Retrying would be an option
@@ -242,10 +400,14 @@ fn touch_and_load_cache<C: Cache>(
     cache: &C,
     url: &str,
     meta: &Metadata,
-    headers: Headers,
+    mut headers: Headers,
 ) -> Result<TextAndHeaders> {
     let raw = cache.read(meta)?;
-    let (text, _) = to_text_and_headers(raw, &meta.metadata)?;
+    let (text, old_headers) = to_text_and_headers(raw, &meta.metadata)?;
+    // Servers may omit the Last-Modified header on 304 responses
+    if headers.last_modified.is_none() {
+        headers.last_modified = old_headers.last_modified;
+    }
     // TODO: Update the timestamp in a smarter way..
     // Do not fall on errors, this doesn’t matter
     cache.write(&headers, url, &text).log_warn();
@@ -260,10 +422,35 @@ fn headers_from_metadata(meta: &Metadata) -> Result<Headers> {
 
 /// Compares metadata age and local TTL.
 fn is_fresh(meta: &Metadata, local_ttl: &Duration) -> bool {
+    age(meta) < *local_ttl
+}
+
+/// Time since the cache entry was written.
+fn age(meta: &Metadata) -> Duration {
     let now = chrono::Utc::now();
-    let age_ms = meta.time;
-    let cache_age = chrono::Utc.timestamp((age_ms / 1000) as i64, (age_ms % 1000) as u32);
-    now - cache_age < *local_ttl
+    let cache_age = chrono::Utc.timestamp_millis_opt(meta.time as i64).unwrap();
+    now - cache_age
+}
+
+/// Render the age of a cache entry in human terms, i.e. `3 hours ago`.
+pub fn relative_age(meta: &Metadata) -> String {
+    format_age(age(meta))
+}
+
+fn format_age(age: Duration) -> String {
+    let plural = |amount: i64, unit: &str| match amount {
+        1 => format!("1 {} ago", unit),
+        amount => format!("{} {}s ago", amount, unit),
+    };
+    if age < Duration::minutes(1) {
+        String::from("just now")
+    } else if age < Duration::hours(1) {
+        plural(age.num_minutes(), "minute")
+    } else if age < Duration::days(1) {
+        plural(age.num_hours(), "hour")
+    } else {
+        plural(age.num_days(), "day")
+    }
 }
 
 /// Helper to convert raw text and serialized json to [`TextAndHeaders`].
@@ -0,0 +1,78 @@
+use std::{
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+/// Counters of a cache's activity, printed with `--stats`.
+#[derive(Debug, Default)]
+pub struct Stats {
+    requests: AtomicUsize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    stale: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+/// The [`Stats`] at a single point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StatsSnapshot {
+    /// Requests sent, including revalidations of stale entries.
+    pub requests: usize,
+    /// Fetches answered by the cache without any request.
+    pub hits: usize,
+    /// Fetches without a usable cache entry.
+    pub misses: usize,
+    /// Fetches of entries that exceeded their TTL and were revalidated.
+    pub stale: usize,
+    /// Bytes received in response bodies.
+    pub bytes: usize,
+}
+
+impl Stats {
+    pub(super) fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn stale(&self) {
+        self.stale.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a request whose response body had `bytes` bytes.
+    pub(super) fn response(&self, bytes: usize) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl StatsSnapshot {
+    /// Print these stats to stderr, as a single line of json if `json` is set.
+    pub fn print(&self, json: bool) -> Result<()> {
+        let line = if json {
+            serde_json::to_string(self).map_err(|why| Error::Serializing(why, "writing stats"))?
+        } else {
+            format!(
+                "stats: {} requests, {} cache hits, {} misses, {} stale, {} bytes fetched",
+                self.requests, self.hits, self.misses, self.stale, self.bytes
+            )
+        };
+        writeln!(std::io::stderr(), "{}", line).map_err(|why| Error::Io(why, "printing stats"))
+    }
+}
@@ -1,8 +1,10 @@
-use std::thread;
+use std::{fs, thread};
 
 use lazy_static::lazy_static;
 use pretty_assertions::assert_eq;
 
+use crate::config::args::CacheBackend;
+
 use super::*;
 
 lazy_static! {
@@ -12,11 +14,11 @@ lazy_static! {
 fn print_cache_list(header: &'static str) -> Result<()> {
     println!("\n+--- Cache {} ---", header);
     CACHE.list()?.iter().for_each(|meta| {
-        let age_ms = meta.time;
-        let cache_age = chrono::Utc.timestamp((age_ms / 1000) as i64, (age_ms % 1000) as u32);
         println!(
             "| - {}\n|   SIZE: {}\n|   AGE: {}",
-            meta.key, meta.size, cache_age
+            meta.key,
+            meta.size,
+            relative_age(meta)
         )
     });
     println!("+{}", "-".repeat(header.len() + 14));
@@ -25,7 +27,7 @@ fn print_cache_list(header: &'static str) -> Result<()> {
 
 #[test]
 fn test_cache_is_empty() {
-    let read = try_load_cache(&*CACHE, "test cache entry", Duration::max_value()).unwrap();
+    let read = try_load_cache(&*CACHE, "test cache entry", Duration::max_value(), false).unwrap();
     print_cache_list("Cache").unwrap();
     assert_eq!(read, CacheResult::Miss);
 }
@@ -35,14 +37,14 @@ fn basic_caching() {
     let url = "http://invalid.local/test";
     API.register_single(url, "It works", Some("static"));
     // Cache is empty
-    let val = try_load_cache(&*CACHE, url, Duration::max_value()).unwrap();
+    let val = try_load_cache(&*CACHE, url, Duration::max_value(), false).unwrap();
     print_cache_list("After first read").unwrap();
     assert_eq!(val, CacheResult::Miss);
     // Populate the cache with the first request
-    let val = CACHE.fetch(url, *TTL, |txt, _| Ok(txt)).unwrap();
+    let val = CACHE.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).unwrap();
     assert_eq!(val, "It works",);
     // The cache should now be hit
-    let val = dbg!(try_load_cache(&*CACHE, url, Duration::max_value()).unwrap());
+    let val = dbg!(try_load_cache(&*CACHE, url, Duration::max_value(), false).unwrap());
     print_cache_list("After second read").unwrap();
     assert_eq!(
         val,
@@ -50,14 +52,239 @@ fn basic_caching() {
             "It works".into(),
             Headers {
                 etag: Some("static".into()),
+                last_modified: None,
                 this_page: Some(1),
                 next_page: None,
                 last_page: Some(1),
+                not_found: false,
             }
         ))
     );
     // Let's fake a stale entry
     thread::sleep(std::time::Duration::from_secs(1));
-    let val = try_load_cache(&*CACHE, url, Duration::zero()).unwrap();
+    let val = try_load_cache(&*CACHE, url, Duration::zero(), false).unwrap();
     assert!(matches!(val, CacheResult::Stale(_, _)));
 }
+
+#[test]
+fn refreshing_misses_the_cache() {
+    let url = "http://invalid.local/refresh";
+    API.register_single(url, "Fresh", None);
+    CACHE.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).unwrap();
+    let val = try_load_cache(&*CACHE, url, Duration::max_value(), true).unwrap();
+    assert_eq!(val, CacheResult::Miss);
+}
+
+#[test]
+fn failed_fetchables_are_not_retried_immediately() {
+    let mut fetchable: Fetchable<usize> = Fetchable::None;
+    let mut calls = 0;
+    let mut fetch = |fetchable: &mut Fetchable<usize>, result: Result<usize>| {
+        fetchable
+            .fetch(|| {
+                calls += 1;
+                result
+            })
+            .copied()
+    };
+    assert!(fetch(&mut fetchable, Err(Error::InvalidDateInArgs)).is_err());
+    for _ in 0..fetchable::MAX_CACHED_FAILURES {
+        let why = fetch(&mut fetchable, Ok(1)).unwrap_err();
+        assert!(matches!(why, Error::FetchFailedBefore(_)));
+    }
+    assert_eq!(fetch(&mut fetchable, Ok(1)).unwrap(), 1);
+    assert_eq!(fetch(&mut fetchable, Ok(2)).unwrap(), 1);
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn failed_fetchables_are_serialized_like_none() {
+    let failed: Fetchable<usize> = Fetchable::Failed {
+        reason: String::from("broken"),
+        remaining: 1,
+    };
+    assert_eq!(serde_json::to_string(&failed).unwrap(), "null");
+    assert_eq!(serde_json::to_string(&Fetchable::Fetched(1)).unwrap(), "1");
+}
+
+#[test]
+fn it_formats_ages_in_human_terms() {
+    assert_eq!(format_age(Duration::seconds(5)), "just now");
+    assert_eq!(format_age(Duration::minutes(1)), "1 minute ago");
+    assert_eq!(format_age(Duration::minutes(42)), "42 minutes ago");
+    assert_eq!(format_age(Duration::hours(3)), "3 hours ago");
+    assert_eq!(format_age(Duration::days(1)), "1 day ago");
+    assert_eq!(format_age(Duration::days(12)), "12 days ago");
+}
+
+#[test]
+fn dummy_api_answers_if_modified_since() {
+    let url = "http://invalid.local/last-modified";
+    let modified = chrono::Utc.with_ymd_and_hms(2021, 11, 3, 12, 0, 0).unwrap();
+    API.register_with_last_modified(url, "Dated", modified);
+    let resp = API.get(url, None).unwrap();
+    assert_eq!(resp.status, StatusCode::OK);
+    let headers = resp.headers;
+    assert_eq!(
+        headers.last_modified.as_deref(),
+        Some("Wed, 03 Nov 2021 12:00:00 GMT")
+    );
+    let resp = API.get(url, Some(&headers)).unwrap();
+    assert_eq!(resp.status, StatusCode::NOT_MODIFIED);
+    let older = Headers {
+        last_modified: Some("Tue, 02 Nov 2021 12:00:00 GMT".into()),
+        ..headers
+    };
+    let resp = API.get(url, Some(&older)).unwrap();
+    assert_eq!(resp.status, StatusCode::OK);
+}
+
+#[test]
+fn stale_entries_are_revalidated_with_last_modified() {
+    let url = "http://invalid.local/revalidate";
+    let modified = chrono::Utc.with_ymd_and_hms(2021, 11, 3, 12, 0, 0).unwrap();
+    API.register_with_last_modified(url, "Unchanged", modified);
+    CACHE.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).unwrap();
+    let (text, headers) = CACHE
+        .fetch(url, Duration::zero(), false, |txt, headers, _| {
+            Ok((txt, headers))
+        })
+        .unwrap();
+    assert_eq!(text, "Unchanged");
+    assert_eq!(
+        headers.last_modified.as_deref(),
+        Some("Wed, 03 Nov 2021 12:00:00 GMT")
+    );
+    assert_eq!(API.request_count(url), 2);
+}
+
+#[test]
+fn stale_entries_are_used_without_network() {
+    let url = "http://invalid.local/offline";
+    API.register_single(url, "Stale", Some("static"));
+    CACHE.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).unwrap();
+    API.make_unreachable(url);
+    let (text, source) = CACHE
+        .fetch(url, Duration::zero(), false, |txt, _, source| {
+            Ok((txt, source))
+        })
+        .unwrap();
+    assert_eq!(text, "Stale");
+    assert_eq!(source, Source::Cache);
+    // Both the conditional and the plain request were tried
+    assert_eq!(API.request_count(url), 3);
+}
+
+#[test]
+fn fetches_report_their_source() {
+    let url = "http://invalid.local/source";
+    API.register_single(url, "Somewhere", None);
+    let source = |ttl| {
+        CACHE
+            .fetch(url, ttl, false, |_, _, source| Ok(source))
+            .unwrap()
+    };
+    assert_eq!(source(*TTL), Source::Network);
+    assert_eq!(source(*TTL), Source::Cache);
+    assert_eq!(source(Duration::zero()), Source::Network);
+}
+
+#[test]
+fn fetches_are_counted() {
+    let url = "http://invalid.local/counted";
+    API.register_single(url, "Counted", None);
+    let cache = MemoryCache::init().unwrap();
+    cache.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).unwrap();
+    cache.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).unwrap();
+    assert_eq!(
+        cache.stats().snapshot(),
+        StatsSnapshot {
+            requests: 1,
+            hits: 1,
+            misses: 1,
+            stale: 0,
+            bytes: "Counted".len(),
+        }
+    );
+}
+
+#[test]
+fn unknown_fields_are_collected_once() {
+    let text = r#"[
+        { "id": 1, "name": "A", "city": "", "address": "", "phone": "1" },
+        { "id": 2, "name": "B", "city": "", "address": "", "phone": "2", "fax": "3" }
+    ]"#;
+    let (canteens, unknown) =
+        from_json_str_with_unknown::<Vec<crate::canteen::Canteen>>(text).unwrap();
+    assert_eq!(canteens.len(), 2);
+    let unknown: Vec<_> = unknown.into_iter().collect();
+    assert_eq!(unknown, vec![String::from("fax"), String::from("phone")]);
+}
+
+#[test]
+fn missing_resources_are_remembered() {
+    let url = "http://invalid.local/missing";
+    API.register_not_found(url);
+    let fetch = || CACHE.fetch(url, *TTL, false, |txt, _, _| Ok(txt));
+    for _ in 0..3 {
+        assert!(matches!(
+            fetch(),
+            Err(Error::NonSuccessStatusCode(_, StatusCode::NOT_FOUND))
+        ));
+    }
+    assert_eq!(API.request_count(url), 1);
+    // Refreshing asks again
+    assert!(CACHE.fetch(url, *TTL, true, |txt, _, _| Ok(txt)).is_err());
+    assert_eq!(API.request_count(url), 2);
+}
+
+#[test]
+fn missing_resources_respect_the_not_found_ttl() {
+    let url = "http://invalid.local/missing-briefly";
+    API.register_not_found(url);
+    let cache = MemoryCache::init().unwrap().with_options(CacheOptions {
+        not_found_ttl: Duration::zero(),
+        ..CacheOptions::default()
+    });
+    for _ in 0..2 {
+        assert!(cache.fetch(url, *TTL, false, |txt, _, _| Ok(txt)).is_err());
+    }
+    assert_eq!(API.request_count(url), 2);
+}
+
+fn options() -> CacheOptions {
+    CacheOptions {
+        verbose: true,
+        not_found_ttl: Duration::minutes(1),
+    }
+}
+
+#[test]
+fn it_creates_the_memory_backend() {
+    let dir = std::env::temp_dir().join(format!("mensa-backend-mem-{}", std::process::id()));
+    let cache = Backend::new(CacheBackend::Memory, dir.clone(), options()).unwrap();
+    assert!(matches!(cache, Backend::Memory(_)));
+    assert!(cache.options().verbose);
+    cache
+        .write(&Headers::default(), "http://invalid.local/", "text")
+        .unwrap();
+    let meta = cache.meta("http://invalid.local/").unwrap().unwrap();
+    assert_eq!(cache.read(&meta).unwrap(), "text");
+    // Nothing is written to disk
+    assert!(!dir.exists());
+}
+
+#[test]
+fn it_creates_the_disk_backend() {
+    let dir = std::env::temp_dir().join(format!("mensa-backend-disk-{}", std::process::id()));
+    let cache = Backend::new(CacheBackend::Disk, dir.join("nested"), options()).unwrap();
+    assert!(matches!(cache, Backend::Disk(_)));
+    assert_eq!(cache.options().not_found_ttl, Duration::minutes(1));
+    cache
+        .write(&Headers::default(), "http://invalid.local/", "text")
+        .unwrap();
+    let meta = cache.meta("http://invalid.local/").unwrap().unwrap();
+    assert_eq!(cache.read(&meta).unwrap(), "text");
+    assert_eq!(cache.list().unwrap().len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
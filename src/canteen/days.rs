@@ -0,0 +1,143 @@
+//! Serving days of a canteen, see `mensa days`.
+use std::io::{self, Write};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use itertools::Itertools;
+
+use crate::{
+    context::Context,
+    error::{Error, Result},
+};
+
+use super::{fetch_days, Canteen, CanteenId};
+
+/// Number of meals named in the summary of a calendar event.
+const TOP_MEALS: usize = 3;
+
+/// Maximum length of a content line in octets, see RFC 5545 3.1.
+const MAX_LINE_LEN: usize = 75;
+
+/// The meals of an open day as pairs of category and name.
+pub(super) type DayMeals = (NaiveDate, Vec<(String, String)>);
+
+impl Canteen {
+    /// Print the days at which this canteen is open.
+    ///
+    /// With `ical`, an iCalendar with an all-day event per open day
+    /// is printed instead, closed days are skipped.
+    pub fn print_days(&mut self, ctx: &Context<'_>, ical: bool) -> Result<()> {
+        let days = fetch_days(ctx, self.id)?;
+        if !ical {
+            for day in days {
+                let state = if day.closed {
+                    color!("closed"; dimmed)
+                } else {
+                    color!("open"; bright_green)
+                };
+                try_println!("{} {}", color!(day.date; bold), state)?;
+            }
+            return Ok(());
+        }
+        let mut open = vec![];
+        for day in days.iter().filter(|day| !day.closed) {
+            let meals = match self.meals_at_mut(ctx, &day.date)? {
+                Some(meals) => meals
+                    .iter_mut()
+                    .map(|meal| {
+                        let meta = meal.meta()?;
+                        Ok((meta.category.clone(), meta.name.clone()))
+                    })
+                    .collect::<Result<_>>()?,
+                None => vec![],
+            };
+            open.push((day.date, meals));
+        }
+        let name = self.name(ctx)?.clone();
+        let address = self.address(ctx)?.clone();
+        let calendar = calendar(self.id, &name, &address, &open, Utc::now());
+        // Content lines already end with CRLF
+        io::stdout()
+            .write_all(calendar.as_bytes())
+            .map_err(|why| Error::Io(why, "printing calendar"))
+    }
+}
+
+/// Build an iCalendar with an all-day event for every day in `days`.
+///
+/// The summary names the first few meals, the description lists all of them.
+pub(super) fn calendar(
+    id: CanteenId,
+    name: &str,
+    address: &str,
+    days: &[DayMeals],
+    stamp: DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//tammena//mensa//EN"),
+        String::from("CALSCALE:GREGORIAN"),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for (date, meals) in days {
+        let summary = match meals.len() {
+            0 => name.to_owned(),
+            len => {
+                let top = meals
+                    .iter()
+                    .take(TOP_MEALS)
+                    .map(|(_, name)| name)
+                    .join(", ");
+                let more = if len > TOP_MEALS { ", …" } else { "" };
+                format!("{}: {}{}", name, top, more)
+            }
+        };
+        let description = meals
+            .iter()
+            .map(|(category, name)| format!("{}: {}", category, name))
+            .join("\n");
+        lines.extend([
+            String::from("BEGIN:VEVENT"),
+            format!("UID:{}-{}@mensa", id, date.format("%Y%m%d")),
+            format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")),
+            format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (*date + Duration::days(1)).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape(&summary)),
+            format!("DESCRIPTION:{}", escape(&description)),
+            format!("LOCATION:{}", escape(address)),
+            String::from("TRANSP:TRANSPARENT"),
+            String::from("END:VEVENT"),
+        ]);
+    }
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Escape text values, see RFC 5545 3.3.11.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than [`MAX_LINE_LEN`] octets.
+///
+/// Lines are never split inside a multi-byte character.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the line length
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
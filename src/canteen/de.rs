@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::NaiveDate;
 use serde::Deserialize;
 
@@ -26,6 +28,7 @@ impl From<CanteenDeserialized> for super::Canteen {
                 coordinates: raw.coordinates,
             }),
             meals: Fetchable::None,
+            meal_sources: HashMap::new(),
         }
     }
 }
@@ -42,7 +45,7 @@ impl TryFrom<DayDeserialized> for super::Day {
     fn try_from(raw: DayDeserialized) -> Result<Self, Self::Error> {
         Ok(Self {
             date: NaiveDate::parse_from_str(&raw.date, "%Y-%m-%d").map_err(Error::InvalidDate)?,
-            _closed: raw.closed,
+            closed: raw.closed,
         })
     }
 }
@@ -1,27 +1,39 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    io::{self, Write},
+    time::Duration as StdDuration,
+};
 
 use chrono::NaiveDate;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 
+mod days;
 mod de;
 mod ser;
 #[cfg(test)]
 mod tests;
 
 use crate::{
-    cache::{Cache, Fetchable, CACHE},
+    cache::{Cache, Fetchable, Source},
     config::{
-        args::{CloseCommand, Command, GeoCommand},
-        CONF,
+        args::{
+            slugify, CanteenRef, CanteensCommand, Command, DistanceUnit, GeoCommand, DEFAULT_RADIUS,
+        },
+        ConfigFile,
     },
-    error::Result,
-    geoip, get_sane_terminal_dimensions,
+    context::Context,
+    error::{Error, Result, ResultExt},
+    geoip, get_sane_terminal_dimensions, hyperlink,
+    json_array::JsonArray,
     meal::Meal,
     pagination::PaginatedList,
-    print_json, OPEN_MENSA_API, TTL_CANTEENS, TTL_MEALS,
+    parallel, print_json, print_json_line,
+    template::Template,
+    JSON_VERSION, OPEN_MENSA_API, OPEN_MENSA_WEB, TTL_CANTEENS,
 };
 
 use self::ser::CanteenCompleteWithoutMeals;
@@ -40,10 +52,12 @@ pub struct Canteen {
     id: CanteenId,
     #[serde(flatten)]
     meta: Fetchable<Meta>,
-    /// A map from dates to lists of meals.
+    /// A map from the dates of the published schedule to lists of meals.
     ///
     /// The list of dates itself is fetchable as are the lists of meals.
-    meals: Fetchable<HashMap<NaiveDate, Fetchable<Vec<Meal>>>>,
+    meals: Fetchable<HashMap<NaiveDate, Served>>,
+    /// Where the fetched lists of meals came from.
+    meal_sources: HashMap<NaiveDate, Source>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -58,141 +72,697 @@ pub struct Meta {
 #[serde(try_from = "de::DayDeserialized")]
 pub struct Day {
     date: NaiveDate,
-    #[serde(rename = "closed")]
-    _closed: bool,
+    closed: bool,
+}
+
+/// A day in the published schedule of a canteen.
+#[derive(Debug, Clone, PartialEq)]
+enum Served {
+    /// The canteen is closed that day.
+    Closed,
+    /// The canteen is open, the meals are fetched on demand.
+    Open(Fetchable<Vec<Meal>>),
 }
 
 impl Meta {
-    pub fn fetch(id: CanteenId) -> Result<Self> {
+    pub fn fetch(ctx: &Context<'_>, id: CanteenId) -> Result<Self> {
         let url = format!("{}/canteens/{}", OPEN_MENSA_API, id);
-        CACHE.fetch_json(url, *TTL_CANTEENS)
+        ctx.cache
+            .fetch_json(url, *TTL_CANTEENS, ctx.conf.args.refresh)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn city(&self) -> &str {
+        &self.city
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Latitude and longitude of the canteen, if known.
+    pub fn coordinates(&self) -> Option<(f32, f32)> {
+        self.coordinates.map(|[lat, long]| (lat, long))
     }
 }
 
 impl Canteen {
+    /// Placeholders available in `--canteen-format`.
+    pub const PLACEHOLDERS: &'static [&'static str] =
+        &["id", "name", "city", "address", "url", "distance"];
+
     /// Infer canteens from the config.
     ///
     /// # Command
     /// - Meals:
-    ///   - Close: Canteens close to the current location
-    ///   - Else: Canteen given by id
+    ///   - Close, or a position without `--id`: Canteens close to it
+    ///   - Else: Canteen given by id or slug, or picked by the user
+    ///     if `--interactive` is given and no id is known.
+    ///     With `--save-default`, the id is stored in the config.
+    /// - Tags, Days: Canteen given by id or slug, like for meals
+    /// - Canteens: Canteens given by `--ids` and `--id-range`, or all
+    ///   canteens with `--all`, or canteens close to the current location
+    /// - Compare, Open: Canteens close to the current location
     /// - Else: Panic!
-    pub fn infer() -> Result<Vec<Self>> {
-        match CONF.cmd() {
-            Command::Meals(cmd) => match cmd.close {
-                Some(CloseCommand::Close(ref geo)) => Self::fetch_for_geo(geo, false),
-                None => {
-                    let id = CONF.canteen_id()?;
-                    Ok(vec![id.into()])
+    pub fn infer(ctx: &Context<'_>) -> Result<Vec<Self>> {
+        let conf = ctx.conf;
+        match conf.cmd() {
+            Command::Meals(cmd) => match cmd.geo() {
+                Some(geo) => {
+                    let mut canteens = Self::fetch_for_geo(ctx, geo, false)?;
+                    if let Some(n) = cmd.nearest {
+                        Self::keep_nearest(ctx, &mut canteens, geoip::infer(ctx)?, n)?;
+                    }
+                    if cmd.first_open {
+                        let origin = geoip::infer(ctx)?;
+                        let date = conf.date();
+                        Self::first_open(ctx, canteens, origin, date).map(|canteen| vec![canteen])
+                    } else {
+                        Ok(canteens)
+                    }
                 }
+                None => Self::configured(ctx).map(|canteen| vec![canteen]),
+            },
+            Command::Tags(_) | Command::Days(_) => {
+                Self::configured(ctx).map(|canteen| vec![canteen])
+            }
+            Command::Canteens(cmd) => match cmd.requested_ids() {
+                Some(ids) => Self::fetch_by_ids(ctx, ids),
+                None => Self::fetch_for_geo(ctx, &cmd.geo, cmd.all),
             },
-            Command::Canteens(cmd) => Self::fetch_for_geo(&cmd.geo, cmd.all),
-            Command::Tags => unreachable!("BUG: This is not relevant here"),
+            Command::Compare(cmd) => Self::fetch_for_geo(ctx, &cmd.geo, false),
+            Command::Open(cmd) => Self::fetch_for_geo(ctx, &cmd.geo, false),
+            Command::Config(_) => {
+                unreachable!("BUG: This is not relevant here")
+            }
+        }
+    }
+
+    /// The canteen given by id, or picked by the user if `--interactive`
+    /// is given and no id is known.
+    ///
+    /// With `--save-default`, the id is stored in the config.
+    fn configured(ctx: &Context<'_>) -> Result<Self> {
+        let conf = ctx.conf;
+        let canteen = match conf.canteen_id() {
+            Err(Error::CanteenIdMissing) if conf.args.interactive && is_tty() => {
+                Self::pick_interactively(ctx)?
+            }
+            id => Self::resolve(ctx, id?)?,
+        };
+        if conf.args.save_default {
+            ConfigFile::save_default_canteen_id(conf, canteen.id)?;
         }
+        Ok(canteen)
     }
 
-    pub fn print(&mut self) -> Result<()> {
+    /// The canteen referenced by `id`.
+    ///
+    /// Slugs are looked up in the list of all canteens.
+    fn resolve(ctx: &Context<'_>, id: CanteenRef) -> Result<Self> {
+        let slug = match id {
+            CanteenRef::Id(id) => return Ok(id.into()),
+            CanteenRef::Slug(slug) => slug,
+        };
+        let url = format!("{}/canteens", OPEN_MENSA_API);
+        let all: Vec<Self> =
+            PaginatedList::new(ctx.cache, url, *TTL_CANTEENS, ctx.conf.args.refresh).consume()?;
+        let mut matching = vec![];
+        for mut canteen in all {
+            if slugify(canteen.name(ctx)?) == slug {
+                matching.push(canteen);
+            }
+        }
+        match matching.len() {
+            0 => Err(Error::UnknownCanteenSlug(slug)),
+            1 => Ok(matching.remove(0)),
+            _ => {
+                let ids = matching.iter().map(Self::id).collect();
+                Err(Error::AmbiguousCanteenSlug(slug, ids))
+            }
+        }
+    }
+
+    /// Print this canteen.
+    ///
+    /// If an `origin` is given, the distance to it is shown in `unit`,
+    /// or `distance: unknown` if the canteen has no coordinates.
+    pub fn print(
+        &mut self,
+        ctx: &Context<'_>,
+        origin: Option<(f32, f32)>,
+        unit: DistanceUnit,
+    ) -> Result<()> {
         let (width, _) = get_sane_terminal_dimensions();
         let address = textwrap::fill(
-            self.address()?,
+            self.address(ctx)?,
             textwrap::Options::new(width)
                 .initial_indent(ADRESS_INDENT)
                 .subsequent_indent(ADRESS_INDENT),
         );
+        let url = format!("{}/c/{}", OPEN_MENSA_WEB, self.id);
+        let distance = match origin {
+            Some(origin) => match self.distance_to(ctx, origin)? {
+                Some(km) => format!(" {:.1}{}", unit.convert_km(km), unit.symbol()),
+                None => String::from(" distance: unknown"),
+            },
+            None => String::new(),
+        };
         try_println!(
-            "{} {}\n{}",
+            "{} {}{}\n{}",
             color!(format!("{:>4}", self.id); bold, bright_yellow),
-            color!(self.meta()?.name; bold),
-            color!(address; bright_black),
+            hyperlink(&url, color!(self.meta(ctx)?.name; bold)),
+            color!(distance; muted),
+            color!(address; muted),
         )
     }
 
+    /// Render this canteen with a template given by `--canteen-format`.
+    ///
+    /// The distance is empty without an `origin`.
+    pub fn format(
+        &mut self,
+        ctx: &Context<'_>,
+        template: &Template,
+        origin: Option<(f32, f32)>,
+        unit: DistanceUnit,
+    ) -> Result<String> {
+        let distance = match origin {
+            Some(origin) => match self.distance_to(ctx, origin)? {
+                Some(km) => format!("{:.1}{}", unit.convert_km(km), unit.symbol()),
+                None => String::from("unknown"),
+            },
+            None => String::new(),
+        };
+        let id = self.id;
+        let meta = self.meta(ctx)?;
+        template.render(|placeholder| {
+            Ok(match placeholder {
+                "id" => id.to_string(),
+                "name" => meta.name.clone(),
+                "city" => meta.city.clone(),
+                "address" => meta.address.clone(),
+                "url" => format!("{}/c/{}", OPEN_MENSA_WEB, id),
+                "distance" => distance.clone(),
+                _ => unreachable!("BUG: Unknown placeholders are rejected while parsing"),
+            })
+        })
+    }
+
+    /// Distance in km between this canteen and `origin`.
+    ///
+    /// Returns `None` if the canteen has no coordinates.
+    pub fn distance_to(&mut self, ctx: &Context<'_>, origin: (f32, f32)) -> Result<Option<f32>> {
+        let coordinates = self.meta(ctx)?.coordinates;
+        Ok(coordinates.map(|[lat, long]| geoip::distance(origin, (lat, long))))
+    }
+
     pub fn id(&self) -> CanteenId {
         self.id
     }
 
-    pub fn address(&mut self) -> Result<&String> {
-        Ok(&self.meta()?.address)
+    /// The metadata of this canteen, if it is known without fetching.
+    ///
+    /// Canteens listed by the API, like those near a position, come with it.
+    pub fn fetched_meta(&self) -> Option<&Meta> {
+        self.meta.get()
     }
 
-    pub fn name(&mut self) -> Result<&String> {
-        Ok(&self.meta()?.name)
+    pub fn address(&mut self, ctx: &Context<'_>) -> Result<&String> {
+        Ok(&self.meta(ctx)?.address)
     }
 
-    pub fn complete_without_meals(&mut self) -> Result<CanteenCompleteWithoutMeals<'_>> {
+    pub fn name(&mut self, ctx: &Context<'_>) -> Result<&String> {
+        Ok(&self.meta(ctx)?.name)
+    }
+
+    pub fn complete_without_meals(
+        &mut self,
+        ctx: &Context<'_>,
+    ) -> Result<CanteenCompleteWithoutMeals<'_>> {
         Ok(CanteenCompleteWithoutMeals {
             id: self.id,
-            meta: self.meta()?,
+            meta: self.meta(ctx)?,
         })
     }
 
-    pub fn print_all(canteens: &mut [Self]) -> Result<()> {
-        if CONF.args.json {
-            Self::print_all_json(canteens)
+    /// Print all `canteens`, at most `--limit` of them.
+    pub fn print_all(ctx: &Context<'_>, canteens: &mut [Self]) -> Result<()> {
+        let limit = ctx
+            .conf
+            .args
+            .limit
+            .unwrap_or(usize::MAX)
+            .min(canteens.len());
+        let canteens = &mut canteens[..limit];
+        if ctx.conf.args.json {
+            Self::print_all_json(ctx, canteens)
         } else {
+            let (origin, unit) = match ctx.conf.cmd() {
+                Command::Canteens(cmd) if !cmd.all && cmd.requested_ids().is_none() => {
+                    (Some(geoip::infer(ctx)?), cmd.geo.unit)
+                }
+                Command::Open(cmd) => (Some(geoip::infer(ctx)?), cmd.geo.unit),
+                _ => (None, DistanceUnit::Km),
+            };
             for canteen in canteens {
-                try_println!()?;
-                canteen.print()?;
+                match ctx.conf.args.canteen_format {
+                    Some(ref format) => {
+                        try_println!("{}", canteen.format(ctx, &format.0, origin, unit)?)?
+                    }
+                    None => {
+                        try_println!()?;
+                        canteen.print(ctx, origin, unit)?;
+                    }
+                }
             }
             Ok(())
         }
     }
 
-    pub fn meals_at_mut(&mut self, date: &NaiveDate) -> Result<Option<&mut Vec<Meal>>> {
+    /// Fetch the meals of all `canteens` at `dates` in parallel.
+    ///
+    /// At most `--max-concurrency` requests are sent at once.
+    /// Failures are only logged, they'll resurface once the
+    /// meals are accessed via [`Self::meals_at_mut`].
+    pub fn prefetch_meals(ctx: &Context<'_>, canteens: &mut [Self], dates: &[NaiveDate]) {
+        let ids: Vec<_> = canteens
+            .iter()
+            .filter(|canteen| matches!(canteen.meals, Fetchable::None))
+            .map(|canteen| canteen.id)
+            .collect();
+        let fetched = parallel::map_bounded(ids, ctx.conf.args.max_concurrency, |id| {
+            let mut days = fetch_dates_for_canteen(ctx, id)?;
+            let mut sources = HashMap::new();
+            for date in dates {
+                if let Some(Served::Open(meals)) = days.get_mut(date) {
+                    let (fetched, source) = fetch_meals(ctx, id, date)?;
+                    *meals = Fetchable::Fetched(fetched);
+                    sources.insert(*date, source);
+                }
+            }
+            Result::Ok((id, days, sources))
+        });
+        for (id, days, sources) in fetched.into_iter().filter_map(ResultExt::log_warn) {
+            if let Some(canteen) = canteens.iter_mut().find(|canteen| canteen.id == id) {
+                canteen.meals = Fetchable::Fetched(days);
+                canteen.meal_sources.extend(sources);
+            }
+        }
+    }
+
+    /// Print all canteens which are open at the requested date.
+    ///
+    /// Canteens are sorted by distance, canteens without
+    /// coordinates are printed last.
+    pub fn print_open(ctx: &Context<'_>, canteens: &mut Vec<Self>) -> Result<()> {
+        let date = ctx.conf.date();
+        let mut open = vec![];
+        for canteen in canteens.drain(..) {
+            if canteen.is_open_at(ctx, date)? {
+                open.push(canteen);
+            }
+        }
+        Self::sort_by_distance(ctx, &mut open, geoip::infer(ctx)?)?;
+        *canteens = open;
+        Self::print_all(ctx, canteens)
+    }
+
+    /// The canteen nearest to `origin` which serves meals at `date`.
+    ///
+    /// Meals are only fetched until such a canteen is found.
+    pub fn first_open(
+        ctx: &Context<'_>,
+        mut canteens: Vec<Self>,
+        origin: (f32, f32),
+        date: &NaiveDate,
+    ) -> Result<Self> {
+        Self::sort_by_distance(ctx, &mut canteens, origin)?;
+        for mut canteen in canteens {
+            let serves_meals = canteen
+                .meals_at_mut(ctx, date)?
+                .map_or(false, |meals| !meals.is_empty());
+            if serves_meals {
+                return Ok(canteen);
+            }
+        }
+        Err(Error::NoOpenCanteenNearby(*date))
+    }
+
+    /// Keep only the `n` canteens nearest to `origin`, nearest first.
+    ///
+    /// Canteens without coordinates can't be ranked and are only
+    /// kept if there are fewer than `n` canteens with coordinates.
+    pub fn keep_nearest(
+        ctx: &Context<'_>,
+        canteens: &mut Vec<Self>,
+        origin: (f32, f32),
+        n: usize,
+    ) -> Result<()> {
+        Self::sort_by_distance(ctx, canteens, origin)?;
+        canteens.truncate(n);
+        Ok(())
+    }
+
+    /// Sort `canteens` by their distance to `origin`, nearest first.
+    ///
+    /// Canteens without coordinates are sorted last, keeping their order.
+    pub fn sort_by_distance(
+        ctx: &Context<'_>,
+        canteens: &mut [Self],
+        origin: (f32, f32),
+    ) -> Result<()> {
+        let mut distances = HashMap::new();
+        for canteen in canteens.iter_mut() {
+            distances.insert(canteen.id, canteen.distance_to(ctx, origin)?);
+        }
+        canteens.sort_by(|a, b| match (distances[&a.id], distances[&b.id]) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        Ok(())
+    }
+
+    /// The response of OpenMensa's meals endpoint for `date`, exactly as it was received.
+    ///
+    /// Nothing is deserialized, this is meant for debugging, see `--raw`.
+    pub fn raw_meals_at(&self, ctx: &Context<'_>, date: &NaiveDate) -> Result<String> {
+        let (ttl, refresh) = (ctx.conf.meals_ttl(self.id), ctx.conf.args.refresh);
+        ctx.cache
+            .fetch(meals_url(self.id, date), ttl, refresh, |text, _, _| {
+                Ok(text)
+            })
+    }
+
+    /// Is this canteen open at `date`?
+    ///
+    /// Dates without any information are considered closed.
+    pub fn is_open_at(&self, ctx: &Context<'_>, date: &NaiveDate) -> Result<bool> {
+        let days = fetch_days(ctx, self.id)?;
+        Ok(days.iter().any(|day| day.date == *date && !day.closed))
+    }
+
+    pub fn meals_at_mut(
+        &mut self,
+        ctx: &Context<'_>,
+        date: &NaiveDate,
+    ) -> Result<Option<&mut Vec<Meal>>> {
         let id = self.id();
-        let dates = self.meals.fetch_mut(|| fetch_dates_for_canteen(self.id))?;
+        let dates = self
+            .meals
+            .fetch_mut(|| fetch_dates_for_canteen(ctx, self.id))?;
         match dates.get_mut(date) {
-            Some(meals) => {
-                let meals = meals.fetch_mut(|| fetch_meals(id, date))?;
+            Some(Served::Open(meals)) => {
+                let sources = &mut self.meal_sources;
+                let meals = meals.fetch_mut(|| {
+                    let (meals, source) = fetch_meals(ctx, id, date)?;
+                    sources.insert(*date, source);
+                    Ok(meals)
+                })?;
                 Ok(Some(meals))
             }
-            None => Ok(None),
+            Some(Served::Closed) | None => Ok(None),
+        }
+    }
+
+    /// Is `date` part of the published schedule of this canteen?
+    ///
+    /// Distinguishes closed days from days without any information
+    /// if [`Self::meals_at_mut`] returns no meals.
+    pub fn is_scheduled_at(&mut self, ctx: &Context<'_>, date: &NaiveDate) -> Result<bool> {
+        let dates = self
+            .meals
+            .fetch_mut(|| fetch_dates_for_canteen(ctx, self.id))?;
+        Ok(dates.contains_key(date))
+    }
+
+    /// Where the meals at `date` came from, if they have been fetched.
+    pub fn meal_source(&self, date: &NaiveDate) -> Option<Source> {
+        self.meal_sources.get(date).copied()
+    }
+
+    /// Fetch the meals at `date` on a background thread, unless they're
+    /// known already, and wait at most `timeout` for them.
+    ///
+    /// Returns `false` if the meals did not arrive in time. The thread
+    /// is not waited for and updates the cache unless the program exits first.
+    pub fn fetch_meals_within(
+        &mut self,
+        ctx: &Context<'static>,
+        date: &NaiveDate,
+        timeout: StdDuration,
+    ) -> Result<bool> {
+        let pending = match &self.meals {
+            Fetchable::None => true,
+            Fetchable::Fetched(days) => {
+                matches!(days.get(date), Some(Served::Open(Fetchable::None)))
+            }
+            Fetchable::Failed { .. } => false,
+        };
+        if !pending {
+            return Ok(true);
+        }
+        let (ctx, id, date) = (*ctx, self.id, *date);
+        let fetched = parallel::within(timeout, move || {
+            let mut days = fetch_dates_for_canteen(&ctx, id)?;
+            let mut source = None;
+            if let Some(Served::Open(meals)) = days.get_mut(&date) {
+                let (fetched, fetched_from) = fetch_meals(&ctx, id, &date)?;
+                *meals = Fetchable::Fetched(fetched);
+                source = Some(fetched_from);
+            }
+            Result::Ok((days, source))
+        });
+        let (mut days, source) = match fetched {
+            Some(days) => days?,
+            None => {
+                warn!(
+                    "Fetching the meals of canteen {} timed out after {}s",
+                    id,
+                    timeout.as_secs()
+                );
+                return Ok(false);
+            }
+        };
+        match &mut self.meals {
+            Fetchable::Fetched(known) => {
+                if let Some(meals) = days.remove(&date) {
+                    known.insert(date, meals);
+                }
+            }
+            _ => self.meals = Fetchable::Fetched(days),
+        }
+        if let Some(source) = source {
+            self.meal_sources.insert(date, source);
         }
+        Ok(true)
     }
 
-    fn print_all_json(canteens: &mut [Self]) -> Result<()> {
+    fn print_all_json(ctx: &Context<'_>, canteens: &mut [Self]) -> Result<()> {
         let serializable: Vec<_> = canteens
             .iter_mut()
-            .map(|c| c.complete_without_meals())
+            .map(|c| c.complete_without_meals(ctx))
             .try_collect()?;
         print_json(&serializable)
     }
 
-    fn meta(&mut self) -> Result<&Meta> {
-        self.meta.fetch(|| Meta::fetch(self.id))
+    fn meta(&mut self, ctx: &Context<'_>) -> Result<&Meta> {
+        self.meta.fetch(|| Meta::fetch(ctx, self.id))
+    }
+
+    /// Let the user pick one of the canteens close to the current location.
+    ///
+    /// This should only be used if stdin and stdout are terminals.
+    fn pick_interactively(ctx: &Context<'_>) -> Result<Self> {
+        let geo = GeoCommand {
+            lat: None,
+            long: None,
+            city: None,
+            postal: None,
+            radius: DEFAULT_RADIUS,
+            unit: DistanceUnit::Km,
+        };
+        let mut canteens = Self::fetch_for_geo(ctx, &geo, false)?;
+        if canteens.is_empty() {
+            return Err(Error::NoCanteensNearby);
+        }
+        try_println!("No canteen id configured, please pick a canteen:\n")?;
+        for (idx, canteen) in canteens.iter_mut().enumerate() {
+            try_println!(
+                "{} {}",
+                color!(format!("{:>4})", idx + 1); bold, bright_yellow),
+                canteen.name(ctx)?,
+            )?;
+        }
+        loop {
+            print!("\nNumber [1-{}]: ", canteens.len());
+            io::stdout()
+                .flush()
+                .map_err(|why| Error::Io(why, "printing"))?;
+            let mut input = String::new();
+            let read = io::stdin()
+                .read_line(&mut input)
+                .map_err(|why| Error::Io(why, "reading selection"))?;
+            if read == 0 {
+                return Err(Error::NoCanteenSelected);
+            }
+            match input.trim().parse::<usize>() {
+                Ok(nr) if (1..=canteens.len()).contains(&nr) => {
+                    return Ok(canteens.swap_remove(nr - 1));
+                }
+                _ => try_println!("Please enter a number between 1 and {}", canteens.len())?,
+            }
+        }
+    }
+
+    /// Print canteens as newline delimited json while they're fetched.
+    pub fn print_ndjson(ctx: &Context<'_>, cmd: &CanteensCommand) -> Result<()> {
+        Self::stream(ctx, cmd, |canteen| print_json_line(&canteen))
+    }
+
+    /// Print canteens as a single json array while they're fetched.
+    pub fn print_json_array(ctx: &Context<'_>, cmd: &CanteensCommand) -> Result<()> {
+        let version = if ctx.conf.args.json_versioned {
+            Some(JSON_VERSION)
+        } else {
+            None
+        };
+        let mut array = JsonArray::open(io::stdout(), version)?;
+        Self::stream(ctx, cmd, |canteen| array.push(&canteen))?;
+        array.close()
+    }
+
+    /// Pass the requested canteens to `emit` one by one, as soon as they're fetched.
+    fn stream<F>(ctx: &Context<'_>, cmd: &CanteensCommand, mut emit: F) -> Result<()>
+    where
+        F: FnMut(CanteenCompleteWithoutMeals<'_>) -> Result<()>,
+    {
+        if let Some(ids) = cmd.requested_ids() {
+            let limit = ctx.conf.args.limit.unwrap_or(usize::MAX);
+            for id in ids.into_iter().take(limit) {
+                let mut canteen = Self::from(id);
+                emit(canteen.complete_without_meals(ctx)?)?;
+            }
+            return Ok(());
+        }
+        let url = Self::url_for_geo(ctx, &cmd.geo, cmd.all)?;
+        let pages =
+            PaginatedList::<Self, _>::new(ctx.cache, url, *TTL_CANTEENS, ctx.conf.args.refresh);
+        // Stop before fetching pages beyond the limit
+        let mut remaining = ctx.conf.args.limit.unwrap_or(usize::MAX);
+        for page in pages {
+            if remaining == 0 {
+                break;
+            }
+            for mut canteen in page?.into_iter().take(remaining) {
+                emit(canteen.complete_without_meals(ctx)?)?;
+                remaining -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch the canteens with the given `ids`, at most `--max-concurrency` at once.
+    ///
+    /// With `--best-effort`, canteens that could not be fetched are skipped.
+    fn fetch_by_ids(ctx: &Context<'_>, ids: Vec<CanteenId>) -> Result<Vec<Self>> {
+        let fetched = parallel::map_bounded(ids, ctx.conf.args.max_concurrency, |id| {
+            let meta = Meta::fetch(ctx, id)?;
+            Result::Ok(Self {
+                meta: Fetchable::Fetched(meta),
+                ..Self::from(id)
+            })
+        });
+        if ctx.conf.args.best_effort {
+            Ok(fetched
+                .into_iter()
+                .filter_map(ResultExt::log_warn)
+                .collect())
+        } else {
+            fetched.into_iter().collect()
+        }
     }
 
-    fn fetch_for_geo(geo: &GeoCommand, all: bool) -> Result<Vec<Self>> {
+    fn fetch_for_geo(ctx: &Context<'_>, geo: &GeoCommand, all: bool) -> Result<Vec<Self>> {
+        let url = Self::url_for_geo(ctx, geo, all)?;
+        let pages = PaginatedList::new(ctx.cache, url, *TTL_CANTEENS, ctx.conf.args.refresh);
+        if ctx.conf.args.best_effort {
+            let (canteens, errors) = pages.consume_partial();
+            for why in errors {
+                Err::<(), _>(why).log_warn();
+            }
+            Ok(canteens)
+        } else {
+            pages.consume()
+        }
+    }
+
+    /// Url listing all canteens, or the canteens close to the current location.
+    fn url_for_geo(ctx: &Context<'_>, geo: &GeoCommand, all: bool) -> Result<String> {
         let url = if all {
             info!("Fetching all canteens");
             format!("{}/canteens", OPEN_MENSA_API)
         } else {
-            let (lat, long) = geoip::infer()?;
+            let (lat, long) = geoip::infer(ctx)?;
             info!(
-                "Fetching canteens for lat: {}, long: {} with radius: {}",
-                lat, long, geo.radius
+                "Fetching canteens for lat: {}, long: {} with radius: {}{}",
+                lat,
+                long,
+                geo.radius,
+                geo.unit.symbol(),
             );
-            format!(
-                "{}/canteens?near[lat]={}&near[lng]={}&near[dist]={}",
-                OPEN_MENSA_API, lat, long, geo.radius,
-            )
+            near_url(lat, long, geo.unit.to_km(geo.radius))
         };
-        PaginatedList::new(url, *TTL_CANTEENS).consume()
+        Ok(url)
     }
 }
 
-fn fetch_dates_for_canteen(id: CanteenId) -> Result<HashMap<NaiveDate, Fetchable<Vec<Meal>>>> {
+/// Url listing all canteens within `radius` km.
+pub(crate) fn near_url(lat: f32, long: f32, radius: f64) -> String {
+    format!(
+        "{}/canteens?near[lat]={}&near[lng]={}&near[dist]={}",
+        OPEN_MENSA_API, lat, long, radius,
+    )
+}
+
+/// Url listing the meals of canteen `id` at `date`.
+pub(crate) fn meals_url(id: CanteenId, date: &NaiveDate) -> String {
+    format!("{}/canteens/{}/days/{}/meals", OPEN_MENSA_API, id, date)
+}
+
+/// Are both stdin and stdout attached to a terminal?
+fn is_tty() -> bool {
+    atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout)
+}
+
+fn fetch_days(ctx: &Context<'_>, id: CanteenId) -> Result<Vec<Day>> {
     let url = format!("{}/canteens/{}/days", OPEN_MENSA_API, id,);
-    let days: Vec<Day> = PaginatedList::new(url, *TTL_MEALS).consume()?;
-    Ok(days
+    let (ttl, refresh) = (ctx.conf.meals_ttl(id), ctx.conf.args.refresh);
+    PaginatedList::new(ctx.cache, url, ttl, refresh).consume()
+}
+
+fn fetch_dates_for_canteen(ctx: &Context<'_>, id: CanteenId) -> Result<HashMap<NaiveDate, Served>> {
+    Ok(fetch_days(ctx, id)?
         .into_iter()
-        .map(|day| (day.date, Fetchable::None))
+        .map(|day| {
+            let served = if day.closed {
+                Served::Closed
+            } else {
+                Served::Open(Fetchable::None)
+            };
+            (day.date, served)
+        })
         .collect())
 }
 
-fn fetch_meals(id: CanteenId, date: &NaiveDate) -> Result<Vec<Meal>> {
-    let url = format!("{}/canteens/{}/days/{}/meals", OPEN_MENSA_API, id, date);
-    PaginatedList::new(url, *TTL_MEALS).consume()
+fn fetch_meals(ctx: &Context<'_>, id: CanteenId, date: &NaiveDate) -> Result<(Vec<Meal>, Source)> {
+    let (ttl, refresh) = (ctx.conf.meals_ttl(id), ctx.conf.args.refresh);
+    PaginatedList::new(ctx.cache, meals_url(id, date), ttl, refresh).consume_with_source()
 }
 
 impl From<CanteenId> for Canteen {
@@ -201,6 +771,7 @@ impl From<CanteenId> for Canteen {
             id,
             meta: Fetchable::None,
             meals: Fetchable::None,
+            meal_sources: HashMap::new(),
         }
     }
 }
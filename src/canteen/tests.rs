@@ -1,10 +1,15 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
 
-use chrono::Duration;
+use chrono::{Duration, TimeZone, Utc};
 use pretty_assertions::assert_eq;
+use structopt::StructOpt;
 
 use crate::{
-    cache::{Fetchable, API},
+    cache::{DefaultCache, Fetchable, Source, API, CACHE},
+    config::{
+        args::{Args, CanteenFormat},
+        Config, CONF,
+    },
     meal::{self, Prices},
     tag::Tag,
 };
@@ -39,7 +44,7 @@ fn it_parses_a_canteen() {
             }
         "#;
     API.register_single(url, value, None);
-    let canteen: Canteen = CACHE.fetch_json(url, Duration::zero()).unwrap();
+    let canteen: Canteen = CACHE.fetch_json(url, Duration::zero(), false).unwrap();
     assert_eq!(
         canteen,
         Canteen {
@@ -51,6 +56,7 @@ fn it_parses_a_canteen() {
                 coordinates: Some([52.13, 11.64]),
             }),
             meals: Fetchable::None,
+            meal_sources: HashMap::new(),
         }
     );
 }
@@ -80,7 +86,7 @@ fn it_parses_a_list_of_canteens() {
         ]
     "#;
     API.register_single(url, value, None);
-    let canteens: Vec<Canteen> = CACHE.fetch_json(url, Duration::zero()).unwrap();
+    let canteens: Vec<Canteen> = CACHE.fetch_json(url, Duration::zero(), false).unwrap();
     assert_eq!(
         canteens,
         &[
@@ -93,6 +99,7 @@ fn it_parses_a_list_of_canteens() {
                     coordinates: None,
                 }),
                 meals: Fetchable::None,
+                meal_sources: HashMap::new(),
             },
             Canteen {
                 id: 10,
@@ -103,6 +110,7 @@ fn it_parses_a_list_of_canteens() {
                     coordinates: Some([52.13, 11.64]),
                 }),
                 meals: Fetchable::None,
+                meal_sources: HashMap::new(),
             }
         ]
     );
@@ -161,7 +169,7 @@ fn it_parses_multipage_canteen_lists() {
         ),
     ];
     API.register_pages(map);
-    let canteens: Vec<Canteen> = PaginatedList::new(first_url, Duration::zero())
+    let canteens: Vec<Canteen> = PaginatedList::new(&*CACHE, first_url, Duration::zero(), false)
         .consume()
         .unwrap();
     assert_eq!(
@@ -176,6 +184,7 @@ fn it_parses_multipage_canteen_lists() {
                     coordinates: None,
                 }),
                 meals: Fetchable::None,
+                meal_sources: HashMap::new(),
             },
             Canteen {
                 id: 1,
@@ -186,6 +195,7 @@ fn it_parses_multipage_canteen_lists() {
                     coordinates: Some([1.1, 2.2]),
                 }),
                 meals: Fetchable::None,
+                meal_sources: HashMap::new(),
             },
             Canteen {
                 id: 2,
@@ -196,16 +206,88 @@ fn it_parses_multipage_canteen_lists() {
                     coordinates: None,
                 }),
                 meals: Fetchable::None,
+                meal_sources: HashMap::new(),
             }
         ]
     )
 }
 
+#[test]
+fn it_continues_after_empty_pages() {
+    let first_url = "http://invalid.local/canteen/gap";
+    let map = &[
+        (
+            first_url,
+            r#"[ { "id": 0, "name": "First", "city": "", "address": "" } ]"#,
+            None,
+        ),
+        ("http://invalid.local/canteen/gap/2", "[]", None),
+        (
+            "http://invalid.local/canteen/gap/3",
+            r#"[ { "id": 2, "name": "Third", "city": "", "address": "" } ]"#,
+            None,
+        ),
+    ];
+    API.register_pages(map);
+    let canteens: Vec<Canteen> = PaginatedList::new(&*CACHE, first_url, Duration::zero(), false)
+        .consume()
+        .unwrap();
+    let ids: Vec<_> = canteens.iter().map(Canteen::id).collect();
+    assert_eq!(ids, vec![0, 2]);
+}
+
+#[test]
+fn it_follows_links_without_page_counters() {
+    let first_url = "http://invalid.local/canteen/linked";
+    API.register_linked_pages(&[
+        (
+            first_url,
+            r#"[ { "id": 0, "name": "First", "city": "", "address": "" } ]"#,
+        ),
+        ("http://invalid.local/canteen/linked/2", "[]"),
+        (
+            "http://invalid.local/canteen/linked/3",
+            r#"[ { "id": 2, "name": "Third", "city": "", "address": "" } ]"#,
+        ),
+    ]);
+    let canteens: Vec<Canteen> = PaginatedList::new(&*CACHE, first_url, Duration::zero(), false)
+        .consume()
+        .unwrap();
+    let ids: Vec<_> = canteens.iter().map(Canteen::id).collect();
+    assert_eq!(ids, vec![0, 2]);
+}
+
+#[test]
+fn it_keeps_pages_before_a_failing_page() {
+    let first_url = "http://invalid.local/canteen/partial";
+    let map = &[
+        (
+            first_url,
+            r#"[ { "id": 0, "name": "First", "city": "", "address": "" } ]"#,
+            None,
+        ),
+        ("http://invalid.local/canteen/partial/2", "{", None),
+        ("http://invalid.local/canteen/partial/3", "[]", None),
+    ];
+    API.register_pages(map);
+    let (canteens, errors) =
+        PaginatedList::<Canteen, _>::new(&*CACHE, first_url, Duration::zero(), false)
+            .consume_partial();
+    let ids: Vec<_> = canteens.iter().map(Canteen::id).collect();
+    assert_eq!(ids, vec![0]);
+    assert_eq!(errors.len(), 1);
+    assert!(
+        PaginatedList::<Canteen, _>::new(&*CACHE, first_url, Duration::zero(), false)
+            .consume()
+            .is_err()
+    );
+}
+
 #[test]
 fn it_parses_empty_lists() {
     let url = "http://invalid.local/canteen/empty";
     API.register_single(url, "[]", None);
-    let canteens: Vec<Canteen> = CACHE.fetch_json(url, Duration::zero()).unwrap();
+    let canteens: Vec<Canteen> = CACHE.fetch_json(url, Duration::zero(), false).unwrap();
     assert_eq!(canteens, &[]);
 }
 
@@ -213,7 +295,7 @@ fn it_parses_empty_lists() {
 fn it_errors_on_empty_body() {
     let url = "http://invalid.local/canteen/null";
     API.register_single(url, "", None);
-    let res: Result<Vec<Canteen>> = CACHE.fetch_json(url, Duration::zero());
+    let res: Result<Vec<Canteen>> = CACHE.fetch_json(url, Duration::zero(), false);
     assert!(res.is_err());
 }
 
@@ -234,7 +316,7 @@ fn it_fetches_metadata() {
     API.register_single(&url, &value, None);
     let mut canteen = Canteen::from(id);
     // Trigger fetch
-    canteen.meta().unwrap();
+    canteen.meta(&Context::global()).unwrap();
     assert_eq!(
         canteen,
         Canteen {
@@ -246,6 +328,7 @@ fn it_fetches_metadata() {
                 coordinates: None,
             }),
             meals: Fetchable::None,
+            meal_sources: HashMap::new(),
         }
     );
 }
@@ -253,7 +336,7 @@ fn it_fetches_metadata() {
 #[test]
 fn it_fetches_meals() {
     let id = uniq_id!();
-    let date = chrono::NaiveDate::from_ymd(2021, 10, 27);
+    let date = chrono::NaiveDate::from_ymd_opt(2021, 10, 27).unwrap();
 
     // Add dates to canteen
     let url = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
@@ -297,10 +380,11 @@ fn it_fetches_meals() {
             id,
             meta: Fetchable::None,
             meals: Fetchable::None,
+            meal_sources: HashMap::new(),
         }
     );
     // Trigger fetch
-    canteen.meals_at_mut(&date).unwrap();
+    canteen.meals_at_mut(&Context::global(), &date).unwrap();
     assert_eq!(
         canteen,
         Canteen {
@@ -310,14 +394,15 @@ fn it_fetches_meals() {
                 vec![
                     (
                         date,
-                        Fetchable::Fetched(vec![Meal {
+                        Served::Open(Fetchable::Fetched(vec![Meal {
                             id: 8442313,
                             meta: Fetchable::Fetched(meal::Meta {
                                 name: String::from(
                                     "Schweinebraten mit Rotkohl und Kartoffelklößen"
                                 ),
                                 tags: vec![Tag::Pig].into_iter().collect(),
-                                descs: HashSet::new(),
+                                descs: BTreeSet::new(),
+                                notes: vec![String::from("Schwein")],
                                 category: String::from("Hauptgerichte"),
                                 prices: Prices {
                                     students: Some(3.1),
@@ -326,13 +411,375 @@ fn it_fetches_meals() {
                                     others: Some(6.2),
                                 },
                             })
-                        }])
+                        }]))
+                    ),
+                    (
+                        NaiveDate::from_ymd_opt(2021, 10, 28).unwrap(),
+                        Served::Open(Fetchable::None)
                     ),
-                    (NaiveDate::from_ymd(2021, 10, 28), Fetchable::None),
                 ]
                 .into_iter()
                 .collect()
             ),
+            meal_sources: vec![(date, Source::Network)].into_iter().collect(),
         }
     );
+    assert_eq!(canteen.meal_source(&date), Some(Source::Network));
+}
+
+#[test]
+fn it_converts_the_radius_to_km() {
+    let mut geo = GeoCommand {
+        lat: None,
+        long: None,
+        city: None,
+        postal: None,
+        radius: 10.0,
+        unit: DistanceUnit::Km,
+    };
+    assert_eq!(
+        near_url(51.3, 12.4, geo.unit.to_km(geo.radius)),
+        format!(
+            "{}/canteens?near[lat]=51.3&near[lng]=12.4&near[dist]=10",
+            OPEN_MENSA_API
+        )
+    );
+    geo.unit = DistanceUnit::Miles;
+    assert_eq!(
+        near_url(51.3, 12.4, geo.unit.to_km(geo.radius)),
+        format!(
+            "{}/canteens?near[lat]=51.3&near[lng]=12.4&near[dist]=16.09344",
+            OPEN_MENSA_API
+        )
+    );
+}
+
+#[test]
+fn the_name_is_not_the_address() {
+    let mut canteen = Canteen {
+        id: 1,
+        meta: Fetchable::Fetched(Meta {
+            name: String::from("Awesome Canteen"),
+            city: String::from("Lummerland"),
+            address: String::from("Some place!"),
+            coordinates: None,
+        }),
+        meals: Fetchable::None,
+        meal_sources: HashMap::new(),
+    };
+    assert_eq!(canteen.name(&Context::global()).unwrap(), "Awesome Canteen");
+    assert_eq!(canteen.address(&Context::global()).unwrap(), "Some place!");
+}
+
+#[test]
+fn it_prefetches_meals_of_many_canteens() {
+    let base = uniq_id!() % 1_000_000_000;
+    let date = NaiveDate::from_ymd_opt(2021, 11, 3).unwrap();
+    let ids: Vec<_> = (base..base + 6).collect();
+    for id in &ids {
+        let days = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+        API.register_single(
+            &days,
+            r#"[{ "date": "2021-11-03", "closed": false }]"#,
+            None,
+        );
+        let meals = format!("{}/canteens/{}/days/2021-11-03/meals", OPEN_MENSA_API, id);
+        API.register_single(&meals, "[]", None);
+    }
+    let mut canteens: Vec<_> = ids.iter().copied().map(Canteen::from).collect();
+    Canteen::prefetch_meals(&Context::global(), &mut canteens, &[date]);
+    for (id, canteen) in ids.iter().zip(&mut canteens) {
+        let days = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+        let meals = format!("{}/canteens/{}/days/2021-11-03/meals", OPEN_MENSA_API, id);
+        assert_eq!(API.request_count(&days), 1);
+        assert_eq!(API.request_count(&meals), 1);
+        // Already fetched, no further requests
+        assert_eq!(
+            canteen.meals_at_mut(&Context::global(), &date).unwrap(),
+            Some(&mut vec![])
+        );
+        assert_eq!(API.request_count(&meals), 1);
+    }
+}
+
+#[test]
+fn it_checks_whether_a_canteen_is_open() {
+    let id = uniq_id!();
+    let url = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+    let value = r#"[
+        { "date": "2021-11-08", "closed": false },
+        { "date": "2021-11-09", "closed": true }
+    ]"#;
+    API.register_single(&url, value, None);
+    let canteen = Canteen::from(id);
+    let ctx = Context::global();
+    let date = |day| NaiveDate::from_ymd_opt(2021, 11, day).unwrap();
+    assert!(canteen.is_open_at(&ctx, &date(8)).unwrap());
+    assert!(!canteen.is_open_at(&ctx, &date(9)).unwrap());
+    assert!(!canteen.is_open_at(&ctx, &date(10)).unwrap());
+}
+
+#[test]
+fn contexts_can_bring_their_own_cache_and_config() {
+    let id = uniq_id!();
+    let url = format!("{}/canteens/{}", OPEN_MENSA_API, id);
+    let value = format!(
+        r#"{{ "id": {}, "name": "Own", "city": "Leer", "address": "Here", "coordinates": null }}"#,
+        id
+    );
+    API.register_single(&url, &value, None);
+    Canteen::from(id).meta(&Context::global()).unwrap();
+    assert_eq!(API.request_count(&url), 1);
+    // A separate cache does not know about the global one
+    let cache = DefaultCache::init().unwrap();
+    let ctx = Context {
+        cache: &cache,
+        conf: &CONF,
+    };
+    Canteen::from(id).meta(&ctx).unwrap();
+    Canteen::from(id).meta(&ctx).unwrap();
+    assert_eq!(API.request_count(&url), 2);
+    // Refreshing ignores the cached value
+    let conf = Config::from_args(Args::from_iter(&["mensa", "--refresh"]));
+    let ctx = Context {
+        cache: &cache,
+        conf: &conf,
+    };
+    Canteen::from(id).meta(&ctx).unwrap();
+    assert_eq!(API.request_count(&url), 3);
+}
+
+#[test]
+fn canteens_without_coordinates_are_sorted_last() {
+    let canteen = |id, coordinates| Canteen {
+        id,
+        meta: Fetchable::Fetched(Meta {
+            name: id.to_string(),
+            city: String::new(),
+            address: String::new(),
+            coordinates,
+        }),
+        meals: Fetchable::None,
+        meal_sources: HashMap::new(),
+    };
+    let mut canteens = vec![
+        canteen(1, None),
+        canteen(2, Some([52.0, 11.0])),
+        canteen(3, None),
+        canteen(4, Some([51.0, 11.0])),
+        canteen(5, Some([52.1, 11.0])),
+    ];
+    Canteen::sort_by_distance(&Context::global(), &mut canteens, (52.0, 11.0)).unwrap();
+    let ids: Vec<_> = canteens.iter().map(Canteen::id).collect();
+    assert_eq!(ids, vec![2, 5, 4, 1, 3]);
+}
+
+#[test]
+fn only_the_nearest_canteens_are_kept() {
+    let canteen = |id, coordinates| Canteen {
+        id,
+        meta: Fetchable::Fetched(Meta {
+            name: id.to_string(),
+            city: String::new(),
+            address: String::new(),
+            coordinates,
+        }),
+        meals: Fetchable::None,
+        meal_sources: HashMap::new(),
+    };
+    let ctx = Context::global();
+    let ids = |canteens: &[Canteen]| canteens.iter().map(Canteen::id).collect::<Vec<_>>();
+    let mut canteens = vec![
+        canteen(1, Some([53.0, 11.0])),
+        canteen(2, None),
+        canteen(3, Some([52.0, 11.0])),
+        canteen(4, Some([52.5, 11.0])),
+    ];
+    Canteen::keep_nearest(&ctx, &mut canteens, (52.0, 11.0), 2).unwrap();
+    assert_eq!(ids(&canteens), vec![3, 4]);
+    // Without coordinates, the order of the API is kept
+    let mut canteens = vec![canteen(7, None), canteen(5, None), canteen(6, None)];
+    Canteen::keep_nearest(&ctx, &mut canteens, (52.0, 11.0), 2).unwrap();
+    assert_eq!(ids(&canteens), vec![7, 5]);
+}
+
+#[test]
+fn it_resolves_canteen_slugs() {
+    let url = format!("{}/canteens", OPEN_MENSA_API);
+    let value = r#"[
+        { "id": 1, "name": "Mensa am Park", "city": "Leipzig", "address": "" },
+        { "id": 2, "name": "Mensa Süd", "city": "Magdeburg", "address": "" },
+        { "id": 3, "name": "Mensa Sued", "city": "Erfurt", "address": "" }
+    ]"#;
+    API.register_single(&url, value, None);
+    let ctx = Context::global();
+    let resolve = |raw: &str| Canteen::resolve(&ctx, raw.parse().unwrap());
+    assert_eq!(resolve("63").unwrap().id(), 63);
+    assert_eq!(resolve("mensa-am-park").unwrap().id(), 1);
+    assert_eq!(resolve("Mensa am Park").unwrap().id(), 1);
+    assert!(matches!(
+        resolve("mensa-sued"),
+        Err(Error::AmbiguousCanteenSlug(slug, ids)) if slug == "mensa-sued" && ids == vec![2, 3]
+    ));
+    assert!(matches!(
+        resolve("mensa-nord"),
+        Err(Error::UnknownCanteenSlug(slug)) if slug == "mensa-nord"
+    ));
+}
+
+#[test]
+fn calendars_have_an_event_per_day() {
+    let day = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    let meals = vec![
+        (
+            String::from("Hauptgericht"),
+            String::from("Linsen, Spätzle"),
+        ),
+        (String::from("Beilage"), String::from("Bohnen")),
+    ];
+    let stamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let ics = days::calendar(
+        63,
+        "Mensa am Park",
+        "Universitätsstraße 5",
+        &[(day, meals)],
+        stamp,
+    );
+    let lines: Vec<_> = ics.split("\r\n").collect();
+    assert_eq!(lines[0], "BEGIN:VCALENDAR");
+    assert_eq!(lines[lines.len() - 2], "END:VCALENDAR");
+    assert!(lines.contains(&"UID:63-20240108@mensa"));
+    assert!(lines.contains(&"DTSTAMP:20240101T120000Z"));
+    assert!(lines.contains(&"DTSTART;VALUE=DATE:20240108"));
+    assert!(lines.contains(&"DTEND;VALUE=DATE:20240109"));
+    assert!(lines.contains(&"SUMMARY:Mensa am Park: Linsen\\, Spätzle\\, Bohnen"));
+    assert!(lines.contains(&"DESCRIPTION:Hauptgericht: Linsen\\, Spätzle\\nBeilage: Bohnen"));
+    assert_eq!(
+        lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+        1
+    );
+}
+
+#[test]
+fn long_calendar_lines_are_folded() {
+    let day = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    let name = "ä".repeat(60);
+    let ics = days::calendar(1, &name, "", &[(day, vec![])], Utc::now());
+    assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!("SUMMARY:{}\r\n", name)));
+}
+
+#[test]
+fn closed_days_differ_from_unscheduled_days() {
+    let id = uniq_id!();
+    let url = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+    let value = r#"[
+        { "date": "2021-12-24", "closed": true },
+        { "date": "2021-12-27", "closed": false }
+    ]"#;
+    API.register_single(&url, value, None);
+    let ctx = Context::global();
+    let mut canteen = Canteen::from(id);
+    let closed = NaiveDate::from_ymd_opt(2021, 12, 24).unwrap();
+    let unscheduled = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+    // Neither have meals, closed days are not fetched at all
+    assert_eq!(canteen.meals_at_mut(&ctx, &closed).unwrap(), None);
+    assert_eq!(canteen.meals_at_mut(&ctx, &unscheduled).unwrap(), None);
+    assert!(canteen.is_scheduled_at(&ctx, &closed).unwrap());
+    assert!(!canteen.is_scheduled_at(&ctx, &unscheduled).unwrap());
+}
+
+#[test]
+fn raw_meals_are_not_parsed() {
+    let id = uniq_id!();
+    let date = NaiveDate::from_ymd_opt(2021, 11, 8).unwrap();
+    let value = r#"[ { "id": 1, "name": "Soup", "surprise": true } ]"#;
+    API.register_single(&meals_url(id, &date), value, None);
+    let raw = Canteen::from(id)
+        .raw_meals_at(&Context::global(), &date)
+        .unwrap();
+    assert_eq!(raw, value);
+}
+
+#[test]
+fn the_first_open_canteen_is_the_nearest_serving_meals() {
+    let base = uniq_id!() % 1_000_000_000;
+    let date = NaiveDate::from_ymd_opt(2021, 11, 8).unwrap();
+    let canteen = |id, lat| Canteen {
+        id,
+        meta: Fetchable::Fetched(Meta {
+            name: id.to_string(),
+            city: String::new(),
+            address: String::new(),
+            coordinates: Some([lat, 11.0]),
+        }),
+        meals: Fetchable::None,
+        meal_sources: HashMap::new(),
+    };
+    let (closed, empty, open, far) = (base, base + 1, base + 2, base + 3);
+    let days = |id| format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+    API.register_single(
+        &days(closed),
+        r#"[{ "date": "2021-11-08", "closed": true }]"#,
+        None,
+    );
+    for id in [empty, open, far] {
+        API.register_single(
+            &days(id),
+            r#"[{ "date": "2021-11-08", "closed": false }]"#,
+            None,
+        );
+    }
+    let meal = r#"[ { "id": 1, "name": "Soup", "notes": [], "category": "Suppen",
+        "prices": { "students": 1.0, "employees": null, "pupils": null, "others": null } } ]"#;
+    API.register_single(&meals_url(empty, &date), "[]", None);
+    API.register_single(&meals_url(open, &date), meal, None);
+    API.register_single(&meals_url(far, &date), meal, None);
+    let canteens = vec![
+        canteen(far, 53.0),
+        canteen(open, 52.2),
+        canteen(empty, 52.1),
+        canteen(closed, 52.0),
+    ];
+    let ctx = Context::global();
+    let first = Canteen::first_open(&ctx, canteens, (52.0, 11.0), &date).unwrap();
+    assert_eq!(first.id(), open);
+    // Canteens beyond the first open one are not asked for meals
+    assert_eq!(API.request_count(&days(far)), 0);
+    assert!(matches!(
+        Canteen::first_open(&ctx, vec![canteen(closed, 52.0)], (52.0, 11.0), &date),
+        Err(Error::NoOpenCanteenNearby(_))
+    ));
+}
+
+#[test]
+fn canteens_can_be_formatted() {
+    let mut canteen = Canteen {
+        id: 63,
+        meta: Fetchable::Fetched(Meta {
+            name: String::from("Mensa am Park"),
+            city: String::from("Leipzig"),
+            address: String::from("Universitätsstraße 5"),
+            coordinates: None,
+        }),
+        meals: Fetchable::None,
+        meal_sources: HashMap::new(),
+    };
+    let format: CanteenFormat = "{id} {name} — {city}{distance}".parse().unwrap();
+    let formatted = canteen
+        .format(&Context::global(), &format.0, None, DistanceUnit::Km)
+        .unwrap();
+    assert_eq!(formatted, "63 Mensa am Park — Leipzig");
+    let format: CanteenFormat = "{distance}".parse().unwrap();
+    let formatted = canteen
+        .format(
+            &Context::global(),
+            &format.0,
+            Some((51.0, 12.0)),
+            DistanceUnit::Km,
+        )
+        .unwrap();
+    assert_eq!(formatted, "unknown");
+    assert!("{id} {phone}".parse::<CanteenFormat>().is_err());
 }
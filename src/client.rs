@@ -0,0 +1,93 @@
+//! Library access to the OpenMensa API.
+//!
+//! The [`Client`] does not read the command line or the configuration
+//! file, everything it needs is given explicitly. It owns its cache,
+//! which is independent of the one used by the CLI.
+
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate};
+use strum::IntoEnumIterator;
+
+use crate::{
+    cache::{Cacache, Cache, MemoryCache},
+    canteen::{self, Canteen, CanteenId},
+    error::Result,
+    meal::Meal,
+    pagination::PaginatedList,
+    tag::Tag,
+    DIR, TTL_CANTEENS, TTL_MEALS,
+};
+
+/// Client for the OpenMensa API.
+///
+/// Responses are cached in the cache `C`, see [`Client::new`],
+/// [`Client::at`] and [`Client::in_memory`].
+pub struct Client<C = Cacache> {
+    cache: C,
+    /// Ignore cached values, but update the cache with fresh responses.
+    pub refresh: bool,
+    /// How long canteens are cached.
+    pub canteens_ttl: Duration,
+    /// How long meals are cached.
+    pub meals_ttl: Duration,
+}
+
+impl Client<Cacache> {
+    /// Client caching on disk, in the default cache directory of the CLI.
+    pub fn new() -> Result<Self> {
+        Self::at(DIR.cache_dir())
+    }
+
+    /// Client caching on disk at `path`, which is created if missing.
+    pub fn at<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        Cacache::open(path).map(Self::with_cache)
+    }
+}
+
+impl Client<MemoryCache> {
+    /// Client caching in memory only, nothing is persisted.
+    pub fn in_memory() -> Result<Self> {
+        MemoryCache::init().map(Self::with_cache)
+    }
+}
+
+impl<C: Cache> Client<C> {
+    /// Client using the given `cache` and the default TTLs.
+    pub fn with_cache(cache: C) -> Self {
+        Self {
+            cache,
+            refresh: false,
+            canteens_ttl: *TTL_CANTEENS,
+            meals_ttl: *TTL_MEALS,
+        }
+    }
+
+    /// The cache responses are stored in.
+    pub fn cache(&self) -> &C {
+        &self.cache
+    }
+
+    /// All canteens within `radius` km around the position given
+    /// by `lat` and `long`.
+    ///
+    /// Their name and address are given by [`Canteen::fetched_meta`].
+    pub fn canteens_near(&self, lat: f32, long: f32, radius: f32) -> Result<Vec<Canteen>> {
+        let url = canteen::near_url(lat, long, f64::from(radius));
+        PaginatedList::new(&self.cache, url, self.canteens_ttl, self.refresh).consume()
+    }
+
+    /// The meals of the canteen `id` at `date`.
+    ///
+    /// The OpenMensa API errors if the canteen has no information
+    /// about that date.
+    pub fn meals(&self, id: CanteenId, date: &NaiveDate) -> Result<Vec<Meal>> {
+        let url = canteen::meals_url(id, date);
+        PaginatedList::new(&self.cache, url, self.meals_ttl, self.refresh).consume()
+    }
+
+    /// All known tags, ordered by their number.
+    pub fn tags(&self) -> Vec<Tag> {
+        Tag::iter().collect()
+    }
+}
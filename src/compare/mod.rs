@@ -0,0 +1,128 @@
+//! Compare the price of a meal across canteens.
+
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
+
+use std::cmp::Ordering;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    canteen::{Canteen, CanteenId},
+    config::{Config, Currency, PriceTags},
+    context::Context,
+    error::Result,
+    print_json,
+};
+
+/// A meal matching the compared name, served by a canteen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Offer {
+    pub canteen_id: CanteenId,
+    #[serde(skip)]
+    pub canteen: String,
+    pub name: String,
+    pub price: Option<f32>,
+}
+
+/// Print the prices of all meals matching `query` in `canteens`.
+///
+/// Meals served today are compared using the first selected price tier,
+/// the cheapest offer is printed first.
+pub fn print(ctx: &Context<'_>, canteens: &mut [Canteen], query: &str) -> Result<()> {
+    let tier = price_tier(ctx.conf);
+    let day = ctx.conf.date();
+    let mut offers = vec![];
+    for canteen in canteens {
+        let canteen_id = canteen.id();
+        let canteen_name = canteen.name(ctx)?.clone();
+        let meals = match canteen.meals_at_mut(ctx, day)? {
+            Some(meals) => meals,
+            None => continue,
+        };
+        for meal in meals {
+            let meta = meal.meta()?;
+            if is_fuzzy_match(query, &meta.name) {
+                offers.push(Offer {
+                    canteen_id,
+                    canteen: canteen_name.clone(),
+                    name: meta.name.clone(),
+                    price: meta.prices.get(tier),
+                });
+            }
+        }
+    }
+    sort_by_price(&mut offers);
+    if ctx.conf.args.json {
+        // Prices are only converted on request
+        if ctx.conf.args.convert {
+            convert_prices(&mut offers, ctx.conf.currency());
+        }
+        print_json(&offers)
+    } else {
+        print_offers(ctx.conf, &offers)
+    }
+}
+
+fn print_offers(conf: &Config, offers: &[Offer]) -> Result<()> {
+    if offers.is_empty() {
+        return try_println!("{}", color!(conf.messages().no_meals; dimmed));
+    }
+    let currency = conf.currency();
+    let prices: Vec<_> = offers
+        .iter()
+        .map(|offer| match offer.price {
+            Some(price) => currency.format(price),
+            None => currency.missing(),
+        })
+        .collect();
+    let width = prices.iter().map(|price| price.width()).max();
+    for (offer, price) in offers.iter().zip(prices) {
+        let price = format!("{:>width$}", price, width = width.unwrap_or_default());
+        try_println!(
+            "{}  {} {}\n{}  {}",
+            color!(price; bright_green),
+            color!(format!("{:>4}", offer.canteen_id); bold, bright_yellow),
+            color!(offer.canteen; bold),
+            " ".repeat(width.unwrap_or_default()),
+            offer.name,
+        )?;
+    }
+    Ok(())
+}
+
+/// The price tier used for comparing, the first of the selected tiers.
+fn price_tier(conf: &Config) -> PriceTags {
+    let selected = conf.price_tags();
+    PriceTags::ORDER
+        .iter()
+        .copied()
+        .find(|tier| selected.is_empty() || selected.contains(tier))
+        .unwrap_or(PriceTags::Student)
+}
+
+/// Does `name` contain every word of `query`, ignoring case?
+fn is_fuzzy_match(query: &str, name: &str) -> bool {
+    let name = name.to_lowercase();
+    query
+        .split_whitespace()
+        .all(|word| name.contains(&word.to_lowercase()))
+}
+
+/// Convert the prices of all `offers` to `currency`.
+fn convert_prices(offers: &mut [Offer], currency: &Currency) {
+    for offer in offers {
+        offer.price = offer.price.map(|price| currency.convert(price));
+    }
+}
+
+/// Sort `offers` by price ascending, offers without a price come last.
+fn sort_by_price(offers: &mut [Offer]) {
+    offers.sort_by(|a, b| match (a.price, b.price) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
@@ -0,0 +1,48 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+fn offer(canteen_id: CanteenId, price: Option<f32>) -> Offer {
+    Offer {
+        canteen_id,
+        canteen: String::from("Mensa"),
+        name: String::from("Pizza"),
+        price,
+    }
+}
+
+#[test]
+fn it_matches_names_fuzzily() {
+    assert!(is_fuzzy_match("pizza", "Pizza Margherita"));
+    assert!(is_fuzzy_match("margherita PIZZA", "Pizza Margherita"));
+    assert!(!is_fuzzy_match("pizza salami", "Pizza Margherita"));
+}
+
+#[test]
+fn it_sorts_the_cheapest_offer_first() {
+    let mut offers = vec![offer(1, None), offer(2, Some(3.5)), offer(3, Some(1.2))];
+    sort_by_price(&mut offers);
+    let ids: Vec<_> = offers.iter().map(|offer| offer.canteen_id).collect();
+    assert_eq!(ids, vec![3, 2, 1]);
+}
+
+#[test]
+fn it_converts_the_prices_of_offers() {
+    let currency = Currency {
+        conversion_rate: Some(2.0),
+        ..Default::default()
+    };
+    let mut offers = vec![offer(1, Some(1.5)), offer(2, None)];
+    convert_prices(&mut offers, &currency);
+    let prices: Vec<_> = offers.iter().map(|offer| offer.price).collect();
+    assert_eq!(prices, vec![Some(3.0), None]);
+}
+
+#[test]
+fn offers_serialize_without_the_canteen_name() {
+    let json = serde_json::to_value(offer(1, Some(1.5))).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({"canteen_id": 1, "name": "Pizza", "price": 1.5})
+    );
+}
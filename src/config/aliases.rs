@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use crate::{error::Error, tag::Tag};
+
+/// Display names replacing the english names of tags.
+///
+/// Configured as a table from tag names, as accepted by `--filter-tag`, to aliases.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct TagAliases(BTreeMap<Tag, String>);
+
+impl TagAliases {
+    /// The alias of `tag`, if any.
+    pub fn get(&self, tag: Tag) -> Option<&str> {
+        self.0.get(&tag).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for TagAliases {
+    type Error = Error;
+
+    fn try_from(raw: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        raw.into_iter()
+            .map(|(tag, alias)| match serde_plain::from_str(&tag) {
+                Ok(tag) => Ok((tag, alias)),
+                Err(_) => Err(Error::UnknownTag(tag)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl From<TagAliases> for BTreeMap<String, String> {
+    fn from(aliases: TagAliases) -> Self {
+        aliases
+            .0
+            .into_iter()
+            .map(|(tag, alias)| {
+                let tag = serde_plain::to_string(&tag).expect("Tags serialize to strings");
+                (tag, alias)
+            })
+            .collect()
+    }
+}
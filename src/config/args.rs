@@ -3,14 +3,17 @@ use regex::Regex;
 use serde::Deserialize;
 use structopt::{clap::arg_enum, StructOpt};
 
-use std::path::PathBuf;
+use std::{collections::BTreeSet, ops::RangeInclusive, path::PathBuf, str::FromStr};
 
 use crate::{
+    canteen::{Canteen, CanteenId},
     error::{Error, Result},
+    meal::MealComplete,
     tag::Tag,
+    template::Template,
 };
 
-use super::PriceTags;
+use super::{rule::FilterMode, PriceTags};
 
 /// OpenMensa.org CLI frontend.
 #[derive(Debug, StructOpt)]
@@ -20,9 +23,59 @@ pub struct Args {
     #[structopt(long, global = true)]
     pub clear_cache: bool,
 
+    /// Ignore cached values, but update the cache with fresh responses.
+    #[structopt(long, global = true)]
+    pub refresh: bool,
+
+    /// Where to keep cached responses.
+    /// The memory backend does not persist anything between invocations.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_CACHE_BACKEND",
+        value_name = "BACKEND",
+        default_value = "Disk",
+        possible_values = &CacheBackend::variants(),
+        case_insensitive = true
+    )]
+    pub cache_backend: CacheBackend,
+
+    /// Directory of the disk cache. Created if missing.
+    #[structopt(long, env = "MENSA_CACHE_DIR", value_name = "PATH", global = true)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Print the directory of the disk cache and exit.
+    #[structopt(long, global = true, hidden = true)]
+    pub print_cache_dir: bool,
+
+    /// Print the path of the configuration file and exit.
+    /// With several files given by `--config`, the last one is printed.
+    #[structopt(long, global = true, hidden = true)]
+    pub print_config_path: bool,
+
     /// Path to the configuration file.
-    #[structopt(long, short, env = "MENSA_CONFIG", name = "PATH", global = true)]
-    pub config: Option<PathBuf>,
+    ///
+    /// Repeat to layer several files, like a shared and a personal one.
+    /// Filters and favourites of all files are joined, every other
+    /// option is taken from the last file setting it.
+    /// `--save-default` and `config init` write to the last file.
+    #[structopt(
+        long,
+        short,
+        env = "MENSA_CONFIG",
+        name = "PATH",
+        number_of_values = 1,
+        global = true
+    )]
+    pub config: Vec<PathBuf>,
+
+    /// Configuration as toml, takes precedence over the configuration file.
+    #[structopt(long, env = "MENSA_CONFIG_TOML", hide_env_values = true, global = true)]
+    pub config_toml: Option<String>,
+
+    /// Merge the named profile from the configuration over the base configuration.
+    #[structopt(long, env = "MENSA_PROFILE", name = "NAME", global = true)]
+    pub profile: Option<String>,
 
     /// Use ascii characters only.
     /// This does not prune non-ascii characters returned by the openmensa API.
@@ -30,6 +83,11 @@ pub struct Args {
     #[structopt(long, env = "MENSA_ASCII_ONLY", global = true, takes_value = false)]
     pub plain: bool,
 
+    /// Write primary tags like `Vegan` as text instead of emojis,
+    /// while keeping the other unicode characters.
+    #[structopt(long, env = "MENSA_NO_EMOJI", global = true, takes_value = false)]
+    pub no_emoji: bool,
+
     /// When to use terminal colors.
     #[structopt(
         long,
@@ -41,10 +99,177 @@ pub struct Args {
     )]
     pub color: ColorWhen,
 
+    /// Background of the terminal, used to pick readable colors.
+    /// `Auto` inspects `$COLORFGBG`, falling back to `Dark`.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_THEME",
+        value_name = "THEME",
+        default_value = "Dark",
+        possible_values = &Theme::variants(),
+        case_insensitive = true
+    )]
+    pub theme: Theme,
+
+    /// Format of the log messages printed to stderr.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_LOG_FORMAT",
+        value_name = "FORMAT",
+        default_value = "Pretty",
+        possible_values = &LogFormat::variants(),
+        case_insensitive = true
+    )]
+    pub log_format: LogFormat,
+
+    /// Never guess the location via geoip, a location must be given instead.
+    /// See `disable-geoip` in the configuration.
+    #[structopt(long, env = "MENSA_NO_GEOIP", global = true, takes_value = false)]
+    pub no_geoip: bool,
+
+    /// Report fields of OpenMensa's responses that are not understood.
+    /// Also shows warnings, unless `RUST_LOG` says otherwise.
+    #[structopt(long, global = true, takes_value = false)]
+    pub verbose: bool,
+
     /// Output json. Useful for shell scripts.
     #[structopt(long, global = true, takes_value = false)]
     pub json: bool,
 
+    /// Output json including the canteen of every list of meals.
+    /// Implies `--json`.
+    #[structopt(long, global = true, takes_value = false)]
+    pub json_full: bool,
+
+    /// Wrap json output in an object containing the version of its shape.
+    /// Has no effect without json output.
+    #[structopt(long, global = true, takes_value = false)]
+    pub json_versioned: bool,
+
+    /// Only print the part of the json output referenced by this
+    /// JSON Pointer (RFC 6901), like `/63/0/name`.
+    #[structopt(long, global = true, value_name = "POINTER")]
+    pub select: Option<JsonPointer>,
+
+    /// Output one json object per line, as soon as it's fetched.
+    /// Only supported when listing canteens.
+    #[structopt(long, global = true, takes_value = false)]
+    pub ndjson: bool,
+
+    /// Output a single json array, writing every element as soon as it's fetched.
+    /// Only supported when listing canteens.
+    #[structopt(long, global = true, takes_value = false, conflicts_with = "ndjson")]
+    pub json_array: bool,
+
+    /// Show at most N meals per canteen, or N canteens when listing canteens.
+    /// Applied after filtering and sorting, right before printing.
+    /// Truncates json output, too.
+    #[structopt(long, global = true, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Print every canteen on a single line, like `"{id} {name} — {city}"`.
+    /// Placeholders: id, name, city, address, url and distance.
+    /// Literal braces are written as `{{` and `}}`.
+    #[structopt(long, global = true, value_name = "FORMAT")]
+    pub canteen_format: Option<CanteenFormat>,
+
+    /// Mark meals read from the cache without sending a request as `(cached)`.
+    /// Adds a `source` field to meals in json output.
+    #[structopt(long, global = true, takes_value = false)]
+    pub show_source: bool,
+
+    /// Print the number of requests, cache hits and misses and the
+    /// fetched bytes to stderr when done. Printed as json with `--json`.
+    #[structopt(long, global = true, takes_value = false)]
+    pub stats: bool,
+
+    /// Keep the canteens fetched so far if fetching a page fails.
+    #[structopt(long, global = true, takes_value = false)]
+    pub best_effort: bool,
+
+    /// Maximum number of requests sent at once when fetching many canteens.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_MAX_CONCURRENCY",
+        default_value = "4"
+    )]
+    pub max_concurrency: usize,
+
+    /// Stop waiting for the meals of a single canteen after SECONDS.
+    /// The canteen is reported as timed out, its meals are still cached
+    /// if they arrive before mensa exits. Canteens are then fetched one
+    /// after another. Ignored by json and Markdown output.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_TIMEOUT_PER_CANTEEN",
+        value_name = "SECONDS"
+    )]
+    pub timeout_per_canteen: Option<u64>,
+
+    /// Link canteen names to their OpenMensa page.
+    /// Requires a terminal supporting OSC-8 hyperlinks. Ignored with --plain.
+    #[structopt(long, env = "MENSA_HYPERLINKS", global = true, takes_value = false)]
+    pub hyperlinks: bool,
+
+    /// Terminal width used for wrapping text.
+    /// Defaults to the width of the terminal or `$COLUMNS`.
+    #[structopt(long, env = "MENSA_WIDTH", global = true, value_name = "N")]
+    pub width: Option<usize>,
+
+    /// Do not wrap meal names and descriptions.
+    #[structopt(long, env = "MENSA_NO_WRAP", global = true, takes_value = false)]
+    pub no_wrap: bool,
+
+    /// Prefix every meal with its OpenMensa id, like `#1234`.
+    #[structopt(long, env = "MENSA_SHOW_IDS", global = true, takes_value = false)]
+    pub show_ids: bool,
+
+    /// Do not print the descriptions of meals. Json output keeps them.
+    #[structopt(
+        long,
+        env = "MENSA_NO_DESCRIPTIONS",
+        global = true,
+        takes_value = false
+    )]
+    pub no_descriptions: bool,
+
+    /// Print the number of meals and their student prices per canteen.
+    #[structopt(long, env = "MENSA_SUMMARY", global = true, takes_value = false)]
+    pub summary: bool,
+
+    /// Pick a canteen from a list of nearby canteens if no id is configured.
+    /// Only works if stdin and stdout are terminals.
+    #[structopt(long, global = true, takes_value = false)]
+    pub interactive: bool,
+
+    /// Save the selected canteen id as default in the configuration file.
+    #[structopt(long, global = true, takes_value = false)]
+    pub save_default: bool,
+
+    /// Print which note of a meal resulted in which tags to stderr.
+    #[structopt(long, global = true, takes_value = false)]
+    pub explain_tags: bool,
+
+    /// Print why every meal was kept or dropped by the filter to stderr.
+    #[structopt(long, global = true, takes_value = false)]
+    pub explain_filter: bool,
+
+    /// Tint vegan and vegetarian tags green and meat tags red.
+    #[structopt(long, env = "MENSA_TAG_COLORS", global = true, takes_value = false)]
+    pub tag_colors: bool,
+
+    /// Align the prices of all meals of a canteen in a column.
+    #[structopt(long, env = "MENSA_ALIGN_PRICES", global = true, takes_value = false)]
+    pub align_prices: bool,
+
+    /// Convert prices in json output using the configured conversion rate.
+    #[structopt(long, env = "MENSA_CONVERT", global = true, takes_value = false)]
+    pub convert: bool,
+
     #[structopt(subcommand)]
     pub command: Option<Command>,
 }
@@ -55,9 +280,84 @@ pub enum Command {
     /// List canteens close to you.
     Canteens(CanteensCommand),
     /// List all known tags.
-    Tags,
+    Tags(TagsCommand),
     /// Default. Show meals.
     Meals(MealsCommand),
+    /// Manage the configuration file.
+    Config(ConfigCommand),
+    /// Compare the price of a meal in canteens close to you.
+    Compare(CompareCommand),
+    /// List canteens close to you which are open today.
+    Open(OpenCommand),
+    /// List the days a canteen serves meals.
+    Days(DaysCommand),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct TagsCommand {
+    /// Order in which the tags are listed.
+    #[structopt(
+        long,
+        default_value = "number",
+        possible_values = &TagSort::variants(),
+        case_insensitive = true
+    )]
+    pub sort: TagSort,
+
+    /// With `--json`, print an object keyed by the tag numbers instead of a list.
+    /// Has no effect with `--present`.
+    #[structopt(long, takes_value = false)]
+    pub map: bool,
+
+    /// Only list the tags of today's meals, with the number of meals carrying them.
+    #[structopt(long, takes_value = false)]
+    pub present: bool,
+
+    /// Canteen ID or slug used with `--present`.
+    #[structopt(long = "id", short = "i", env = "MENSA_ID")]
+    pub canteen_id: Option<CanteenRef>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DaysCommand {
+    /// Print an iCalendar with an all-day event per open day instead.
+    /// Every event lists the meals of its day.
+    #[structopt(long, takes_value = false)]
+    pub ical: bool,
+
+    /// Canteen ID or slug for which to list the days.
+    #[structopt(long = "id", short = "i", env = "MENSA_ID")]
+    pub canteen_id: Option<CanteenRef>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct OpenCommand {
+    #[structopt(flatten)]
+    pub geo: GeoCommand,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CompareCommand {
+    /// Name of the meal, all words must be contained in the meal name.
+    pub name: String,
+
+    #[structopt(flatten)]
+    pub geo: GeoCommand,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ConfigCommand {
+    /// Write a documented default configuration file.
+    ///
+    /// The file is written to the path given by `--config`
+    /// or the default configuration path.
+    Init {
+        /// Overwrite an existing configuration file.
+        #[structopt(long, short)]
+        force: bool,
+    },
+    /// Check the configuration file for errors and unknown keys.
+    Validate,
 }
 
 #[derive(Debug, StructOpt)]
@@ -66,10 +366,33 @@ pub struct CanteensCommand {
     #[structopt(long, short)]
     pub all: bool,
 
+    /// Only list the canteens with these ids, like `1,2,63`.
+    /// Fetches just their metadata instead of a list of canteens.
+    #[structopt(long, value_name = "IDS", use_delimiter = true)]
+    pub ids: Vec<CanteenId>,
+
+    /// Only list the canteens with ids in this inclusive range, like `60-70`.
+    /// Combines with `--ids`. The range may span at most 1000 ids.
+    #[structopt(long, value_name = "FROM-TO")]
+    pub id_range: Option<IdRange>,
+
     #[structopt(flatten)]
     pub geo: GeoCommand,
 }
 
+impl CanteensCommand {
+    /// The sorted ids given by `--ids` and `--id-range`, if any.
+    pub fn requested_ids(&self) -> Option<Vec<CanteenId>> {
+        let range = self.id_range.iter().flat_map(|range| range.0.clone());
+        let ids: BTreeSet<_> = self.ids.iter().copied().chain(range).collect();
+        if ids.is_empty() {
+            None
+        } else {
+            Some(ids.into_iter().collect())
+        }
+    }
+}
+
 #[derive(Debug, Clone, StructOpt)]
 pub enum CloseCommand {
     /// Show meals from canteens around you. Will overwrite --id.
@@ -86,29 +409,70 @@ pub struct GeoCommand {
     #[structopt(long)]
     pub long: Option<f32>,
 
-    /// Maximum distance of potential canteens from your position in km.
+    /// City to use as your position, instead of geoip.
+    #[structopt(long)]
+    pub city: Option<String>,
+
+    /// Postal code to use as your position, instead of geoip.
+    #[structopt(long)]
+    pub postal: Option<String>,
+
+    /// Maximum distance of potential canteens from your position.
     #[structopt(long, short, default_value = "10")]
     pub radius: f32,
+
+    /// Unit of the radius and displayed distances.
+    #[structopt(
+        long,
+        value_name = "UNIT",
+        default_value = "Km",
+        possible_values = &DistanceUnit::variants(),
+        case_insensitive = true
+    )]
+    pub unit: DistanceUnit,
+}
+
+impl GeoCommand {
+    /// Whether any position was given, by coordinates, city or postal code.
+    pub fn is_given(&self) -> bool {
+        self.lat.is_some() || self.long.is_some() || self.city.is_some() || self.postal.is_some()
+    }
 }
 
 #[derive(Debug, Clone, StructOpt)]
 pub struct MealsCommand {
     /// Date for which to display information.
     ///
-    /// Try values like `tomorrow`, `wed`, etc.
+    /// Try values like `tomorrow`, `wed`, etc. Multiple dates can be
+    /// given as list `mon,wed,fri` or range `2024-01-08..2024-01-12`.
+    /// A range may span at most 31 days.
     #[structopt(
-        long, short,
+        long,
+        short,
         global = true,
         env = "MENSA_DATE",
-        parse(try_from_str = parse_human_date),
         default_value = "today",
         global = true
     )]
-    pub date: NaiveDate,
+    pub date: Dates,
+
+    /// Also show the meals of the day after `--date`.
+    #[structopt(long, global = true, takes_value = false)]
+    pub also_tomorrow: bool,
+
+    /// Hint at the next weekday if a date falls on a weekend.
+    #[structopt(long, global = true, takes_value = false)]
+    pub suggest_open: bool,
 
     /// Canteen ID for which to fetch meals.
+    /// A slug of the canteen name, like `mensa-am-park`, works, too.
     #[structopt(long = "id", global = true, short = "i", env = "MENSA_ID")]
-    pub canteen_id: Option<usize>,
+    pub canteen_id: Option<CanteenRef>,
+
+    /// Use the canteen of the last successful `mensa meals --id`.
+    /// Takes precedence over `--id` and the configured default.
+    #[structopt(long, global = true, takes_value = false)]
+    pub last: bool,
 
     /// Specify which price tags should be displayed
     #[structopt(
@@ -121,6 +485,61 @@ pub struct MealsCommand {
     )]
     pub price: Option<Vec<PriceTags>>,
 
+    /// Display all price tags, ignoring the configured ones.
+    /// Tags given with `--price` take precedence.
+    #[structopt(long, global = true, env = "MENSA_ALL_PRICES", takes_value = false)]
+    pub all_prices: bool,
+
+    /// Show the meals of every canteen as a table with the columns
+    /// Name, Category, Price and Tags. Has no effect on json output.
+    #[structopt(long, global = true, env = "MENSA_TABLE", takes_value = false)]
+    pub table: bool,
+
+    /// Print the meals of every canteen as a Markdown table,
+    /// headed by the canteen name. Has no effect on json output.
+    #[structopt(long, global = true, env = "MENSA_MARKDOWN", takes_value = false)]
+    pub markdown: bool,
+
+    /// With `close` or a position, only show the meals of the nearest canteen
+    /// serving meals at the requested date.
+    #[structopt(long, global = true, takes_value = false)]
+    pub first_open: bool,
+
+    /// With `close` or a position, only show the N canteens nearest to it.
+    /// Combines with `--radius`. Canteens are ranked by their coordinates,
+    /// canteens without coordinates keep the order of the OpenMensa API.
+    #[structopt(long, global = true, value_name = "N")]
+    pub nearest: Option<usize>,
+
+    /// Print every meal on a single line, like `"{name}: {price.student}"`.
+    /// Placeholders: id, name, category, tags, descs, prices, price.student,
+    /// price.employee, price.pupil and price.other.
+    /// Literal braces are written as `{{` and `}}`. Has no effect on json output.
+    #[structopt(long, global = true, value_name = "FORMAT")]
+    pub meal_format: Option<MealFormat>,
+
+    /// Group the meals, like `canteen,category`. Canteens are always the
+    /// outer level, `category` adds a heading per category within every
+    /// canteen. Json output nests the meals by category, too.
+    /// Ignored by `--table`, `--markdown` and `--meal-format`.
+    #[structopt(long, global = true, value_name = "KEYS")]
+    pub group_by: Option<GroupBy>,
+
+    /// Print OpenMensa's json response for the meals as is,
+    /// without filtering or parsing it. Useful for debugging.
+    #[structopt(long, global = true, takes_value = false)]
+    pub raw: bool,
+
+    /// Print nothing if the selected meals are the same as in the last
+    /// run with this flag for the same canteens. Useful for notifications.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_ONLY_IF_CHANGED",
+        takes_value = false
+    )]
+    pub only_if_changed: bool,
+
     #[structopt(
         long,
         global = true,
@@ -129,6 +548,44 @@ pub struct MealsCommand {
     )]
     pub overwrite_filter: bool,
 
+    /// Match the name and category regexes of the filter case-insensitively.
+    /// Inline flags like `(?-i)` in a regex still take precedence.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_FILTER_CASE_INSENSITIVE",
+        takes_value = false
+    )]
+    pub filter_case_insensitive: bool,
+
+    /// How filter criteria combine: any criterion or all criteria must match.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_FILTER_MODE",
+        value_name = "MODE",
+        possible_values = &FilterMode::variants(),
+        case_insensitive = true
+    )]
+    pub filter_mode: Option<FilterMode>,
+
+    /// Hide meals without a price in any of the selected price tiers.
+    #[structopt(long, global = true, env = "MENSA_ONLY_PRICED", takes_value = false)]
+    pub only_priced: bool,
+
+    /// Hide meals cheaper than AMOUNT in all of the selected price tiers.
+    /// The boundary is inclusive, a meal costing exactly AMOUNT is shown.
+    /// AMOUNT is given in the displayed currency, meals without a
+    /// selected price are hidden.
+    #[structopt(long, global = true, env = "MENSA_MIN_PRICE", value_name = "AMOUNT")]
+    pub min_price: Option<f32>,
+
+    /// Hide meals more expensive than AMOUNT in all of the selected price tiers.
+    /// The boundary is inclusive, like for `--min-price`. Combined with it,
+    /// a meal is shown if any selected price lies within both bounds.
+    #[structopt(long, global = true, env = "MENSA_MAX_PRICE", value_name = "AMOUNT")]
+    pub max_price: Option<f32>,
+
     #[structopt(long, global = true, env = "MENSA_FILTER_NAME_ADD")]
     pub filter_name: Vec<Regex>,
 
@@ -151,6 +608,17 @@ pub struct MealsCommand {
     )]
     pub no_filter_tag: Vec<Tag>,
 
+    /// Hide meals containing the given allergen or additive.
+    ///
+    /// Only secondary tags are accepted, see `mensa tags`.
+    #[structopt(
+        long,
+        global = true,
+        env = "MENSA_NO_ALLERGEN",
+        parse(try_from_str = parse_allergen)
+    )]
+    pub no_allergen: Vec<Tag>,
+
     #[structopt(long, global = true, env = "MENSA_FILTER_CATEGORY_ADD")]
     pub filter_cat: Vec<Regex>,
 
@@ -188,10 +656,109 @@ pub struct MealsCommand {
     #[structopt(long, global = true, env = "MENSA_FAVS_CATEGORY_SUB")]
     pub no_favs_cat: Vec<Regex>,
 
+    /// Print favourites before all other meals.
+    #[structopt(long, global = true, env = "MENSA_FAVS_FIRST", takes_value = false)]
+    pub favs_first: bool,
+
+    /// A position given without `close` shows the meals of canteens around it,
+    /// unless `--id` or `--last` is given, too.
+    #[structopt(flatten)]
+    pub geo: GeoCommand,
+
     #[structopt(subcommand)]
     pub close: Option<CloseCommand>,
 }
 
+impl MealsCommand {
+    /// The position to search canteens around, if any.
+    ///
+    /// This is the position given with `close` or, if neither `--id`
+    /// nor `--last` is given, the one given directly to `meals`.
+    pub fn geo(&self) -> Option<&GeoCommand> {
+        match self.close {
+            Some(CloseCommand::Close(ref geo)) => Some(geo),
+            None if self.canteen_id.is_none() && !self.last && self.geo.is_given() => {
+                Some(&self.geo)
+            }
+            None => None,
+        }
+    }
+}
+
+/// Default search radius in km, see [`GeoCommand::radius`].
+pub const DEFAULT_RADIUS: f32 = 10.0;
+
+/// Kilometers per mile.
+const KM_PER_MILE: f64 = 1.609344;
+
+/// Maximum number of canteen ids spanned by `--id-range`.
+pub const MAX_ID_RANGE: usize = 1000;
+
+/// Maximum number of days spanned by a range given to `--date`.
+pub const MAX_DATE_RANGE: usize = 31;
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DistanceUnit {
+        Km,
+        Miles,
+    }
+}
+
+impl DistanceUnit {
+    /// Convert `distance` given in this unit to km.
+    pub fn to_km(self, distance: f32) -> f64 {
+        match self {
+            Self::Km => f64::from(distance),
+            Self::Miles => f64::from(distance) * KM_PER_MILE,
+        }
+    }
+
+    /// Convert `distance` given in km to this unit.
+    pub fn convert_km(self, distance: f32) -> f32 {
+        match self {
+            Self::Km => distance,
+            Self::Miles => (f64::from(distance) / KM_PER_MILE) as f32,
+        }
+    }
+
+    /// Short symbol for this unit.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Km => "km",
+            Self::Miles => "mi",
+        }
+    }
+}
+
+arg_enum! {
+    /// Order of the tags listed by `mensa tags`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TagSort {
+        Name,
+        Number,
+        Group,
+    }
+}
+
+arg_enum! {
+    /// Format of the log messages.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LogFormat {
+        Pretty,
+        Json,
+    }
+}
+
+arg_enum! {
+    /// Storage used by the cache.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CacheBackend {
+        Memory,
+        Disk,
+    }
+}
+
 arg_enum! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
     pub enum ColorWhen {
@@ -201,10 +768,215 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Theme {
+        Dark,
+        Light,
+        Auto,
+    }
+}
+
+impl Theme {
+    /// Replace `Auto` by the theme matching the background in `colorfgbg`.
+    ///
+    /// `$COLORFGBG` looks like `15;0`, the last number being the background
+    /// color. 7 and 9 to 15 are light colors.
+    pub fn resolve(self, colorfgbg: Option<&str>) -> Self {
+        match self {
+            Self::Auto => {
+                let background = colorfgbg
+                    .and_then(|raw| raw.rsplit(';').next())
+                    .and_then(|bg| bg.trim().parse::<u8>().ok());
+                match background {
+                    Some(7 | 9..=15) => Self::Light,
+                    _ => Self::Dark,
+                }
+            }
+            theme => theme,
+        }
+    }
+}
+
 pub fn parse_human_date(inp: &str) -> Result<NaiveDate> {
     date_time_parser::DateParser::parse(inp).ok_or(Error::InvalidDateInArgs)
 }
 
+/// Parse a secondary [`Tag`], i.e. an allergen or additive.
+pub fn parse_allergen(inp: &str) -> Result<Tag> {
+    match serde_plain::from_str::<Tag>(inp) {
+        Ok(tag) if tag.is_secondary() => Ok(tag),
+        _ => Err(Error::InvalidAllergen(inp.to_owned())),
+    }
+}
+
+/// One or more dates given by `--date`, never empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dates(pub Vec<NaiveDate>);
+
+/// A template for a single canteen, given by `--canteen-format`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanteenFormat(pub Template);
+
+impl FromStr for CanteenFormat {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        Template::parse(raw, Canteen::PLACEHOLDERS).map(Self)
+    }
+}
+
+/// A template for a single meal, given by `--meal-format`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MealFormat(pub Template);
+
+impl FromStr for MealFormat {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        Template::parse(raw, MealComplete::PLACEHOLDERS).map(Self)
+    }
+}
+
+/// Levels by which meals are grouped, given by `--group-by`.
+///
+/// Meals are always grouped by canteen first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupBy {
+    /// Group the meals of every canteen by category.
+    pub category: bool,
+}
+
+impl FromStr for GroupBy {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let keys: Vec<_> = raw.split(',').map(str::trim).collect();
+        match keys[..] {
+            ["canteen"] => Ok(GroupBy { category: false }),
+            ["category"] | ["canteen", "category"] => Ok(GroupBy { category: true }),
+            _ => Err(Error::InvalidGroupBy(raw.to_owned())),
+        }
+    }
+}
+
+/// A JSON Pointer given by `--select`, see RFC 6901.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPointer(pub String);
+
+impl JsonPointer {
+    /// Take the value referenced by this pointer out of `value`.
+    pub fn select(&self, mut value: serde_json::Value) -> Result<serde_json::Value> {
+        value
+            .pointer_mut(&self.0)
+            .map(serde_json::Value::take)
+            .ok_or_else(|| Error::JsonPointerNotFound(self.0.clone()))
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        // Every `~` must be escaped as `~0` or `~1`
+        let valid_escapes = raw
+            .match_indices('~')
+            .all(|(idx, _)| matches!(raw.as_bytes().get(idx + 1), Some(b'0' | b'1')));
+        if (raw.is_empty() || raw.starts_with('/')) && valid_escapes {
+            Ok(Self(raw.to_owned()))
+        } else {
+            Err(Error::InvalidJsonPointer(raw.to_owned()))
+        }
+    }
+}
+
+/// An inclusive range of canteen ids given by `--id-range`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdRange(pub RangeInclusive<CanteenId>);
+
+impl FromStr for IdRange {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let invalid = || Error::InvalidIdRange(raw.to_owned());
+        let (start, end) = raw.split_once('-').ok_or_else(invalid)?;
+        let start = start.trim().parse().map_err(|_| invalid())?;
+        let end = end.trim().parse().map_err(|_| invalid())?;
+        if end < start {
+            return Err(invalid());
+        }
+        if end - start >= MAX_ID_RANGE {
+            return Err(Error::IdRangeTooLarge(raw.to_owned(), MAX_ID_RANGE));
+        }
+        Ok(Self(start..=end))
+    }
+}
+
+/// A canteen given by its numeric id or a slug of its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanteenRef {
+    Id(CanteenId),
+    Slug(String),
+}
+
+impl FromStr for CanteenRef {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.trim().parse() {
+            Ok(id) => Ok(Self::Id(id)),
+            Err(_) => match slugify(raw) {
+                slug if slug.is_empty() => Err(Error::InvalidCanteenSlug(raw.to_owned())),
+                slug => Ok(Self::Slug(slug)),
+            },
+        }
+    }
+}
+
+/// Derive a slug from a canteen `name`, i.e. `Mensa am Park` becomes `mensa-am-park`.
+pub fn slugify(name: &str) -> String {
+    let lowercase = name
+        .to_lowercase()
+        .replace('ä', "ae")
+        .replace('ö', "oe")
+        .replace('ü', "ue")
+        .replace('ß', "ss");
+    lowercase
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl FromStr for Dates {
+    type Err = Error;
+
+    /// Parse a single date, a comma separated list or an inclusive range.
+    ///
+    /// Every date may be an ISO date or anything accepted by [`parse_human_date`].
+    fn from_str(raw: &str) -> Result<Self> {
+        let parse_date = |raw: &str| {
+            let raw = raw.trim();
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d").or_else(|_| parse_human_date(raw))
+        };
+        if let Some((start, end)) = raw.split_once("..") {
+            let start = parse_date(start)?;
+            let end = parse_date(end)?;
+            if end < start {
+                return Err(Error::InvalidDateInArgs);
+            }
+            if (end - start).num_days() >= MAX_DATE_RANGE as i64 {
+                return Err(Error::DateRangeTooLarge(raw.to_owned(), MAX_DATE_RANGE));
+            }
+            let dates = start.iter_days().take_while(|day| *day <= end).collect();
+            Ok(Self(dates))
+        } else {
+            let dates = raw.split(',').map(parse_date).collect::<Result<_>>()?;
+            Ok(Self(dates))
+        }
+    }
+}
+
 impl Default for Command {
     fn default() -> Self {
         Self::Meals(Default::default())
@@ -214,14 +986,32 @@ impl Default for Command {
 impl Default for MealsCommand {
     fn default() -> Self {
         MealsCommand {
-            date: parse_human_date("today").unwrap(),
+            date: "today".parse().unwrap(),
+            also_tomorrow: false,
+            suggest_open: false,
             canteen_id: None,
+            last: false,
             price: None,
+            all_prices: false,
+            table: false,
+            markdown: false,
+            first_open: false,
+            nearest: None,
+            meal_format: None,
+            group_by: None,
+            raw: false,
+            only_if_changed: false,
             overwrite_filter: false,
+            filter_case_insensitive: false,
+            filter_mode: None,
+            only_priced: false,
+            min_price: None,
+            max_price: None,
             filter_name: vec![],
             no_filter_name: vec![],
             filter_tag: vec![],
             no_filter_tag: vec![],
+            no_allergen: vec![],
             filter_cat: vec![],
             no_filter_cat: vec![],
             overwrite_favs: false,
@@ -231,6 +1021,15 @@ impl Default for MealsCommand {
             no_favs_tag: vec![],
             favs_cat: vec![],
             no_favs_cat: vec![],
+            favs_first: false,
+            geo: GeoCommand {
+                lat: None,
+                long: None,
+                city: None,
+                postal: None,
+                radius: DEFAULT_RADIUS,
+                unit: DistanceUnit::Km,
+            },
             close: None,
         }
     }
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+/// How prices are formatted.
+///
+/// Defaults to `x.xx€`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Currency {
+    pub symbol: String,
+    pub position: CurrencyPosition,
+    pub decimals: usize,
+    /// Multiplier applied to euro prices, no conversion happens if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversion_rate: Option<f32>,
+}
+
+/// Where the currency symbol is placed relative to the amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CurrencyPosition {
+    Prefix,
+    Suffix,
+}
+
+impl Currency {
+    /// Format the euro `amount` in this currency.
+    pub fn format(&self, amount: f32) -> String {
+        self.with_symbol(format!("{:.*}", self.decimals, self.convert(amount)))
+    }
+
+    /// Convert the euro `amount` to this currency.
+    ///
+    /// The result is rounded to the configured decimals.
+    pub fn convert(&self, amount: f32) -> f32 {
+        match self.conversion_rate {
+            Some(rate) => {
+                let factor = 10_f32.powi(self.decimals as i32);
+                (amount * rate * factor).round() / factor
+            }
+            None => amount,
+        }
+    }
+
+    /// Placeholder for a missing price, i.e. `-.--€`.
+    pub fn missing(&self) -> String {
+        let amount = match self.decimals {
+            0 => String::from("-"),
+            decimals => format!("-.{}", "-".repeat(decimals)),
+        };
+        self.with_symbol(amount)
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn with_symbol(&self, amount: String) -> String {
+        match self.position {
+            CurrencyPosition::Prefix => format!("{}{}", self.symbol, amount),
+            CurrencyPosition::Suffix => format!("{}{}", amount, self.symbol),
+        }
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self {
+            symbol: String::from("€"),
+            position: CurrencyPosition::Suffix,
+            decimals: 2,
+            conversion_rate: None,
+        }
+    }
+}
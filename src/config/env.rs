@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use std::{convert::TryFrom, env};
+
+use crate::error::{Error, Result};
+
+/// A string from the configuration that may reference environment variables.
+///
+/// Variables are written like `${HOME}` and expanded when the configuration
+/// is loaded. The raw string is kept, so saving the configuration does not
+/// replace the variables with their values.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EnvString {
+    raw: String,
+    expanded: String,
+}
+
+impl EnvString {
+    /// The string with all variables replaced by their values.
+    pub fn expanded(&self) -> &str {
+        &self.expanded
+    }
+}
+
+impl TryFrom<String> for EnvString {
+    type Error = Error;
+
+    fn try_from(raw: String) -> Result<Self> {
+        let expanded = expand_with(&raw, |name| env::var(name).ok())?;
+        Ok(EnvString { raw, expanded })
+    }
+}
+
+impl From<EnvString> for String {
+    fn from(string: EnvString) -> Self {
+        string.raw
+    }
+}
+
+/// Replace every `${NAME}` in `raw` with the value returned by `lookup`.
+///
+/// A `$` that does not start a reference is kept as is.
+pub(super) fn expand_with<F>(raw: &str, lookup: F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut expanded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| Error::InvalidConfigVariable(rest[start..].to_owned()))?;
+        let name = &after[..end];
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(Error::InvalidConfigVariable(format!("${{{}}}", name)));
+        }
+        let value = lookup(name).ok_or_else(|| Error::UnsetConfigVariable(name.to_owned()))?;
+        expanded.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
@@ -0,0 +1,67 @@
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use crate::error::Error;
+
+/// Icons prefixing the category of matching meals.
+///
+/// Configured as a table from category regexes to icons.
+/// If multiple regexes match, the first one in lexicographical order wins.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct CategoryIcons {
+    regexes: RegexSet,
+    icons: Vec<String>,
+}
+
+impl CategoryIcons {
+    /// The icon for `category`, if any regex matches.
+    pub fn get(&self, category: &str) -> Option<&str> {
+        let idx = self.regexes.matches(category).into_iter().next()?;
+        Some(&self.icons[idx])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+}
+
+impl Default for CategoryIcons {
+    fn default() -> Self {
+        Self {
+            regexes: RegexSet::empty(),
+            icons: vec![],
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for CategoryIcons {
+    type Error = Error;
+
+    fn try_from(raw: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        // Compile each key on its own first, so the error names the broken one
+        for key in raw.keys() {
+            Regex::new(key).map_err(|why| Error::InvalidIconRegex(key.clone(), why))?;
+        }
+        let regexes = RegexSet::new(raw.keys()).expect("every regex compiled on its own");
+        let icons = raw.into_values().collect();
+        Ok(Self { regexes, icons })
+    }
+}
+
+impl From<CategoryIcons> for BTreeMap<String, String> {
+    fn from(icons: CategoryIcons) -> Self {
+        icons
+            .regexes
+            .patterns()
+            .iter()
+            .cloned()
+            .zip(icons.icons)
+            .collect()
+    }
+}
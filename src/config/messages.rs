@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Messages printed in place of meals.
+///
+/// Defaults to the english messages.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Messages {
+    /// No meal is selected by the filter.
+    pub no_meals: String,
+    /// The canteen is closed that day.
+    pub closed: String,
+    /// The day is not part of the canteen's published schedule.
+    pub not_scheduled: String,
+}
+
+impl Messages {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Self {
+            no_meals: String::from("no matching meals found"),
+            closed: String::from("closed"),
+            not_scheduled: String::from("not in the published schedule"),
+        }
+    }
+}
@@ -1,24 +1,53 @@
-use chrono::NaiveDate;
+use chrono::{Duration, NaiveDate};
 use lazy_static::lazy_static;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use structopt::{clap::arg_enum, StructOpt};
+use tracing::{info, warn};
 
-use std::{collections::HashSet, fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
 use crate::{
     canteen::CanteenId,
     config::args::{parse_human_date, Command},
     error::{Error, Result, ResultExt},
-    DIR,
+    geoip::{DEFAULT_GEOCODING_URL, TTL_GEOIP},
+    state::State,
+    tag::Tag,
+    DIR, TTL_MEALS, TTL_NOT_FOUND,
 };
 
 use self::{
-    args::{Args, MealsCommand},
-    rule::{RegexRule, Rule, TagRule},
+    args::{Args, CanteenRef, MealFormat, MealsCommand},
+    rule::{FilterMode, RegexRule, Rule, TagRule},
 };
 
+mod aliases;
 pub mod args;
+mod currency;
+mod env;
+mod icons;
+mod messages;
 pub mod rule;
+#[cfg(test)]
+mod tests;
+mod ttl;
+
+pub use self::{
+    aliases::TagAliases,
+    currency::Currency,
+    env::EnvString,
+    icons::CategoryIcons,
+    messages::Messages,
+    ttl::{CanteenTtls, Ttl},
+};
+
+/// The documented example configuration, see [`ConfigFile::template`].
+const EXAMPLE_CONFIG: &str = include_str!("../../config.toml");
 
 lazy_static! {
     pub static ref CONF: Config = Config::assemble();
@@ -28,15 +57,68 @@ lazy_static! {
 pub struct Config {
     pub config: Option<ConfigFile>,
     pub args: Args,
+    /// Paths of the layered configuration files, they may not exist.
+    pub paths: Vec<PathBuf>,
+    /// Path of the last configuration file, which is written to.
+    pub path: PathBuf,
 }
 
 impl Config {
+    /// Make sure the profile requested with `--profile` exists.
+    pub fn ensure_profile(&self) -> Result<()> {
+        match self.args.profile {
+            Some(ref name)
+                if !self
+                    .config
+                    .as_ref()
+                    .map_or(false, |config| config.profiles.contains_key(name)) =>
+            {
+                Err(Error::UnknownProfile(name.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn assemble() -> Self {
+        #[cfg(not(test))]
         let args = Args::from_args();
-        let default_config_path = || DIR.config_dir().join("config.toml");
-        let path = args.config.clone().unwrap_or_else(default_config_path);
-        let config = ConfigFile::load_or_log(path);
-        Config { config, args }
+        // Tests must not depend on the arguments given to the test binary
+        #[cfg(test)]
+        let args = Args::from_iter(&["mensa"]);
+        Self::from_args(args)
+    }
+
+    /// Assemble the configuration for the given `args`.
+    ///
+    /// The configuration files are loaded as the CLI would.
+    pub fn from_args(mut args: Args) -> Self {
+        // Applies to every command, not only the meals
+        args.json |= args.json_full;
+        let paths = if args.config.is_empty() {
+            vec![DIR.config_dir().join("config.toml")]
+        } else {
+            args.config.clone()
+        };
+        // There is always at least one path
+        let path = paths[paths.len() - 1].clone();
+        // Inline configuration takes precedence over the files
+        let config = match args.config_toml {
+            Some(ref raw) => ConfigFile::parse_or_log(raw),
+            None => ConfigFile::load_layered_or_log(&paths),
+        };
+        // Missing profiles are reported by `Config::ensure_profile`
+        let config = match (config, &args.profile) {
+            (Some(config), Some(name)) if config.profiles.contains_key(name) => config
+                .with_profile(name, |key| warn!("Unknown key {:?} in configuration", key))
+                .log_err(),
+            (config, _) => config,
+        };
+        Config {
+            config,
+            args,
+            paths,
+            path,
+        }
     }
 
     /// Easy reference to the Command
@@ -46,41 +128,256 @@ impl Config {
         }
         match self.args.command {
             Some(ref cmd) => cmd,
-            None => &*DEFAULT,
+            None => &DEFAULT,
         }
     }
 
-    pub fn canteen_id(&self) -> Result<CanteenId> {
+    /// The canteen given by `--id`, or the configured default canteen.
+    ///
+    /// With `--last`, the last used canteen is read from the [`State`].
+    pub fn canteen_id(&self) -> Result<CanteenRef> {
+        if matches!(self.cmd(), Command::Meals(cmd) if cmd.last) {
+            let state = State::load(&State::path())?;
+            let id = state.last_canteen_id.ok_or(Error::NoLastCanteen)?;
+            return Ok(CanteenRef::Id(id));
+        }
         // Get the default canteen id from the config file
-        let default = || self.config.as_ref()?.default_canteen_id;
+        let default = || Some(CanteenRef::Id(self.config.as_ref()?.default_canteen_id?));
         let id = match self.cmd() {
-            Command::Meals(cmd) => cmd.canteen_id,
+            Command::Meals(cmd) => cmd.canteen_id.clone(),
+            Command::Tags(cmd) => cmd.canteen_id.clone(),
+            Command::Days(cmd) => cmd.canteen_id.clone(),
             _ => None,
         };
         id.or_else(default).ok_or(Error::CanteenIdMissing)
     }
 
+    /// The first day for which to print meals, see [`Self::dates`].
     pub fn date(&self) -> &NaiveDate {
         lazy_static! {
             static ref DEFAULT: NaiveDate = parse_human_date("today").unwrap();
         }
         match self.cmd() {
-            Command::Meals(cmd) => &cmd.date,
-            _ => &*DEFAULT,
+            Command::Meals(cmd) => &cmd.date.0[0],
+            _ => &DEFAULT,
+        }
+    }
+
+    /// All days for which to print meals.
+    ///
+    /// With `--also-tomorrow`, the day after the last date is included.
+    pub fn dates(&self) -> Vec<NaiveDate> {
+        match self.cmd() {
+            Command::Meals(cmd) => {
+                let mut dates = cmd.date.0.clone();
+                if cmd.also_tomorrow {
+                    let last = dates[dates.len() - 1];
+                    dates.push(last + Duration::days(1));
+                }
+                // Drop repeated dates, keeping the order they were given in
+                let mut seen = HashSet::new();
+                dates.retain(|day| seen.insert(*day));
+                dates
+            }
+            _ => vec![*self.date()],
         }
     }
 
+    /// The price tags to display, an empty set selects all of them.
+    ///
+    /// Tags given with `--price` win over `--all-prices`,
+    /// which wins over the configuration.
     pub fn price_tags(&self) -> HashSet<PriceTags> {
         let from_file = || Some(self.config.as_ref()?.price_tags.clone());
         match self.cmd() {
             Command::Meals(cmd) => match cmd.price.clone() {
                 Some(prices) => prices.into_iter().collect(),
+                // No tags select all of them
+                None if cmd.all_prices => HashSet::new(),
                 None => from_file().unwrap_or_default(),
             },
             _ => from_file().unwrap_or_default(),
         }
     }
 
+    /// Whether favourites should be printed before all other meals.
+    pub fn favs_first(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.favs_first,
+            _ => false,
+        }
+    }
+
+    /// How prices are formatted.
+    pub fn currency(&self) -> &Currency {
+        lazy_static! {
+            static ref DEFAULT: Currency = Currency::default();
+        }
+        match self.config {
+            Some(ref config) => &config.currency,
+            None => &DEFAULT,
+        }
+    }
+
+    /// Messages printed in place of meals.
+    pub fn messages(&self) -> &Messages {
+        lazy_static! {
+            static ref DEFAULT: Messages = Messages::default();
+        }
+        match self.config {
+            Some(ref config) => &config.messages,
+            None => &DEFAULT,
+        }
+    }
+
+    /// The configured display name of `tag`, if any.
+    pub fn tag_alias(&self, tag: Tag) -> Option<&str> {
+        self.config.as_ref()?.tag_aliases.get(tag)
+    }
+
+    /// The configured icon for meals in `category`, if any.
+    pub fn category_icon(&self, category: &str) -> Option<&str> {
+        self.config.as_ref()?.category_icons.get(category)
+    }
+
+    /// Whether meals without a selected price should be hidden.
+    pub fn only_priced(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.only_priced,
+            _ => false,
+        }
+    }
+
+    /// Minimal price of meals in the displayed currency, see `--min-price`.
+    pub fn min_price(&self) -> Option<f32> {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.min_price,
+            _ => None,
+        }
+    }
+
+    /// Maximal price of meals in the displayed currency, see `--max-price`.
+    pub fn max_price(&self) -> Option<f32> {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.max_price,
+            _ => None,
+        }
+    }
+
+    /// Whether to print meals as a table.
+    pub fn table(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.table,
+            _ => false,
+        }
+    }
+
+    /// Whether to print meals as Markdown.
+    pub fn markdown(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.markdown,
+            _ => false,
+        }
+    }
+
+    /// Template for printing meals on a single line, if any.
+    pub fn meal_format(&self) -> Option<&MealFormat> {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.meal_format.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether to group the meals of every canteen by category.
+    pub fn group_by_category(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.group_by.map_or(false, |group_by| group_by.category),
+            _ => false,
+        }
+    }
+
+    /// Whether to print the unparsed responses of the meals endpoint.
+    pub fn raw(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.raw,
+            _ => false,
+        }
+    }
+
+    /// Whether to print meals only if they changed since the last run.
+    pub fn only_if_changed(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.only_if_changed,
+            _ => false,
+        }
+    }
+
+    /// Whether to hint at the next weekday for dates on weekends.
+    pub fn suggest_open(&self) -> bool {
+        match self.cmd() {
+            Command::Meals(cmd) => cmd.suggest_open,
+            _ => false,
+        }
+    }
+
+    /// Endpoint used to geocode cities and postal codes.
+    pub fn geocoding_url(&self) -> String {
+        let from_file = || Some(self.config.as_ref()?.geocoding_url.as_ref()?.expanded());
+        from_file().unwrap_or(DEFAULT_GEOCODING_URL).to_owned()
+    }
+
+    /// Directory of the disk cache, given by `--cache-dir` or the default location.
+    pub fn cache_dir(&self) -> PathBuf {
+        match self.args.cache_dir {
+            Some(ref path) => path.clone(),
+            None => DIR.cache_dir().to_owned(),
+        }
+    }
+
+    /// Print the path requested with `--print-cache-dir` or `--print-config-path`.
+    ///
+    /// Returns whether a path was printed.
+    pub fn print_requested_path(&self) -> Result<bool> {
+        let path = if self.args.print_cache_dir {
+            self.cache_dir()
+        } else if self.args.print_config_path {
+            self.path.clone()
+        } else {
+            return Ok(false);
+        };
+        try_println!("{}", path.display())?;
+        Ok(true)
+    }
+
+    /// Whether guessing the location via geoip is forbidden.
+    pub fn geoip_disabled(&self) -> bool {
+        let from_file = || Some(self.config.as_ref()?.disable_geoip);
+        self.args.no_geoip || from_file().unwrap_or_default()
+    }
+
+    /// How long the location guessed via geoip is cached.
+    pub fn geoip_ttl(&self) -> Duration {
+        let from_file = || self.config.as_ref()?.geoip_ttl;
+        from_file().map(|ttl| ttl.0).unwrap_or(*TTL_GEOIP)
+    }
+
+    /// How long resources that were not found are remembered as missing.
+    pub fn not_found_ttl(&self) -> Duration {
+        let from_file = || self.config.as_ref()?.not_found_ttl;
+        from_file().map(|ttl| ttl.0).unwrap_or(*TTL_NOT_FOUND)
+    }
+
+    /// How long the meals of canteen `id` are cached.
+    ///
+    /// Overrides from the config take precedence over the default.
+    pub fn meals_ttl(&self, id: CanteenId) -> Duration {
+        let from_file = || {
+            let overrides = &self.config.as_ref()?.canteen_ttl_overrides;
+            overrides.get(id)
+        };
+        from_file().map(|ttl| ttl.0).unwrap_or(*TTL_MEALS)
+    }
+
     pub fn get_filter_rule(&self) -> Rule {
         match self.cmd() {
             Command::Meals(cmd) => {
@@ -89,14 +386,23 @@ impl Config {
                     name: RegexRule::from_arg_parts(&cmd.filter_name, &cmd.no_filter_name),
                     tag: TagRule {
                         add: cmd.filter_tag.clone(),
-                        sub: cmd.no_filter_tag.clone(),
+                        sub: [&cmd.no_filter_tag[..], &cmd.no_allergen[..]].concat(),
                     },
                     category: RegexRule::from_arg_parts(&cmd.filter_cat, &cmd.no_filter_cat),
+                    mode: FilterMode::default(),
                 };
-                if cmd.overwrite_filter {
+                let conf_mode = || self.config.as_ref()?.filter_mode;
+                let mode = cmd.filter_mode.or_else(conf_mode).unwrap_or_default();
+                let filter = if cmd.overwrite_filter {
                     args_filter
                 } else {
                     conf_filter().unwrap_or_default().joined(args_filter)
+                };
+                let filter = Rule { mode, ..filter };
+                if cmd.filter_case_insensitive {
+                    filter.case_insensitive()
+                } else {
+                    filter
                 }
             }
             _ => {
@@ -116,6 +422,7 @@ impl Config {
                         sub: cmd.no_favs_tag.clone(),
                     },
                     category: RegexRule::from_arg_parts(&cmd.favs_cat, &cmd.no_favs_cat),
+                    mode: FilterMode::default(),
                 };
                 if cmd.overwrite_favs {
                     args_favs
@@ -128,20 +435,42 @@ impl Config {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all(deserialize = "kebab-case"))]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct ConfigFile {
     #[serde(default)]
     default_canteen_id: Option<usize>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     price_tags: HashSet<PriceTags>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Rule::is_empty")]
     filter: Rule,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filter_mode: Option<FilterMode>,
+    #[serde(default, skip_serializing_if = "Rule::is_empty")]
     favs: Rule,
+    #[serde(default)]
+    geocoding_url: Option<EnvString>,
+    #[serde(default)]
+    geoip_ttl: Option<Ttl>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    disable_geoip: bool,
+    #[serde(default)]
+    not_found_ttl: Option<Ttl>,
+    #[serde(default, skip_serializing_if = "CanteenTtls::is_empty")]
+    canteen_ttl_overrides: CanteenTtls,
+    #[serde(default, skip_serializing_if = "Currency::is_default")]
+    currency: Currency,
+    #[serde(default, skip_serializing_if = "CategoryIcons::is_empty")]
+    category_icons: CategoryIcons,
+    #[serde(default, skip_serializing_if = "Messages::is_default")]
+    messages: Messages,
+    #[serde(default, skip_serializing_if = "TagAliases::is_empty")]
+    tag_aliases: TagAliases,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    profiles: HashMap<String, toml::value::Table>,
 }
 arg_enum! {
-    #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Deserialize)]
+    #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Deserialize, Serialize)]
     pub enum PriceTags {
         Student,
         Employee,
@@ -150,13 +479,250 @@ arg_enum! {
     }
 }
 
+impl PriceTags {
+    /// The order in which prices are displayed.
+    pub const ORDER: [PriceTags; 4] = [Self::Student, Self::Employee, Self::Pupil, Self::Other];
+}
+
 impl ConfigFile {
     pub fn load_or_log<P: AsRef<Path>>(path: P) -> Option<Self> {
         let file = fs::read_to_string(path)
             .map_err(Error::ReadingConfig)
             .log_warn()?;
-        toml::from_str(&file)
+        Self::parse_or_log(&file)
+    }
+
+    /// Load the configuration files at `paths`, see [`Self::parse_layered`].
+    ///
+    /// Files that can't be read are skipped with a warning.
+    pub fn load_layered_or_log(paths: &[PathBuf]) -> Option<Self> {
+        let files: Vec<_> = paths
+            .iter()
+            .filter_map(|path| {
+                fs::read_to_string(path)
+                    .map_err(Error::ReadingConfig)
+                    .log_warn()
+            })
+            .collect();
+        if files.is_empty() {
+            return None;
+        }
+        Self::parse_layered(&files, |key| {
+            warn!("Unknown key {:?} in configuration", key)
+        })
+        .log_err()
+    }
+
+    /// Parse the configuration, logging unknown keys and errors.
+    pub fn parse_or_log(raw: &str) -> Option<Self> {
+        Self::parse(raw, |key| warn!("Unknown key {:?} in configuration", key)).log_err()
+    }
+
+    /// Parse the configuration from toml.
+    ///
+    /// Every key that is not part of the configuration is passed to `unknown`.
+    pub fn parse<F>(raw: &str, mut unknown: F) -> Result<Self>
+    where
+        F: FnMut(String),
+    {
+        let mut de = toml::Deserializer::new(raw);
+        serde_ignored::deserialize(&mut de, |path| unknown(path.to_string()))
             .map_err(Error::DeserializingConfig)
-            .log_err()
+    }
+
+    /// Parse the configuration from several toml `files`, later files taking precedence.
+    ///
+    /// - `filter` and `favs` of all files are joined, see [`Rule::joined`].
+    /// - Every other key given in a later file replaces its value from earlier files.
+    ///   Tables like `[currency]` are merged key by key, lists are replaced as a whole.
+    ///
+    /// Every key that is not part of the configuration is passed to `unknown`.
+    pub fn parse_layered<F>(files: &[String], mut unknown: F) -> Result<Self>
+    where
+        F: FnMut(String),
+    {
+        let mut table = toml::value::Table::new();
+        let (mut filter, mut favs) = (Rule::default(), Rule::default());
+        for file in files {
+            let config = Self::parse(file, &mut unknown)?;
+            filter = filter.joined(config.filter);
+            favs = favs.joined(config.favs);
+            let layer = toml::from_str(file).map_err(Error::DeserializingConfig)?;
+            merge_tables(&mut table, layer);
+        }
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .map_err(Error::DeserializingConfig)?;
+        config.filter = filter;
+        config.favs = favs;
+        Ok(config)
+    }
+
+    /// Merge the profile `name` over this configuration.
+    ///
+    /// Keys not set in the profile keep their base value.
+    /// Every key in the profile that is not part of the configuration is passed to `unknown`.
+    pub fn with_profile<F>(self, name: &str, mut unknown: F) -> Result<Self>
+    where
+        F: FnMut(String),
+    {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::UnknownProfile(name.into()))?;
+        let mut table = toml::value::Table::new();
+        if let toml::Value::Table(base) =
+            toml::Value::try_from(&self).map_err(Error::SerializingConfig)?
+        {
+            table = base;
+        }
+        merge_tables(&mut table, profile);
+        serde_ignored::deserialize(toml::Value::Table(table), |path| {
+            unknown(format!("profiles.{}.{}", name, path))
+        })
+        .map_err(Error::DeserializingConfig)
+    }
+
+    /// Validate the configuration files.
+    ///
+    /// Prints unknown keys and the location of errors, if possible.
+    /// Every profile is merged over the base configuration and checked, too.
+    /// Layered files are checked one by one, stopping at the first invalid file.
+    /// Returns the error if the configuration is invalid.
+    pub fn validate() -> Result<()> {
+        let mut out = io::stdout();
+        match CONF.args.config_toml {
+            Some(ref raw) => Self::validate_file(&mut out, "Inline configuration", raw),
+            None => {
+                for path in &CONF.paths {
+                    let file = fs::read_to_string(path).map_err(Error::ReadingConfig)?;
+                    Self::validate_file(&mut out, &path.display().to_string(), &file)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Validate a single configuration `file`, printing the report to `out`.
+    fn validate_file<W: Write>(out: &mut W, source: &str, file: &str) -> Result<()> {
+        macro_rules! report {
+            ($($args:tt)*) => {
+                writeln!(out, $($args)*).map_err(|why| Error::Io(why, "printing"))
+            };
+        }
+        let mut unknown = vec![];
+        let res = Self::parse(file, |key| unknown.push(key)).and_then(|config| {
+            let mut names: Vec<_> = config.profiles.keys().cloned().collect();
+            names.sort();
+            for name in names {
+                config
+                    .clone()
+                    .with_profile(&name, |key| unknown.push(key))?;
+            }
+            Ok(config)
+        });
+        for key in unknown {
+            report!(
+                "{} unknown key {:?}",
+                color!("warning:"; bright_yellow, bold),
+                key
+            )?;
+        }
+        match res {
+            Ok(_) => report!("{} is valid", source),
+            Err(Error::DeserializingConfig(why)) => {
+                if let Some((line, col)) = why.line_col() {
+                    let content = file.lines().nth(line).unwrap_or_default();
+                    report!("{:>4} | {}", line + 1, content)?;
+                    report!("{:>4} | {}^", "", " ".repeat(col))?;
+                }
+                Err(Error::DeserializingConfig(why))
+            }
+            Err(why) => Err(why),
+        }
+    }
+
+    /// Save the configuration to `path`.
+    ///
+    /// Keys in an existing file are kept, unless they're overwritten
+    /// by this configuration. Comments are lost.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut table = match fs::read_to_string(path) {
+            Ok(file) => toml::from_str(&file).map_err(Error::DeserializingConfig)?,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => toml::value::Table::new(),
+            Err(why) => return Err(Error::ReadingConfig(why)),
+        };
+        if let toml::Value::Table(ours) =
+            toml::Value::try_from(self).map_err(Error::SerializingConfig)?
+        {
+            merge_tables(&mut table, ours);
+        }
+        let content =
+            toml::to_string_pretty(&toml::Value::Table(table)).map_err(Error::SerializingConfig)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::WritingConfig)?;
+        }
+        fs::write(path, content).map_err(Error::WritingConfig)?;
+        info!("Saved configuration to {:?}", path);
+        Ok(())
+    }
+
+    /// A documented configuration file with all options commented out.
+    ///
+    /// Deserializing this yields the default configuration.
+    pub fn template() -> String {
+        let mut template = String::from("# Uncomment and adjust the options you need.\n");
+        for line in EXAMPLE_CONFIG.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                template += line;
+            } else {
+                template += "# ";
+                template += line;
+            }
+            template += "\n";
+        }
+        template
+    }
+
+    /// Write the [`Self::template`] to the configuration path.
+    ///
+    /// Refuses to overwrite an existing file unless `force` is set.
+    pub fn init(force: bool) -> Result<()> {
+        let path = &CONF.path;
+        if path.exists() && !force {
+            return Err(Error::ConfigExists(path.clone()));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::WritingConfig)?;
+        }
+        fs::write(path, Self::template()).map_err(Error::WritingConfig)?;
+        try_println!("Wrote configuration to {}", path.display())
+    }
+
+    /// Save `id` as the default canteen id in the configuration file of `conf`.
+    ///
+    /// Only the id is written, values from other layered files are not copied.
+    pub fn save_default_canteen_id(conf: &Config, id: CanteenId) -> Result<()> {
+        let config = ConfigFile {
+            default_canteen_id: Some(id),
+            ..ConfigFile::default()
+        };
+        config.save(&conf.path)
+    }
+}
+
+/// Recursively merge `other` into `table`, overwriting existing values.
+fn merge_tables(table: &mut toml::value::Table, other: toml::value::Table) {
+    for (key, value) in other {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => {
+                merge_tables(existing, value)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
     }
 }
@@ -1,6 +1,8 @@
+use itertools::Itertools;
 use regex::{Regex, RegexSet};
-use serde::Deserialize;
-use std::convert::TryFrom;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt};
+use structopt::clap::arg_enum;
 
 use crate::{
     error::{Error, Result},
@@ -8,17 +10,41 @@ use crate::{
     tag::Tag,
 };
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Rule {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "RegexRule::is_empty")]
     pub name: RegexRule,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "TagRule::is_empty")]
     pub tag: TagRule,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "RegexRule::is_empty")]
     pub category: RegexRule,
+    /// How the criteria combine, this is configured via `filter-mode`.
+    #[serde(skip)]
+    pub mode: FilterMode,
+}
+
+arg_enum! {
+    /// How the added criteria of a [`Rule`] combine.
+    ///
+    /// - `Any`: At least one criterion must match.
+    /// - `All`: Every non-empty criterion must match.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum FilterMode {
+        Any,
+        All,
+    }
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+// `arg_enum!` does not accept `#[default]` on variants
+#[allow(clippy::derivable_impls)]
+impl Default for FilterMode {
+    fn default() -> Self {
+        Self::Any
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct TagRule {
     #[serde(default)]
     pub add: Vec<Tag>,
@@ -26,14 +52,14 @@ pub struct TagRule {
     pub sub: Vec<Tag>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
-#[serde(try_from = "RawRegexRule")]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(try_from = "RawRegexRule", into = "RawRegexRule")]
 pub struct RegexRule {
     pub add: Option<RegexSet>,
     pub sub: Option<RegexSet>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 struct RawRegexRule {
     #[serde(default)]
     pub add: Vec<String>,
@@ -41,17 +67,86 @@ struct RawRegexRule {
     pub sub: Vec<String>,
 }
 
+/// Why a [`Rule`] matched a meal or not, see [`Rule::decide`].
+///
+/// Components are named like `tag add` or `name sub`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub is_match: bool,
+    /// Non-empty add components and whether they matched.
+    pub adds: Vec<(&'static str, bool)>,
+    /// Sub components that matched.
+    pub subs: Vec<&'static str>,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let components = |matched: bool| {
+            self.adds
+                .iter()
+                .filter(|(_, is_match)| *is_match == matched)
+                .map(|(component, _)| component)
+                .join(", ")
+        };
+        if !self.subs.is_empty() {
+            write!(f, "dropped by {}", self.subs.join(", "))
+        } else if self.adds.is_empty() {
+            write!(f, "kept, nothing to add")
+        } else if self.is_match {
+            write!(f, "kept by {}", components(true))
+        } else {
+            write!(f, "dropped, {} did not match", components(false))
+        }
+    }
+}
+
 impl Rule {
     pub fn is_match(&self, meal: &MealComplete) -> bool {
-        let all_adds_empty =
-            self.tag.is_empty_add() && self.category.is_empty_add() && self.name.is_empty_add();
-        let any_add = self.tag.is_match_add(meal)
-            || self.category.is_match_add(meal)
-            || self.name.is_match_add(meal);
-        let any_sub = self.tag.is_match_sub(meal)
-            || self.category.is_match_sub(meal)
-            || self.name.is_match_sub(meal);
-        (all_adds_empty || any_add) && !any_sub
+        self.decide(meal).is_match
+    }
+
+    /// Decide whether `meal` matches, remembering which components were responsible.
+    pub fn decide(&self, meal: &MealComplete) -> Decision {
+        let (name, category) = (&meal.meta.name, &meal.meta.category);
+        let adds: Vec<_> = [
+            (
+                "tag add",
+                self.tag.is_empty_add(),
+                self.tag.is_match_add(meal),
+            ),
+            (
+                "category add",
+                self.category.is_empty_add(),
+                self.category.is_match_add(category),
+            ),
+            (
+                "name add",
+                self.name.is_empty_add(),
+                self.name.is_match_add(name),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, empty, _)| !empty)
+        .map(|(component, _, is_match)| (component, is_match))
+        .collect();
+        let any_add = match self.mode {
+            FilterMode::Any => adds.iter().any(|(_, is_match)| *is_match),
+            FilterMode::All => adds.iter().all(|(_, is_match)| *is_match),
+        };
+        let subs: Vec<_> = [
+            ("tag sub", self.tag.is_match_sub(meal)),
+            ("category sub", self.category.is_match_sub(category)),
+            ("name sub", self.name.is_match_sub(name)),
+        ]
+        .into_iter()
+        .filter(|(_, is_match)| *is_match)
+        .map(|(component, _)| component)
+        .collect();
+        Decision {
+            is_match: (adds.is_empty() || any_add) && subs.is_empty(),
+            adds,
+            subs,
+        }
     }
 
     pub fn is_non_empty_match(&self, meal: &MealComplete) -> bool {
@@ -63,12 +158,22 @@ impl Rule {
             name: self.name.joined(other.name),
             tag: self.tag.joined(other.tag),
             category: self.category.joined(other.category),
+            mode: self.mode,
         }
     }
 
     pub fn is_empty(&self) -> bool {
         self.name.is_empty() && self.tag.is_empty() && self.category.is_empty()
     }
+
+    /// The same rule, matching names and categories case-insensitively.
+    pub fn case_insensitive(self) -> Self {
+        Self {
+            name: self.name.case_insensitive(),
+            category: self.category.case_insensitive(),
+            ..self
+        }
+    }
 }
 
 impl TagRule {
@@ -117,16 +222,50 @@ impl RegexRule {
         self.add.is_none() && self.sub.is_none()
     }
 
-    fn is_match_add(&self, meal: &MealComplete) -> bool {
+    /// The same rule, with every regex matching case-insensitively.
+    ///
+    /// Inline flags like `(?-i)` in a regex still take precedence.
+    pub fn case_insensitive(self) -> Self {
+        let insensitive = |set: Option<RegexSet>| {
+            let patterns = set?
+                .patterns()
+                .iter()
+                .map(|re| format!("(?i){}", re))
+                .collect_vec();
+            // This should not panic, the flag does not invalidate valid regexes
+            Some(RegexSet::new(patterns).unwrap())
+        };
+        Self {
+            add: insensitive(self.add),
+            sub: insensitive(self.sub),
+        }
+    }
+
+    /// The added regexes, compiled one by one to find their matches.
+    pub fn add_regexes(&self) -> Vec<Regex> {
+        let patterns = self
+            .add
+            .as_ref()
+            .map(RegexSet::patterns)
+            .unwrap_or_default();
+        // This should not panic, the patterns were valid for the set
+        patterns.iter().map(|re| Regex::new(re).unwrap()).collect()
+    }
+
+    /// Does any added regex match `text`?
+    ///
+    /// The [`Rule`] decides which field of the meal is passed.
+    fn is_match_add(&self, text: &str) -> bool {
         match self.add {
-            Some(ref rset) => rset.is_match(&meal.meta.category),
+            Some(ref rset) => rset.is_match(text),
             None => false,
         }
     }
 
-    fn is_match_sub(&self, meal: &MealComplete) -> bool {
+    /// Does any subtracted regex match `text`?
+    fn is_match_sub(&self, text: &str) -> bool {
         match self.sub {
-            Some(ref rset) => rset.is_match(&meal.meta.category),
+            Some(ref rset) => rset.is_match(text),
             None => false,
         }
     }
@@ -172,6 +311,19 @@ impl TryFrom<RawRegexRule> for RegexRule {
     }
 }
 
+impl From<RegexRule> for RawRegexRule {
+    fn from(rule: RegexRule) -> Self {
+        let patterns = |set: Option<RegexSet>| match set {
+            Some(set) => set.patterns().to_vec(),
+            None => vec![],
+        };
+        Self {
+            add: patterns(rule.add),
+            sub: patterns(rule.sub),
+        }
+    }
+}
+
 fn slice_to_option<T, V>(vec: &[T], val: V) -> Option<V> {
     if vec.is_empty() {
         None
@@ -0,0 +1,625 @@
+use pretty_assertions::assert_eq;
+use structopt::StructOpt;
+
+use regex::Regex;
+
+use std::collections::BTreeSet;
+
+use crate::{
+    meal::{MealComplete, Meta, Prices},
+    tag::Tag,
+};
+
+use super::*;
+
+#[test]
+fn the_template_is_the_default_config() {
+    let config: ConfigFile = toml::from_str(&ConfigFile::template()).unwrap();
+    assert_eq!(config.default_canteen_id, None);
+    assert!(config.price_tags.is_empty());
+    assert!(config.filter.is_empty());
+    assert!(config.favs.is_empty());
+}
+
+#[test]
+fn the_example_config_parses() {
+    let config: ConfigFile = toml::from_str(EXAMPLE_CONFIG).unwrap();
+    assert_eq!(config.default_canteen_id, Some(63));
+    assert!(!config.filter.is_empty());
+}
+
+#[test]
+fn it_parses_ttls() {
+    assert_eq!("30s".parse::<Ttl>().unwrap(), Ttl(Duration::seconds(30)));
+    assert_eq!("5m".parse::<Ttl>().unwrap(), Ttl(Duration::minutes(5)));
+    assert_eq!("12h".parse::<Ttl>().unwrap(), Ttl(Duration::hours(12)));
+    assert_eq!("2d".parse::<Ttl>().unwrap(), Ttl(Duration::days(2)));
+    assert!("5".parse::<Ttl>().is_err());
+    assert!("m".parse::<Ttl>().is_err());
+    assert!("5y".parse::<Ttl>().is_err());
+    assert_eq!(Ttl(Duration::minutes(90)).to_string(), "90m");
+}
+
+#[test]
+fn it_reads_the_geoip_ttl() {
+    let config: ConfigFile = toml::from_str(r#"geoip-ttl = "1d""#).unwrap();
+    assert_eq!(config.geoip_ttl, Some(Ttl(Duration::days(1))));
+}
+
+#[test]
+fn it_reads_canteen_ttl_overrides() {
+    let config: ConfigFile = toml::from_str(
+        r#"
+        [canteen-ttl-overrides]
+        63 = "30m"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.canteen_ttl_overrides.get(63),
+        Some(Ttl(Duration::minutes(30)))
+    );
+    assert_eq!(config.canteen_ttl_overrides.get(64), None);
+    let invalid = toml::from_str::<ConfigFile>("[canteen-ttl-overrides]\nmensa = \"30m\"");
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn it_formats_the_default_currency() {
+    let currency = Currency::default();
+    assert_eq!(currency.format(2.65), "2.65€");
+    assert_eq!(currency.missing(), "-.--€");
+}
+
+#[test]
+fn it_formats_a_prefix_dollar_currency() {
+    let config: ConfigFile = toml::from_str(
+        r#"
+        [currency]
+        symbol = "$"
+        position = "prefix"
+        decimals = 1
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.currency.format(2.66), "$2.7");
+    assert_eq!(config.currency.format(3.0), "$3.0");
+    assert_eq!(config.currency.missing(), "$-.-");
+}
+
+#[test]
+fn the_currency_defaults_per_key() {
+    let config: ConfigFile = toml::from_str("[currency]\nsymbol = \" EUR\"").unwrap();
+    assert_eq!(config.currency.format(1.5), "1.50 EUR");
+    assert_eq!(config.currency.position, currency::CurrencyPosition::Suffix);
+}
+
+#[test]
+fn messages_default_per_key() {
+    let config: ConfigFile = toml::from_str("[messages]\nclosed = \"geschlossen\"").unwrap();
+    assert_eq!(config.messages.closed, "geschlossen");
+    assert_eq!(config.messages.no_meals, Messages::default().no_meals);
+}
+
+#[test]
+fn it_parses_single_dates() {
+    let today = parse_human_date("today").unwrap();
+    assert_eq!("today".parse::<args::Dates>().unwrap().0, vec![today]);
+    let date = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    assert_eq!("2024-01-08".parse::<args::Dates>().unwrap().0, vec![date]);
+}
+
+#[test]
+fn it_parses_date_ranges() {
+    let dates = "2024-01-08..2024-01-12".parse::<args::Dates>().unwrap().0;
+    let expected: Vec<_> = (8..=12)
+        .map(|day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap())
+        .collect();
+    assert_eq!(dates, expected);
+    assert!("2024-01-12..2024-01-08".parse::<args::Dates>().is_err());
+    assert!("2024-01-01..2024-01-31".parse::<args::Dates>().is_ok());
+    assert!(matches!(
+        "2024-01-01..2024-02-01".parse::<args::Dates>(),
+        Err(Error::DateRangeTooLarge(_, args::MAX_DATE_RANGE))
+    ));
+}
+
+#[test]
+fn it_parses_date_lists() {
+    let dates = "2024-01-08, 2024-01-10,2024-01-12"
+        .parse::<args::Dates>()
+        .unwrap()
+        .0;
+    let expected: Vec<_> = [8, 10, 12]
+        .iter()
+        .map(|day| NaiveDate::from_ymd_opt(2024, 1, *day).unwrap())
+        .collect();
+    assert_eq!(dates, expected);
+    let weekdays = "mon,wed,fri".parse::<args::Dates>().unwrap().0;
+    assert_eq!(weekdays.len(), 3);
+    assert!("mon,nonsense".parse::<args::Dates>().is_err());
+}
+
+#[test]
+fn repeated_dates_are_printed_once() {
+    let dates = |date: &str, tomorrow: bool| {
+        let mut args = vec!["mensa", "meals", "--date", date];
+        if tomorrow {
+            args.push("--also-tomorrow");
+        }
+        Config::from_args(args::Args::from_iter(&args)).dates()
+    };
+    let day = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    assert_eq!(
+        dates("2024-01-10,2024-01-08,2024-01-10", false),
+        vec![day(10), day(8)]
+    );
+    assert_eq!(
+        dates("2024-01-08,2024-01-09", true),
+        vec![day(8), day(9), day(10)]
+    );
+    assert_eq!(dates("2024-01-09,2024-01-08", true), vec![day(9), day(8)]);
+}
+
+#[test]
+fn only_secondary_tags_are_allergens() {
+    assert_eq!(args::parse_allergen("Nuts").unwrap(), Tag::Nuts);
+    assert_eq!(args::parse_allergen("Gluten").unwrap(), Tag::Gluten);
+    assert!(matches!(
+        args::parse_allergen("Vegan"),
+        Err(Error::InvalidAllergen(_))
+    ));
+    assert!(args::parse_allergen("Unknown").is_err());
+}
+
+#[test]
+fn it_parses_inline_configuration() {
+    let config = ConfigFile::parse_or_log("default-canteen-id = 42").unwrap();
+    assert_eq!(config.default_canteen_id, Some(42));
+    assert!(ConfigFile::parse_or_log("default-canteen-id = [").is_none());
+}
+
+#[test]
+fn it_merges_profiles_over_the_base() {
+    let raw = r#"
+        default-canteen-id = 42
+        price-tags = [ "Student" ]
+
+        [profiles.campus]
+        default-canteen-id = 63
+    "#;
+    let config = ConfigFile::parse(raw, |_| {}).unwrap();
+    let mut unknown = vec![];
+    let campus = config
+        .clone()
+        .with_profile("campus", |key| unknown.push(key))
+        .unwrap();
+    assert_eq!(campus.default_canteen_id, Some(63));
+    assert_eq!(campus.price_tags, config.price_tags);
+    assert!(unknown.is_empty());
+    assert!(matches!(
+        config.with_profile("home", |_| {}),
+        Err(Error::UnknownProfile(name)) if name == "home"
+    ));
+}
+
+#[test]
+fn it_layers_configuration_files() {
+    let team = r#"
+        default-canteen-id = 42
+        price-tags = [ "Student", "Employee" ]
+
+        [filter.tag]
+        sub = [ "Fish" ]
+
+        [currency]
+        symbol = "EUR"
+        position = "prefix"
+    "#;
+    let personal = r#"
+        price-tags = [ "Student" ]
+        colour = "blue"
+
+        [filter.tag]
+        sub = [ "Pig" ]
+        add = [ "Vegan" ]
+
+        [currency]
+        symbol = "€"
+    "#;
+    let files = [team.to_owned(), personal.to_owned()];
+    let mut unknown = vec![];
+    let config = ConfigFile::parse_layered(&files, |key| unknown.push(key)).unwrap();
+    assert_eq!(config.default_canteen_id, Some(42));
+    assert_eq!(
+        config.price_tags,
+        [PriceTags::Student].into_iter().collect()
+    );
+    assert_eq!(config.filter.tag.sub, vec![Tag::Fish, Tag::Pig]);
+    assert_eq!(config.filter.tag.add, vec![Tag::Vegan]);
+    assert_eq!(config.currency.format(2.5), "€2.50");
+    assert_eq!(unknown, vec![String::from("colour")]);
+}
+
+#[test]
+fn it_reports_unknown_keys_in_profiles() {
+    let raw = r#"
+        [profiles.home]
+        colour = "blue"
+    "#;
+    let config = ConfigFile::parse(raw, |_| {}).unwrap();
+    let mut unknown = vec![];
+    config
+        .with_profile("home", |key| unknown.push(key))
+        .unwrap();
+    assert_eq!(unknown, vec![String::from("profiles.home.colour")]);
+}
+
+#[test]
+fn it_matches_category_icons() {
+    let raw = r#"
+        [category-icons]
+        "(?i)dessert" = "🍰"
+        "(?i)suppe" = "🍲"
+    "#;
+    let config = ConfigFile::parse(raw, |_| {}).unwrap();
+    let icons = &config.category_icons;
+    assert_eq!(icons.get("Desserts"), Some("🍰"));
+    assert_eq!(icons.get("Tagessuppe"), Some("🍲"));
+    assert_eq!(icons.get("Beilagen"), None);
+    let raw = "[category-icons]\n\"(?i)suppe\" = \"x\"\n\"(\" = \"y\"";
+    let why = ConfigFile::parse(raw, |_| {}).unwrap_err().to_string();
+    assert!(
+        why.contains(r#"invalid regex "(" in `category-icons`"#),
+        "{}",
+        why
+    );
+}
+
+#[test]
+fn all_prices_override_the_configured_price_tags() {
+    let price_tags = |args: &[&str]| {
+        let toml = ["--config-toml", "price-tags = [ \"Student\" ]"];
+        let args = ["mensa"].iter().chain(&toml).chain(args);
+        Config::from_args(Args::from_iter(args)).price_tags()
+    };
+    let tags = |tags: &[PriceTags]| tags.iter().copied().collect::<HashSet<_>>();
+    assert_eq!(price_tags(&["meals"]), tags(&[PriceTags::Student]));
+    assert_eq!(price_tags(&["meals", "--all-prices"]), HashSet::new());
+    assert_eq!(
+        price_tags(&["meals", "--all-prices", "--price", "Employee"]),
+        tags(&[PriceTags::Employee])
+    );
+}
+
+#[test]
+fn it_parses_canteen_ids_and_slugs() {
+    use args::CanteenRef;
+    assert_eq!("63".parse::<CanteenRef>().unwrap(), CanteenRef::Id(63));
+    assert_eq!(
+        "Mensa Süd / Cafeteria".parse::<CanteenRef>().unwrap(),
+        CanteenRef::Slug(String::from("mensa-sued-cafeteria"))
+    );
+    assert!(matches!(
+        " - ".parse::<CanteenRef>(),
+        Err(Error::InvalidCanteenSlug(_))
+    ));
+}
+
+#[test]
+fn requested_canteen_ids_are_merged_and_sorted() {
+    let ids = |args: &[&str]| {
+        let args = args::Args::from_iter(args);
+        match args.command {
+            Some(args::Command::Canteens(cmd)) => cmd.requested_ids(),
+            _ => unreachable!(),
+        }
+    };
+    assert_eq!(ids(&["mensa", "canteens"]), None);
+    assert_eq!(
+        ids(&["mensa", "canteens", "--ids", "63,2,1"]),
+        Some(vec![1, 2, 63])
+    );
+    assert_eq!(
+        ids(&[
+            "mensa",
+            "canteens",
+            "--all",
+            "--ids",
+            "3,70",
+            "--id-range",
+            "1-3"
+        ]),
+        Some(vec![1, 2, 3, 70])
+    );
+    assert!(matches!(
+        "70-60".parse::<args::IdRange>(),
+        Err(Error::InvalidIdRange(_))
+    ));
+    assert!(matches!(
+        "60".parse::<args::IdRange>(),
+        Err(Error::InvalidIdRange(_))
+    ));
+    assert!("1-1000".parse::<args::IdRange>().is_ok());
+    assert!(matches!(
+        "1-1001".parse::<args::IdRange>(),
+        Err(Error::IdRangeTooLarge(_, args::MAX_ID_RANGE))
+    ));
+}
+
+#[test]
+fn a_position_on_meals_searches_nearby() {
+    let lat = |args: &[&str]| {
+        let args = args::Args::from_iter(args);
+        match args.command {
+            Some(args::Command::Meals(cmd)) => cmd.geo().map(|geo| geo.lat),
+            _ => unreachable!(),
+        }
+    };
+    assert_eq!(lat(&["mensa", "meals"]), None);
+    assert_eq!(
+        lat(&["mensa", "meals", "--lat", "1", "--long", "2"]),
+        Some(Some(1.0))
+    );
+    assert_eq!(lat(&["mensa", "meals", "--city", "Greifswald"]), Some(None));
+    assert_eq!(lat(&["mensa", "meals", "--lat", "1", "--id", "63"]), None);
+    assert_eq!(
+        lat(&["mensa", "meals", "close", "--lat", "3"]),
+        Some(Some(3.0))
+    );
+}
+
+#[test]
+fn tags_can_be_aliased() {
+    let toml = "[tag-aliases]\nFlavorEnhancer = \"Flavour Enhancer\"";
+    let conf = Config::from_args(args::Args::from_iter(&["mensa", "--config-toml", toml]));
+    assert_eq!(
+        conf.tag_alias(Tag::FlavorEnhancer),
+        Some("Flavour Enhancer")
+    );
+    assert_eq!(conf.tag_alias(Tag::Vegan), None);
+    assert!(ConfigFile::parse("[tag-aliases]\nNothing = \"x\"", |_| {}).is_err());
+}
+
+#[test]
+fn celery_is_accepted_in_both_spellings() {
+    let filter_tag = |tag: &str| {
+        let conf = Config::from_args(args::Args::from_iter(&[
+            "mensa",
+            "meals",
+            "--filter-tag",
+            tag,
+        ]));
+        conf.get_filter_rule().tag.add
+    };
+    assert_eq!(filter_tag("Celery"), vec![Tag::Sellery]);
+    assert_eq!(filter_tag("Sellery"), vec![Tag::Sellery]);
+    assert_eq!(Tag::Sellery.to_string(), "Celery");
+    assert_eq!(serde_json::to_value(Tag::Sellery).unwrap(), "Sellery");
+}
+
+#[test]
+fn environment_variables_are_expanded() {
+    let lookup = |name: &str| match name {
+        "HOST" => Some(String::from("geo.local")),
+        _ => None,
+    };
+    assert_eq!(
+        env::expand_with("https://${HOST}/search?q=$1", lookup).unwrap(),
+        "https://geo.local/search?q=$1"
+    );
+    assert!(matches!(
+        env::expand_with("${MISSING}", lookup),
+        Err(Error::UnsetConfigVariable(name)) if name == "MISSING"
+    ));
+    assert!(matches!(
+        env::expand_with("${HOST", lookup),
+        Err(Error::InvalidConfigVariable(_))
+    ));
+    std::env::set_var("MENSA_TEST_GEOCODER", "geo.local");
+    let config: ConfigFile =
+        toml::from_str(r#"geocoding-url = "https://${MENSA_TEST_GEOCODER}/search""#).unwrap();
+    let url = config.geocoding_url.unwrap();
+    assert_eq!(url.expanded(), "https://geo.local/search");
+    assert_eq!(String::from(url), "https://${MENSA_TEST_GEOCODER}/search");
+}
+
+#[test]
+fn the_theme_is_inferred_from_the_background() {
+    use args::Theme;
+    assert_eq!(Theme::Auto.resolve(Some("0;15")), Theme::Light);
+    assert_eq!(Theme::Auto.resolve(Some("15;default;0")), Theme::Dark);
+    assert_eq!(Theme::Auto.resolve(Some("nonsense")), Theme::Dark);
+    assert_eq!(Theme::Auto.resolve(None), Theme::Dark);
+    assert_eq!(Theme::Light.resolve(Some("15;0")), Theme::Light);
+}
+
+#[test]
+fn json_pointers_select_parts_of_the_output() {
+    use args::JsonPointer;
+    let value = serde_json::json!({ "63": [{ "name": "Soup", "a/b": 1 }] });
+    let select = |raw: &str| raw.parse::<JsonPointer>().unwrap().select(value.clone());
+    assert_eq!(select("/63/0/name").unwrap(), "Soup");
+    assert_eq!(select("/63/0/a~1b").unwrap(), 1);
+    assert_eq!(select("").unwrap(), value);
+    assert!(matches!(
+        select("/63/1"),
+        Err(Error::JsonPointerNotFound(pointer)) if pointer == "/63/1"
+    ));
+    assert!(matches!(
+        "63/0".parse::<JsonPointer>(),
+        Err(Error::InvalidJsonPointer(_))
+    ));
+    assert!("/a~2".parse::<JsonPointer>().is_err());
+}
+
+#[test]
+fn the_cache_dir_can_be_overridden() {
+    let conf = Config::from_args(Args::from_iter(&["mensa", "--cache-dir", "/tmp/mensa"]));
+    assert_eq!(conf.cache_dir(), PathBuf::from("/tmp/mensa"));
+    let conf = Config::from_args(Args::from_iter(&["mensa", "-c", "a.toml", "-c", "b.toml"]));
+    assert_eq!(conf.path, PathBuf::from("b.toml"));
+}
+
+#[test]
+fn json_full_implies_json_for_every_command() {
+    let json = |args: &[&str]| Config::from_args(args::Args::from_iter(args)).args.json;
+    assert!(json(&["mensa", "--json-full"]));
+    assert!(json(&["mensa", "tags", "--json-full"]));
+    assert!(json(&["mensa", "canteens", "--json-full"]));
+    assert!(!json(&["mensa", "tags"]));
+}
+
+#[test]
+fn validation_reports_unknown_keys_and_invalid_profiles() {
+    let validate = |raw: &str| {
+        let mut out = vec![];
+        let res = ConfigFile::validate_file(&mut out, "test.toml", raw);
+        (res, String::from_utf8(out).unwrap())
+    };
+    let (res, out) = validate("colour = \"blue\"");
+    assert!(res.is_ok());
+    assert!(out.contains("unknown key \"colour\""));
+    assert!(out.contains("test.toml is valid"));
+    let (res, out) = validate("[profiles.bad]\ndefault-canteen-id = \"x\"");
+    assert!(matches!(res, Err(Error::DeserializingConfig(_))));
+    assert!(!out.contains("is valid"));
+}
+
+fn meta(name: &str, category: &str) -> Meta {
+    Meta {
+        name: name.into(),
+        tags: BTreeSet::new(),
+        descs: BTreeSet::new(),
+        prices: Prices {
+            students: None,
+            employees: None,
+            pupils: None,
+            others: None,
+        },
+        category: category.into(),
+        notes: vec![],
+    }
+}
+
+fn regex_rule(add: &[&str], sub: &[&str]) -> RegexRule {
+    let regexes =
+        |raw: &[&str]| -> Vec<_> { raw.iter().map(|re| Regex::new(re).unwrap()).collect() };
+    RegexRule::from_arg_parts(&regexes(add), &regexes(sub))
+}
+
+#[test]
+fn name_rules_match_the_name() {
+    let meta = meta("Pizza", "Hauptgericht");
+    let meal = MealComplete { id: 0, meta: &meta };
+    let rule = |name| Rule {
+        name,
+        ..Default::default()
+    };
+    assert!(rule(regex_rule(&["Pizza"], &[])).is_match(&meal));
+    assert!(!rule(regex_rule(&["Hauptgericht"], &[])).is_match(&meal));
+    assert!(!rule(regex_rule(&[], &["Pizza"])).is_match(&meal));
+    assert!(rule(regex_rule(&[], &["Hauptgericht"])).is_match(&meal));
+}
+
+#[test]
+fn category_rules_match_the_category() {
+    let meta = meta("Pizza", "Hauptgericht");
+    let meal = MealComplete { id: 0, meta: &meta };
+    let rule = |category| Rule {
+        category,
+        ..Default::default()
+    };
+    assert!(rule(regex_rule(&["Hauptgericht"], &[])).is_match(&meal));
+    assert!(!rule(regex_rule(&["Pizza"], &[])).is_match(&meal));
+    assert!(!rule(regex_rule(&[], &["Hauptgericht"])).is_match(&meal));
+    assert!(rule(regex_rule(&[], &["Pizza"])).is_match(&meal));
+}
+
+#[test]
+fn any_mode_needs_one_matching_criterion() {
+    let meta = meta("Pizza", "Hauptgericht");
+    let meal = MealComplete { id: 0, meta: &meta };
+    let rule = Rule {
+        name: regex_rule(&["Pizza"], &[]),
+        category: regex_rule(&["Dessert"], &[]),
+        ..Default::default()
+    };
+    assert_eq!(rule.mode, FilterMode::Any);
+    assert!(rule.is_match(&meal));
+}
+
+#[test]
+fn all_mode_needs_every_non_empty_criterion() {
+    let meta = meta("Pizza", "Hauptgericht");
+    let meal = MealComplete { id: 0, meta: &meta };
+    let rule = |category| Rule {
+        name: regex_rule(&["Pizza"], &[]),
+        category,
+        mode: FilterMode::All,
+        ..Default::default()
+    };
+    assert!(!rule(regex_rule(&["Dessert"], &[])).is_match(&meal));
+    assert!(rule(regex_rule(&["Haupt"], &[])).is_match(&meal));
+    // Empty criteria are ignored
+    assert!(rule(RegexRule::default()).is_match(&meal));
+    assert!(!rule(regex_rule(&[], &["Haupt"])).is_match(&meal));
+}
+
+#[test]
+fn decisions_explain_the_responsible_components() {
+    let meta = meta("Pizza", "Hauptgericht");
+    let meal = MealComplete { id: 0, meta: &meta };
+    let rule = |name, category| Rule {
+        name,
+        category,
+        ..Default::default()
+    };
+    let explain = |rule: Rule| rule.decide(&meal).to_string();
+    assert_eq!(
+        explain(rule(RegexRule::default(), RegexRule::default())),
+        "kept, nothing to add"
+    );
+    assert_eq!(
+        explain(rule(
+            regex_rule(&["Pizza"], &[]),
+            regex_rule(&["Dessert"], &[])
+        )),
+        "kept by name add"
+    );
+    assert_eq!(
+        explain(rule(
+            regex_rule(&["Soup"], &[]),
+            regex_rule(&["Dessert"], &[])
+        )),
+        "dropped, category add, name add did not match"
+    );
+    assert_eq!(
+        explain(rule(
+            regex_rule(&["Pizza"], &["Pizza"]),
+            regex_rule(&[], &["Haupt"])
+        )),
+        "dropped by category sub, name sub"
+    );
+}
+
+#[test]
+fn case_insensitive_rules_ignore_the_case() {
+    let meta = meta("Curry mit Reis", "Hauptgericht");
+    let meal = MealComplete { id: 0, meta: &meta };
+    let rule = |name| Rule {
+        name,
+        ..Default::default()
+    };
+    assert!(!rule(regex_rule(&["curry"], &[])).is_match(&meal));
+    assert!(rule(regex_rule(&["curry"], &[]))
+        .case_insensitive()
+        .is_match(&meal));
+    assert!(!rule(regex_rule(&[], &["reis"]))
+        .case_insensitive()
+        .is_match(&meal));
+    // Inline flags take precedence
+    assert!(!rule(regex_rule(&["(?-i)curry"], &[]))
+        .case_insensitive()
+        .is_match(&meal));
+}
@@ -0,0 +1,102 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeMap, convert::TryFrom, fmt, str::FromStr};
+
+use crate::{canteen::CanteenId, error::Error};
+
+/// A time-to-live for cached values.
+///
+/// Written as a number followed by a unit, i.e. `30s`, `5m`, `1h` or `2d`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ttl(pub Duration);
+
+impl FromStr for Ttl {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+        let invalid = || Error::InvalidTtl(raw.to_owned());
+        let split = raw
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let (amount, unit) = raw.split_at(split);
+        let amount: i64 = amount.parse().map_err(|_| invalid())?;
+        let duration = match unit.trim() {
+            "s" => Duration::seconds(amount),
+            "m" => Duration::minutes(amount),
+            "h" => Duration::hours(amount),
+            "d" => Duration::days(amount),
+            _ => return Err(invalid()),
+        };
+        Ok(Ttl(duration))
+    }
+}
+
+impl TryFrom<String> for Ttl {
+    type Error = Error;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        raw.parse()
+    }
+}
+
+impl From<Ttl> for String {
+    fn from(ttl: Ttl) -> Self {
+        ttl.to_string()
+    }
+}
+
+impl fmt::Display for Ttl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.num_seconds();
+        match secs {
+            _ if secs != 0 && secs % 86400 == 0 => write!(f, "{}d", secs / 86400),
+            _ if secs != 0 && secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+            _ if secs != 0 && secs % 60 == 0 => write!(f, "{}m", secs / 60),
+            _ => write!(f, "{}s", secs),
+        }
+    }
+}
+
+/// TTLs of the meals of single canteens, overriding the default.
+///
+/// Configured as a table from canteen ids to TTLs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "BTreeMap<String, Ttl>", into = "BTreeMap<String, Ttl>")]
+pub struct CanteenTtls(BTreeMap<CanteenId, Ttl>);
+
+impl CanteenTtls {
+    /// The TTL overriding the default for canteen `id`, if any.
+    pub fn get(&self, id: CanteenId) -> Option<Ttl> {
+        self.0.get(&id).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl TryFrom<BTreeMap<String, Ttl>> for CanteenTtls {
+    type Error = Error;
+
+    fn try_from(raw: BTreeMap<String, Ttl>) -> Result<Self, Self::Error> {
+        raw.into_iter()
+            .map(|(id, ttl)| match id.trim().parse() {
+                Ok(id) => Ok((id, ttl)),
+                Err(_) => Err(Error::InvalidCanteenId(id)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl From<CanteenTtls> for BTreeMap<String, Ttl> {
+    fn from(ttls: CanteenTtls) -> Self {
+        ttls.0
+            .into_iter()
+            .map(|(id, ttl)| (id.to_string(), ttl))
+            .collect()
+    }
+}
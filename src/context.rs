@@ -0,0 +1,26 @@
+//! Surroundings of canteens and meals.
+
+use crate::{
+    cache::{DefaultCache, CACHE},
+    config::{Config, CONF},
+};
+
+/// The cache and configuration used to fetch and print canteens and meals.
+///
+/// The CLI uses the global [`CACHE`] and [`CONF`], see [`Context::global`].
+/// Other callers can supply their own, i.e. to isolate tests.
+#[derive(Clone, Copy)]
+pub struct Context<'c> {
+    pub cache: &'c DefaultCache,
+    pub conf: &'c Config,
+}
+
+impl Context<'static> {
+    /// The context built from the global [`CACHE`] and [`CONF`].
+    pub fn global() -> Self {
+        Context {
+            cache: &CACHE,
+            conf: &CONF,
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use chrono::NaiveDate;
+use itertools::Itertools;
 use thiserror::Error;
 use tracing::{error, warn};
 
@@ -15,18 +17,72 @@ pub enum Error {
     Cache(#[source] cacache::Error, &'static str),
     #[error("io error while {_1}: {_0}")]
     Io(#[source] std::io::Error, &'static str),
+    #[error("{_0:?} is not an allergen, see `mensa tags` for allergens and additives")]
+    InvalidAllergen(String),
+    #[error("{_0:?} is not a tag, see `mensa tags`")]
+    UnknownTag(String),
     #[error("could not parse date")]
     InvalidDateInArgs,
     #[error("no default canteen id is defined and `--id` was not given")]
     CanteenIdMissing,
+    #[error("{_0:?} is not a canteen id")]
+    InvalidCanteenId(String),
+    #[error("{_0:?} is neither a canteen id nor a slug")]
+    InvalidCanteenSlug(String),
+    #[error("no canteen matches the slug {_0:?}")]
+    UnknownCanteenSlug(String),
+    #[error("the slug {_0:?} matches multiple canteens: {}", .1.iter().join(", "))]
+    AmbiguousCanteenSlug(String, Vec<usize>),
+    #[error("{_0:?} is not a range of canteen ids like `60-70`")]
+    InvalidIdRange(String),
+    #[error("the range {_0:?} spans more than {_1} canteen ids")]
+    IdRangeTooLarge(String, usize),
+    #[error("the range {_0:?} spans more than {_1} days")]
+    DateRangeTooLarge(String, usize),
+    #[error("no canteen was used before, `--last` needs a previous `mensa meals --id`")]
+    NoLastCanteen,
+    #[error("no canteens found near your location")]
+    NoCanteensNearby,
+    #[error("none of the canteens near your location serves meals on {0}")]
+    NoOpenCanteenNearby(NaiveDate),
+    #[error("no canteen was selected")]
+    NoCanteenSelected,
     #[error("could not read configuration file: {_0}")]
     ReadingConfig(#[source] std::io::Error),
     #[error("could not deserialize configuration file: {_0}")]
     DeserializingConfig(#[source] toml::de::Error),
+    #[error("could not serialize configuration file: {_0}")]
+    SerializingConfig(#[source] toml::ser::Error),
+    #[error("could not write configuration file: {_0}")]
+    WritingConfig(#[source] std::io::Error),
+    #[error("environment variable {_0:?} used in the configuration is not set")]
+    UnsetConfigVariable(String),
+    #[error(
+        "invalid environment variable reference {_0:?} in the configuration, expected `${{NAME}}`"
+    )]
+    InvalidConfigVariable(String),
+    #[error(
+        "invalid template {_0:?}, placeholders look like `{{name}}`, literal braces like `{{{{`"
+    )]
+    InvalidTemplate(String),
+    #[error("unknown placeholder {{{_0}}} in template, expected one of: {_1}")]
+    UnknownPlaceholder(String, String),
+    #[error("invalid json pointer {_0:?}, expected something like `/63/0/name`")]
+    InvalidJsonPointer(String),
+    #[error("json pointer {_0:?} does not reference anything in the output")]
+    JsonPointerNotFound(String),
+    #[error("invalid grouping {_0:?}, expected `canteen`, `category` or `canteen,category`")]
+    InvalidGroupBy(String),
+    #[error("profile {_0:?} is not defined in the configuration")]
+    UnknownProfile(String),
+    #[error("configuration file {_0:?} already exists, use `--force` to overwrite it")]
+    ConfigExists(std::path::PathBuf),
     #[error("failed to read terminal size for standard output")]
     UnableToGetTerminalSize,
     #[error("failed parsing regexes specified in the configuration: {_0}")]
     ParsingFilterRegex(#[source] regex::Error),
+    #[error("invalid regex {_0:?} in `category-icons`: {_1}")]
+    InvalidIconRegex(String, #[source] regex::Error),
     #[error("Url {_0:?} returned status {_1}")]
     NonSuccessStatusCode(String, reqwest::StatusCode),
     #[error("read invalid utf8 bytes")]
@@ -35,11 +91,26 @@ pub enum Error {
     InvalidDate(#[source] chrono::ParseError),
     #[error("internal url parsing error. This is probably a bug")]
     InternalUrl,
+    #[error("invalid ttl {_0:?}, expected something like `30s`, `5m`, `1h` or `2d`")]
+    InvalidTtl(String),
+    #[error("the configured geocoding url is invalid")]
+    InvalidGeocodingUrl,
+    #[error("could not find coordinates for {_0:?}")]
+    UnknownPlace(String),
+    #[error(
+        "geoip is disabled, give your location with `--lat` and `--long` or `--city`/`--postal`"
+    )]
+    GeoipDisabled,
+    #[error("meal {_0} has no details, meals can only be fetched with the meals of a day")]
+    MealMetaUnavailable(usize),
+    #[error("fetching failed earlier, not retrying yet: {_0}")]
+    FetchFailedBefore(String),
 }
 
 pub trait ResultExt<T> {
     fn log_err(self) -> Option<T>;
     fn log_warn(self) -> Option<T>;
+    #[allow(dead_code)]
     fn log_panic(self) -> T;
 }
 
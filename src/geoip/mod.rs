@@ -0,0 +1,135 @@
+//! Everything needed to fetch geoip data.
+
+use chrono::Duration;
+use lazy_static::lazy_static;
+use reqwest::Url;
+use serde::Deserialize;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    cache::Cache,
+    config::args::{Command, GeoCommand},
+    context::Context,
+    error::{Error, Result, ResultExt},
+};
+
+lazy_static! {
+    /// Default TTL for the geoip location, see [`crate::config::Config::geoip_ttl`].
+    pub static ref TTL_GEOIP: Duration = Duration::minutes(5);
+    static ref TTL_GEOCODING: Duration = Duration::days(30);
+}
+
+/// Default endpoint used to geocode cities and postal codes.
+pub const DEFAULT_GEOCODING_URL: &str =
+    "https://nominatim.openstreetmap.org/search?format=json&limit=1";
+
+const GEOIP_URL: &str = "https://api.geoip.rs/";
+
+/// Mean radius of the earth in km.
+const EARTH_RADIUS: f32 = 6371.0;
+
+/// Latitude and Longitude
+///
+/// This is only used to easily parse the json returned
+/// by the api.geoip.rs endpoint.
+#[derive(Debug, Clone, Deserialize)]
+struct LatLong {
+    latitude: f32,
+    longitude: f32,
+}
+
+/// A place returned by the geocoding endpoint.
+///
+/// Nominatim returns coordinates as strings.
+#[derive(Debug, Clone, Deserialize)]
+struct Place {
+    lat: String,
+    lon: String,
+}
+
+/// Infer Latitude and Longitude from the config.
+///
+/// This will use the cli arguments if given. Missing values
+/// are geocoded from `--city`/`--postal` if given and fetched
+/// from api.geoip.rs otherwise, unless geoip is disabled.
+pub fn infer(ctx: &Context<'_>) -> Result<(f32, f32)> {
+    let geo = match ctx.conf.cmd() {
+        Command::Canteens(cmd) => Some(&cmd.geo),
+        Command::Meals(cmd) => cmd.geo(),
+        Command::Compare(cmd) => Some(&cmd.geo),
+        Command::Open(cmd) => Some(&cmd.geo),
+        Command::Tags(_) | Command::Config(_) | Command::Days(_) => None,
+    };
+    let (lat, long) = match geo {
+        Some(geo) => (geo.lat, geo.long),
+        None => (None, None),
+    };
+    let (lat, long) = match (lat, long) {
+        (Some(lat), Some(long)) => (lat, long),
+        (lat, long) => {
+            let guessed = match geo.and_then(geocode_query) {
+                Some(query) => geocode(ctx, &query).log_warn(),
+                None => None,
+            };
+            let guessed = match guessed {
+                Some(guessed) => guessed,
+                None if ctx.conf.geoip_disabled() => return Err(Error::GeoipDisabled),
+                None => fetch_geoip(ctx, ctx.conf.geoip_ttl())?,
+            };
+            (
+                lat.unwrap_or(guessed.latitude),
+                long.unwrap_or(guessed.longitude),
+            )
+        }
+    };
+    Ok((lat, long))
+}
+
+/// Assemble the geocoding query from `--postal` and `--city`.
+fn geocode_query(geo: &GeoCommand) -> Option<String> {
+    match (&geo.postal, &geo.city) {
+        (Some(postal), Some(city)) => Some(format!("{} {}", postal, city)),
+        (Some(query), None) | (None, Some(query)) => Some(query.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Geocode the `query` using the configured geocoding endpoint.
+fn geocode(ctx: &Context<'_>, query: &str) -> Result<LatLong> {
+    let conf = ctx.conf;
+    let mut url = Url::parse(&conf.geocoding_url()).map_err(|_| Error::InvalidGeocodingUrl)?;
+    url.query_pairs_mut().append_pair("q", query);
+    let places: Vec<Place> = ctx
+        .cache
+        .fetch_json(url, *TTL_GEOCODING, conf.args.refresh)?;
+    let place = places
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::UnknownPlace(query.to_owned()))?;
+    let parse = |raw: &str| {
+        raw.parse()
+            .map_err(|_| Error::UnknownPlace(query.to_owned()))
+    };
+    Ok(LatLong {
+        latitude: parse(&place.lat)?,
+        longitude: parse(&place.lon)?,
+    })
+}
+
+/// Fetch geoip for current ip.
+///
+/// The result is cached for `ttl`.
+fn fetch_geoip(ctx: &Context<'_>, ttl: Duration) -> Result<LatLong> {
+    ctx.cache.fetch_json(GEOIP_URL, ttl, ctx.conf.args.refresh)
+}
+
+/// Great-circle distance in km between two (latitude, longitude) pairs.
+pub fn distance((lat1, long1): (f32, f32), (lat2, long2): (f32, f32)) -> f32 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlat = lat2 - lat1;
+    let dlong = (long2 - long1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlong / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
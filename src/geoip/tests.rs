@@ -0,0 +1,90 @@
+use pretty_assertions::assert_eq;
+
+use structopt::StructOpt;
+
+use super::*;
+use crate::{
+    cache::{API, CACHE},
+    config::{args::Args, Config},
+};
+
+/// Configuration for `mensa` called with `args`.
+fn conf_for(args: &[&str]) -> Config {
+    Config::from_args(Args::from_iter(["mensa"].iter().chain(args)))
+}
+
+fn ctx_for(conf: &Config) -> Context<'_> {
+    Context {
+        cache: &CACHE,
+        conf,
+    }
+}
+
+/// Infer the location of `mensa canteens` called with `args`.
+fn infer_for(args: &[&str]) -> Result<(f32, f32)> {
+    let conf = conf_for(&[&["canteens"], args].concat());
+    infer(&ctx_for(&conf))
+}
+
+#[test]
+fn a_longer_ttl_serves_cached_coordinates() {
+    let value = r#"{ "latitude": 51.34, "longitude": 12.37 }"#;
+    API.register_single(GEOIP_URL, value, None);
+    let ctx = Context::global();
+    let first = fetch_geoip(&ctx, Duration::days(1)).unwrap();
+    let second = fetch_geoip(&ctx, Duration::days(1)).unwrap();
+    assert_eq!(API.request_count(GEOIP_URL), 1);
+    assert_eq!(
+        (first.latitude, first.longitude),
+        (second.latitude, second.longitude)
+    );
+    // Without a TTL, the location is requested again
+    fetch_geoip(&ctx, Duration::zero()).unwrap();
+    assert_eq!(API.request_count(GEOIP_URL), 2);
+}
+
+#[test]
+fn disabled_geoip_requires_coordinates() {
+    assert!(matches!(
+        infer_for(&["--no-geoip"]),
+        Err(Error::GeoipDisabled)
+    ));
+    assert!(matches!(
+        infer_for(&["--config-toml", "disable-geoip = true", "--lat", "1.0"]),
+        Err(Error::GeoipDisabled)
+    ));
+    assert_eq!(
+        infer_for(&["--no-geoip", "--lat", "1.0", "--long", "2.0"]).unwrap(),
+        (1.0, 2.0)
+    );
+}
+
+#[test]
+fn cities_are_geocoded() {
+    let base = "http://invalid.local/geocode?format=json";
+    let value = r#"[{ "lat": "51.34", "lon": "12.37", "display_name": "Leipzig" }]"#;
+    API.register_single(&format!("{}&q=04109+Leipzig", base), value, None);
+    API.register_single(&format!("{}&q=Nowhere", base), "[]", None);
+    let toml = format!("geocoding-url = {:?}", base);
+    let prefix = ["--no-geoip", "--config-toml", &toml];
+    let infer_with = |args: &[&str]| infer_for(&[&prefix[..], args].concat());
+    assert_eq!(
+        infer_with(&["--city", "Leipzig", "--postal", "04109"]).unwrap(),
+        (51.34, 12.37)
+    );
+    // Explicit coordinates take precedence
+    assert_eq!(
+        infer_with(&["--city", "Leipzig", "--postal", "04109", "--lat", "1.0"]).unwrap(),
+        (1.0, 12.37)
+    );
+    // Unknown places fall back to geoip, which is disabled
+    assert!(matches!(
+        infer_with(&["--city", "Nowhere"]),
+        Err(Error::GeoipDisabled)
+    ));
+    let conf = conf_for(&["--config-toml", &toml]);
+    assert!(matches!(
+        geocode(&ctx_for(&conf), "Nowhere"),
+        Err(Error::UnknownPlace(place)) if place == "Nowhere"
+    ));
+}
@@ -0,0 +1,70 @@
+//! Streaming output of json arrays, see `--json-array`.
+use std::io::Write;
+
+use serde::Serialize;
+
+#[cfg(test)]
+mod tests;
+
+use crate::error::{Error, Result};
+
+/// A json array written element by element.
+///
+/// Nothing is buffered, every element is written and flushed
+/// as soon as it's pushed. Call [`JsonArray::close`] to finish the array.
+pub struct JsonArray<W: Write> {
+    output: W,
+    /// Whether the array was wrapped in a versioned envelope.
+    versioned: bool,
+    empty: bool,
+}
+
+impl<W: Write> JsonArray<W> {
+    /// Open the array on `output`.
+    ///
+    /// With a `version`, the array is wrapped like `--json-versioned` does.
+    pub fn open(mut output: W, version: Option<u32>) -> Result<Self> {
+        let res = match version {
+            Some(version) => write!(output, "{{\"version\":{},\"data\":[", version),
+            None => write!(output, "["),
+        };
+        res.map_err(|why| Error::Io(why, "opening json array"))?;
+        Ok(JsonArray {
+            output,
+            versioned: version.is_some(),
+            empty: true,
+        })
+    }
+
+    /// Write `value` as the next element of the array.
+    pub fn push<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        let sep = if self.empty { "\n" } else { ",\n" };
+        self.empty = false;
+        self.output
+            .write_all(sep.as_bytes())
+            .map_err(|why| Error::Io(why, "writing json array"))?;
+        // This is done to catch broken pipe errors
+        match serde_json::to_writer(&mut self.output, value) {
+            Err(why) if why.is_io() => Err(Error::Io(why.into(), "serializing json")),
+            Err(other) => Err(Error::Serializing(other, "writing json array")),
+            Ok(()) => self
+                .output
+                .flush()
+                .map_err(|why| Error::Io(why, "writing json array")),
+        }
+    }
+
+    /// Close the array, and the envelope if there is one.
+    pub fn close(mut self) -> Result<()> {
+        let end = match (self.empty, self.versioned) {
+            (true, false) => "]\n",
+            (true, true) => "]}\n",
+            (false, false) => "\n]\n",
+            (false, true) => "\n]}\n",
+        };
+        self.output
+            .write_all(end.as_bytes())
+            .and_then(|_| self.output.flush())
+            .map_err(|why| Error::Io(why, "closing json array"))
+    }
+}
@@ -0,0 +1,32 @@
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+
+use super::*;
+
+fn stream(values: &[Value], version: Option<u32>) -> String {
+    let mut output = vec![];
+    let mut array = JsonArray::open(&mut output, version).unwrap();
+    for value in values {
+        array.push(value).unwrap();
+    }
+    array.close().unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn streamed_arrays_are_valid_json() {
+    let values = [json!({ "id": 1 }), json!({ "id": 2 })];
+    let parsed: Value = serde_json::from_str(&stream(&values, None)).unwrap();
+    assert_eq!(parsed, json!([{ "id": 1 }, { "id": 2 }]));
+    let parsed: Value = serde_json::from_str(&stream(&values, Some(1))).unwrap();
+    assert_eq!(
+        parsed,
+        json!({ "version": 1, "data": [{ "id": 1 }, { "id": 2 }] })
+    );
+}
+
+#[test]
+fn empty_arrays_are_closed() {
+    assert_eq!(stream(&[], None), "[]\n");
+    assert_eq!(stream(&[], Some(1)), "{\"version\":1,\"data\":[]}\n");
+}
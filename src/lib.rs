@@ -0,0 +1,272 @@
+//! Library interface of the [`mensa`](https://github.com/MalteT/mensa) CLI.
+//!
+//! Use the [`Client`] to query the [OpenMensa](https://openmensa.org) API
+//! from other programs. All other modules are implementation details of
+//! the command line interface and may change without notice.
+//!
+//! ```no_run
+//! use chrono::Local;
+//! use mensa::Client;
+//!
+//! let client = Client::new()?;
+//! let canteens = client.canteens_near(51.34, 12.37, 2.0)?;
+//! for canteen in canteens {
+//!     let today = Local::now().date_naive();
+//!     let meals = client.meals(canteen.id(), &today)?;
+//!     println!("{}: {} meals", canteen.id(), meals.len());
+//! }
+//! # Ok::<(), mensa::error::Error>(())
+//! ```
+
+use std::{fmt, io::Write};
+
+use chrono::Duration;
+use directories_next::ProjectDirs;
+use lazy_static::lazy_static;
+use owo_colors::{AnsiColors, FgDynColorDisplay, OwoColorize};
+use serde::Serialize;
+
+/// Colorizes the output.
+///
+/// This will colorize for Stdout based on heuristics and colors
+/// from the [`owo_colors`] library.
+///
+/// **Windows**: Automatic color requires virtual terminal processing,
+/// see [`ANSI_SUPPORT`].
+macro_rules! color {
+    ($what:expr; $($fn:ident),+) => {
+        {
+            use owo_colors::{OwoColorize, Stream};
+            #[allow(unused_imports)]
+            use crate::ThemeColorize;
+            use crate::config::args::ColorWhen;
+            match crate::config::CONF.args.color {
+                ColorWhen::Always => {
+                    $what $(. $fn())+ .to_string()
+                }
+                ColorWhen::Automatic if *crate::ANSI_SUPPORT => {
+                    $what.if_supports_color(Stream::Stdout,
+                                            |txt| txt $(. $fn().to_string())+).to_string()
+                }
+                ColorWhen::Automatic | ColorWhen::Never => {
+                    $what.to_string()
+                }
+            }
+        }
+    };
+}
+
+/// Conditionally select one of two expressions.
+///
+/// The former will be used unless the `--plain` flag is specified.
+macro_rules! if_plain {
+    ($fancy:expr, $plain:expr) => {
+        if cfg!(windows) || crate::config::CONF.args.plain {
+            $plain
+        } else {
+            $fancy
+        }
+    };
+}
+
+/// Safer `println` which doesn't panic, but errors.
+macro_rules! try_println {
+    () => {
+        try_println!("\n")
+    };
+    ($str:literal $(, $args:expr )* $(,)?) => ({
+        use std::io::Write;
+        writeln!(::std::io::stdout(), $str, $( $args ),* )
+            .map_err(|why| crate::error::Error::Io(why, "printing"))
+    })
+}
+
+#[doc(hidden)]
+pub mod cache;
+pub mod canteen;
+mod client;
+#[doc(hidden)]
+pub mod compare;
+#[doc(hidden)]
+pub mod config;
+mod context;
+pub mod error;
+mod geoip;
+mod json_array;
+pub mod meal;
+mod pagination;
+mod parallel;
+mod request;
+#[doc(hidden)]
+pub mod state;
+pub mod tag;
+mod template;
+
+pub use self::{client::Client, context::Context};
+
+use crate::{
+    config::{args::Theme, CONF},
+    error::{Error, Result, ResultExt},
+};
+
+const OPEN_MENSA_API: &str = "https://openmensa.org/api/v2";
+const OPEN_MENSA_WEB: &str = "https://openmensa.org";
+
+/// Version of the json output shape, see `--json-versioned`.
+///
+/// This must be increased whenever the serialized shape of meals,
+/// canteens, tags or comparisons changes.
+///
+/// # Version 1
+///
+/// - meals: A list of meals with their `id`, `name`, `tags`,
+///   `descs`, `prices`, `category` and `is_favourite`, keyed by canteen id.
+///   With `--show-source`, meals also have a `source`.
+///   For multiple days, meals also have the `date` they are served on.
+///   With `--group-by category`, the meals of every canteen are an object
+///   mapping categories to lists of meals instead.
+/// - canteens: A list of canteens with `id`, `name`, `city`, `address`
+///   and `coordinates`.
+/// - tags: A list of tags with `id`, `number`, `name` and `desc`.
+/// - compare: A list of offers with `canteen_id`, `name` and `price`.
+const JSON_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref DIR: ProjectDirs =
+        ProjectDirs::from("rocks", "tammena", "mensa").expect("Could not detect home directory");
+    static ref TTL_CANTEENS: Duration = Duration::days(1);
+    static ref TTL_MEALS: Duration = Duration::hours(1);
+    static ref TTL_NOT_FOUND: Duration = Duration::minutes(5);
+    /// Whether the terminal understands ANSI escape codes.
+    ///
+    /// On Windows, this enables virtual terminal processing first.
+    #[doc(hidden)]
+    pub static ref ANSI_SUPPORT: bool = enable_ansi_support();
+    /// Color of less important text, see [`ThemeColorize::muted`].
+    static ref MUTED: AnsiColors = {
+        let colorfgbg = std::env::var("COLORFGBG").ok();
+        match CONF.args.theme.resolve(colorfgbg.as_deref()) {
+            Theme::Light => AnsiColors::Black,
+            Theme::Dark | Theme::Auto => AnsiColors::BrightBlack,
+        }
+    };
+}
+
+/// Colors depending on `--theme`, usable in [`color!`] like any other color.
+trait ThemeColorize: OwoColorize {
+    /// Color of less important text, like prefixes and addresses.
+    fn muted(&self) -> FgDynColorDisplay<'_, AnsiColors, Self> {
+        self.color(*MUTED)
+    }
+}
+
+impl<T: OwoColorize> ThemeColorize for T {}
+
+/// Get the dimensions of the terminal.
+///
+/// Precedence: `--width`, the terminal size, `$COLUMNS`, `80`.
+fn get_sane_terminal_dimensions() -> (usize, usize) {
+    const MIN_TERM_WIDTH: usize = 20;
+    const DEFAULT_DIMENSIONS: (usize, usize) = (80, 80);
+    let from_env = || {
+        let width = std::env::var("COLUMNS").ok()?.parse().ok()?;
+        Some((width, DEFAULT_DIMENSIONS.1))
+    };
+    let (width, height) = match CONF.args.width {
+        Some(width) => (width, DEFAULT_DIMENSIONS.1),
+        None => terminal_size::terminal_size()
+            .map(|(w, h)| (w.0 as usize, h.0 as usize))
+            .or_else(from_env)
+            .ok_or(Error::UnableToGetTerminalSize)
+            .log_warn()
+            .unwrap_or(DEFAULT_DIMENSIONS),
+    };
+    (width.max(MIN_TERM_WIDTH), height)
+}
+
+/// Try enabling ANSI escape codes in the terminal.
+#[cfg(windows)]
+fn enable_ansi_support() -> bool {
+    enable_ansi_support::enable_ansi_support().is_ok()
+}
+
+/// ANSI escape codes are always supported outside of windows.
+#[cfg(not(windows))]
+fn enable_ansi_support() -> bool {
+    true
+}
+
+/// Wrap `text` in an OSC-8 hyperlink pointing to `url`.
+///
+/// Returns the plain `text` unless `--hyperlinks` is given
+/// and the output is not plain.
+fn hyperlink<S: fmt::Display>(url: &str, text: S) -> String {
+    if if_plain!(CONF.args.hyperlinks, false) {
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Envelope around json output, see `--json-versioned`.
+#[derive(Debug, Serialize)]
+struct JsonEnvelope<'v, T> {
+    version: u32,
+    data: &'v T,
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let envelope = JsonEnvelope {
+        version: JSON_VERSION,
+        data: value,
+    };
+    // The pointer of `--select` applies to the whole output
+    let selected = match CONF.args.select {
+        Some(ref pointer) => {
+            let value = if CONF.args.json_versioned {
+                serde_json::to_value(&envelope)
+            } else {
+                serde_json::to_value(value)
+            };
+            let value = value.map_err(|why| Error::Serializing(why, "selecting json"))?;
+            Some(pointer.select(value)?)
+        }
+        None => None,
+    };
+    let stdout = std::io::stdout();
+    let output = stdout.lock();
+    let res = match selected {
+        Some(selected) => serde_json::to_writer_pretty(output, &selected),
+        None if CONF.args.json_versioned => serde_json::to_writer_pretty(output, &envelope),
+        None => serde_json::to_writer_pretty(output, value),
+    };
+    // This is done to catch broken pipe errors
+    match res {
+        Err(why) if why.is_io() => {
+            // Propagate as simple io error.
+            // BrokenPipe errors are catched in main
+            Err(Error::Io(why.into(), "serializing json"))
+        }
+        Err(other) => Err(Error::Serializing(other, "writing meals as json")),
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Print `value` as a single line of json and flush stdout.
+fn print_json_line<T: Serialize>(value: &T) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut output = stdout.lock();
+    let res = serde_json::to_writer(&mut output, value);
+    // This is done to catch broken pipe errors
+    match res {
+        Err(why) if why.is_io() => {
+            // Propagate as simple io error.
+            // BrokenPipe errors are catched in main
+            Err(Error::Io(why.into(), "serializing json"))
+        }
+        Err(other) => Err(Error::Serializing(other, "writing json line")),
+        Ok(()) => writeln!(output)
+            .and_then(|_| output.flush())
+            .map_err(|why| Error::Io(why, "writing json line")),
+    }
+}
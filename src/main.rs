@@ -12,11 +12,11 @@
 //!
 //! - [X] Runs on Linux, macOS and Windows.
 //! - [X] Custom filters and favourites using CLI flags or the
-//!       optional configuration file.
+//!   optional configuration file.
 //! - [X] List canteens close to you based on GeoIP.
 //! - [X] All request are cached locally.
 //! - [X] Fuzzy date parsing based on
-//!       [date_time_parser](https://lib.rs/crates/date_time_parser).
+//!   [date_time_parser](https://lib.rs/crates/date_time_parser).
 //! - [X] List your favourite meals in canteens close to your location.
 //! - [X] JSON Output
 //!
@@ -50,10 +50,21 @@
 //! - `mensa meals` will show meals served today for the default canteen
 //!   mentioned in the configuration.
 //!   If no such configuration exists, try `mensa meals --id 63`.
+//!   Slugs of canteen names work, too: `mensa meals --id mensa-am-park`.
+//!   `mensa meals --last` reuses the canteen of the last `mensa meals --id`.
 //!   You can find the id for your canteen using
 //! - `mensa canteens` lists canteens near you based on your current
 //!   IP in a default radius of 10km.
+//!   Use `--ids 1,2,63` or `--id-range 60-70` to list specific canteens.
 //! - `mensa tags` will list the currently known meal tags like "**12** Nuts".
+//! - `mensa tags --present` lists the tags of today's meals with their counts.
+//! - `mensa open` lists canteens near you which are open today.
+//! - `mensa days --ical` prints the open days of a canteen as iCalendar,
+//!   try `mensa days --ical > mensa.ics`.
+//! - `mensa compare <name>` lists the prices of a meal in canteens near you,
+//!   cheapest first.
+//! - `mensa meals --table` shows one row per meal instead of the tree view.
+//! - `mensa meals --markdown` prints Markdown tables, ready to be pasted into chats.
 //!
 //! ### Examples
 //!
@@ -138,6 +149,22 @@
 //!   $ mensa canteens --all --json | jq '.[].id' | wc -l
 //!   704
 //!   ```
+//!
+//!   With `--json-array` instead of `--json`, the canteens are written
+//!   while the pages are fetched, without collecting all of them first.
+//! </details>
+//!
+//! <details>
+//!   <summary><b>Guard scripts against changes of the json output</b> (<i>Click me!</i>)</summary>
+//!
+//!   With `--json-versioned`, json output is wrapped in an object
+//!   like `{ "version": 1, "data": ... }`. The version is increased
+//!   whenever the shape of `data` changes.
+//!
+//!   ```console
+//!   $ mensa tags --json --json-versioned | jq '.version'
+//!   1
+//!   ```
 //! </details>
 //!
 //! ## Configuration *(Optional)*
@@ -146,113 +173,46 @@
 //! - `$XDG_CONFIG_DIR/mensa/config.toml` on **Linux**,
 //! - `$HOME/Library/Application Support/mensa/config.toml` on **macOS**,
 //! - `{FOLDERID_RoamingAppData}\mensa\config.toml` on **Windows**
+//!
+//! Alternatively, `mensa config init` writes a documented template to that location.
+//!
+//! The configuration is taken from the first of:
+//! - the toml in the `MENSA_CONFIG_TOML` environment variable,
+//! - the files given by `--config`,
+//! - the default location above.
+//!
+//! Several files can be layered by repeating `--config`, e.g. a shared team
+//! configuration followed by your personal one. Filters and favourites of
+//! all files are joined, every other option is taken from the last file
+//! setting it, where tables like `[currency]` are merged option by option.
+//!
+//! ## State
+//!
+//! The canteen used for `--last` and the hashes of the meals seen with
+//! `--only-if-changed` are remembered in a `state.json` in:
+//! - `$XDG_DATA_HOME/mensa` on **Linux**,
+//! - `$HOME/Library/Application Support/rocks.tammena.mensa` on **macOS**,
+//! - `{FOLDERID_RoamingAppData}\tammena\mensa\data` on **Windows**
 
 use std::io;
 
-use cache::Cache;
-use chrono::Duration;
-use directories_next::ProjectDirs;
-use lazy_static::lazy_static;
-use serde::Serialize;
-use tracing::{error, info};
-use tracing_subscriber::EnvFilter;
-
-/// Colorizes the output.
-///
-/// This will colorize for Stdout based on heuristics and colors
-/// from the [`owo_colors`] library.
-///
-/// **Windows**: Automatic color defaults to no color at the moment!
-// TODO: Make colors work on windows
-macro_rules! color {
-    ($what:expr; $($fn:ident),+) => {
-        {
-            #[cfg(not(windows))]
-            {
-                use owo_colors::{OwoColorize, Stream};
-                use crate::config::args::ColorWhen;
-                match crate::config::CONF.args.color {
-                    ColorWhen::Always => {
-                        $what $(. $fn())+ .to_string()
-                    }
-                    ColorWhen::Automatic => {
-                        $what.if_supports_color(Stream::Stdout,
-                                                |txt| txt $(. $fn().to_string())+).to_string()
-                    }
-                    ColorWhen::Never => {
-                        $what.to_string()
-                    }
-                }
-            }
-            #[cfg(windows)]
-            {
-                use owo_colors::{OwoColorize};
-                use crate::config::args::ColorWhen;
-                match crate::config::CONF.args.color {
-                    ColorWhen::Always => {
-                        $what $(. $fn())+ .to_string()
-                    }
-                    ColorWhen::Automatic | ColorWhen::Never => {
-                        $what.to_string()
-                    }
-                }
-            }
-        }
-    };
-}
-
-/// Conditionally select one of two expressions.
-///
-/// The former will be used unless the `--plain` flag is specified.
-macro_rules! if_plain {
-    ($fancy:expr, $plain:expr) => {
-        if cfg!(windows) || crate::config::CONF.args.plain {
-            $plain
-        } else {
-            $fancy
-        }
-    };
-}
-
-/// Safer `println` which doesn't panic, but errors.
-macro_rules! try_println {
-    () => {
-        try_println!("\n")
-    };
-    ($str:literal $(, $args:expr )* $(,)?) => ({
-        use std::io::Write;
-        writeln!(::std::io::stdout(), $str, $( $args ),* )
-            .map_err(|why| crate::error::Error::Io(why, "printing"))
-    })
-}
-
-mod cache;
-mod canteen;
-mod config;
-mod error;
-mod geoip;
-mod meal;
-mod pagination;
-mod request;
-mod tag;
-
-use crate::{
-    cache::CACHE,
+use mensa::{
+    cache::{Cache, CACHE},
     canteen::Canteen,
-    config::{args::Command, CONF},
+    compare,
+    config::{
+        args::{Args, Command, ConfigCommand, LogFormat},
+        ConfigFile, CONF,
+    },
     error::{Error, Result, ResultExt},
     meal::Meal,
+    state::State,
     tag::Tag,
+    Context, ANSI_SUPPORT,
 };
-
-const OPEN_MENSA_API: &str = "https://openmensa.org/api/v2";
-
-lazy_static! {
-    static ref DIR: ProjectDirs =
-        ProjectDirs::from("rocks", "tammena", "mensa").expect("Could not detect home directory");
-    static ref TTL_CANTEENS: Duration = Duration::days(1);
-    static ref TTL_MEALS: Duration = Duration::hours(1);
-}
+use structopt::StructOpt;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
 
 fn main() -> Result<()> {
     match real_main() {
@@ -270,54 +230,81 @@ fn main() -> Result<()> {
 }
 
 fn real_main() -> Result<()> {
-    // Initialize logger
-    tracing_subscriber::FmtSubscriber::builder()
+    // Initialize logger. The arguments are parsed without touching
+    // CONF, since assembling the configuration may already log
+    let args = Args::from_args();
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) if args.verbose => EnvFilter::new("warn"),
+        Err(_) => EnvFilter::default(),
+    };
+    let builder = tracing_subscriber::FmtSubscriber::builder()
         .with_writer(::std::io::stderr)
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+        .with_env_filter(filter);
+    match args.log_format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+    // Enable colors on windows terminals before printing anything
+    lazy_static::initialize(&ANSI_SUPPORT);
+    // Print paths for scripts, without touching the network or cache
+    if CONF.print_requested_path()? {
+        return Ok(());
+    }
     // Clear cache if requested
     if CONF.args.clear_cache {
         CACHE.clear()?;
     }
+    CONF.ensure_profile()?;
+    let ctx = Context::global();
     // Match over the user requested command
     match CONF.cmd() {
-        Command::Meals(_) => {
-            let mut canteens = Canteen::infer()?;
-            Meal::print_for_all_canteens(&mut canteens)?;
+        Command::Meals(cmd) => {
+            let mut canteens = Canteen::infer(&ctx)?;
+            Meal::print_for_all_canteens(&ctx, &mut canteens)?;
+            // Remember explicitly given canteens for `--last`
+            if cmd.canteen_id.is_some() && cmd.close.is_none() {
+                State::remember_canteen(&State::path(), canteens[0].id()).log_warn();
+            }
+        }
+        Command::Canteens(cmd) if CONF.args.ndjson => {
+            Canteen::print_ndjson(&ctx, cmd)?;
+        }
+        Command::Canteens(cmd) if CONF.args.json_array => {
+            Canteen::print_json_array(&ctx, cmd)?;
         }
         Command::Canteens(_) => {
-            let mut canteens = Canteen::infer()?;
-            Canteen::print_all(&mut canteens)?;
+            let mut canteens = Canteen::infer(&ctx)?;
+            Canteen::print_all(&ctx, &mut canteens)?;
         }
-        Command::Tags => {
-            Tag::print_all()?;
+        Command::Tags(cmd) if cmd.present => {
+            let mut canteens = Canteen::infer(&ctx)?;
+            Tag::print_present(&ctx, &mut canteens[0], cmd.sort)?;
         }
-    }
-    Ok(())
-}
-
-fn get_sane_terminal_dimensions() -> (usize, usize) {
-    const MIN_TERM_WIDTH: usize = 20;
-    terminal_size::terminal_size()
-        .map(|(w, h)| (w.0 as usize, h.0 as usize))
-        .map(|(w, h)| (w.max(MIN_TERM_WIDTH), h))
-        .ok_or(Error::UnableToGetTerminalSize)
-        .log_warn()
-        .unwrap_or((80, 80))
-}
-
-fn print_json<T: Serialize>(value: &T) -> Result<()> {
-    let stdout = std::io::stdout();
-    let output = stdout.lock();
-    let res = serde_json::to_writer_pretty(output, value);
-    // This is done to catch broken pipe errors
-    match res {
-        Err(why) if why.is_io() => {
-            // Propagate as simple io error.
-            // BrokenPipe errors are catched in main
-            Err(Error::Io(why.into(), "serializing json"))
+        Command::Tags(cmd) => {
+            Tag::print_all(&ctx, cmd.sort, cmd.map)?;
+        }
+        Command::Config(ConfigCommand::Init { force }) => {
+            ConfigFile::init(*force)?;
         }
-        Err(other) => Err(Error::Serializing(other, "writing meals as json")),
-        Ok(()) => Ok(()),
+        Command::Config(ConfigCommand::Validate) => {
+            ConfigFile::validate()?;
+        }
+        Command::Days(cmd) => {
+            let mut canteens = Canteen::infer(&ctx)?;
+            canteens[0].print_days(&ctx, cmd.ical)?;
+        }
+        Command::Open(_) => {
+            let mut canteens = Canteen::infer(&ctx)?;
+            Canteen::print_open(&ctx, &mut canteens)?;
+        }
+        Command::Compare(cmd) => {
+            let mut canteens = Canteen::infer(&ctx)?;
+            compare::print(&ctx, &mut canteens, &cmd.name)?;
+        }
+    }
+    if CONF.args.stats {
+        CACHE.stats().snapshot().print(CONF.args.json)?;
     }
+    Ok(())
 }
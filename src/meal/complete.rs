@@ -1,11 +1,25 @@
 use core::fmt;
+use std::{borrow::Cow, io::Write, ops::Range};
 
+use comfy_table::{
+    presets::{ASCII_FULL, UTF8_FULL},
+    ContentArrangement, Table,
+};
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use serde::Serialize;
+use regex::Regex;
+use serde::{Serialize, Serializer};
 use unicode_width::UnicodeWidthStr;
 
-use crate::{error::Result, get_sane_terminal_dimensions};
+use crate::{
+    cache::Source,
+    config::{rule::Rule, Config},
+    context::Context,
+    error::{Error, Result},
+    get_sane_terminal_dimensions,
+    tag::Tag,
+    template::Template,
+};
 
 use super::{MealId, Meta, PRE};
 
@@ -25,65 +39,277 @@ pub struct MealComplete<'c> {
     pub meta: &'c Meta,
 }
 
+/// A [`MealComplete`] with its favourite status, used for json output.
+///
+/// The [`Meta`] is owned if its prices were converted.
+#[derive(Debug, Serialize)]
+pub struct MealJson<'c> {
+    pub id: MealId,
+    #[serde(flatten)]
+    pub meta: Cow<'c, Meta>,
+    pub is_favourite: bool,
+    /// The ISO date the meal is served on, only given for multiple days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// Only given with `--show-source`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+}
+
+/// The [`MealJson`]s of a canteen, grouped by category with `--group-by category`.
+#[derive(Debug)]
+pub enum MealList<'c> {
+    Flat(Vec<MealJson<'c>>),
+    /// Categories in the order of their first meal.
+    ByCategory(Vec<(String, Vec<MealJson<'c>>)>),
+}
+
+impl<'c> MealList<'c> {
+    pub fn new(meals: Vec<MealJson<'c>>, by_category: bool) -> Self {
+        if by_category {
+            Self::ByCategory(group_by_category(meals, |meal| &meal.meta.category))
+        } else {
+            Self::Flat(meals)
+        }
+    }
+}
+
+impl Serialize for MealList<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Flat(meals) => meals.serialize(serializer),
+            Self::ByCategory(groups) => {
+                serializer.collect_map(groups.iter().map(|(category, meals)| (category, meals)))
+            }
+        }
+    }
+}
+
 impl<'c> MealComplete<'c> {
+    /// Placeholders available in `--meal-format`.
+    pub const PLACEHOLDERS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "category",
+        "tags",
+        "descs",
+        "prices",
+        "price.student",
+        "price.employee",
+        "price.pupil",
+        "price.other",
+    ];
+
     /// Print this [`MealComplete`] to the terminal.
-    pub fn print(&self, highlight: bool) -> Result<()> {
+    ///
+    /// Prices are padded to the given `price_widths`,
+    /// see [`Self::price_column_widths`]. Parts of the name
+    /// matching any of the `matches` are underlined.
+    pub fn print(
+        &self,
+        ctx: &Context<'_>,
+        highlight: bool,
+        price_widths: &[usize],
+        matches: &[Regex],
+    ) -> Result<()> {
         let (width, _height) = get_sane_terminal_dimensions();
         // Print meal name
-        self.print_name_to_terminal(width, highlight)?;
+        self.print_name_to_terminal(ctx.conf, width, highlight, matches)?;
         // Get notes, i.e. allergenes, descriptions, tags
-        self.print_category_and_primary_tags(highlight)?;
-        self.print_descriptions(width, highlight)?;
-        self.print_price_and_secondary_tags(highlight)
+        self.print_category_and_primary_tags(ctx, highlight)?;
+        if !ctx.conf.args.no_descriptions {
+            self.print_descriptions(ctx.conf, width, highlight)?;
+        }
+        self.print_price_and_secondary_tags(ctx, width, highlight, price_widths)
     }
 
-    fn print_name_to_terminal(&self, width: usize, highlight: bool) -> Result<()> {
-        let max_name_width = width - NAME_PRE.width() - PRE.width();
-        let mut name_parts = textwrap::wrap(&self.meta.name, max_name_width).into_iter();
+    /// Render this meal with a template given by `--meal-format`.
+    ///
+    /// Missing prices are replaced by the currency's placeholder.
+    pub fn format(&self, ctx: &Context<'_>, template: &Template) -> Result<String> {
+        let currency = ctx.conf.currency();
+        let price = |price: Option<f32>| match price {
+            Some(price) => currency.format(price),
+            None => currency.missing(),
+        };
+        let prices = &self.meta.prices;
+        template.render(|placeholder| {
+            Ok(match placeholder {
+                "id" => self.id.to_string(),
+                "name" => self.meta.name.clone(),
+                "category" => self.meta.category.clone(),
+                "tags" => self.meta.tags.iter().map(|tag| tag.name(ctx)).join(", "),
+                "descs" => self.meta.descs.iter().join(", "),
+                "prices" => prices.to_plain_string(ctx.conf),
+                "price.student" => price(prices.students),
+                "price.employee" => price(prices.employees),
+                "price.pupil" => price(prices.pupils),
+                "price.other" => price(prices.others),
+                _ => unreachable!("BUG: Unknown placeholders are rejected while parsing"),
+            })
+        })
+    }
+
+    /// Print which raw note was parsed into which tags to stderr.
+    ///
+    /// Notes without tags are kept as descriptions.
+    pub fn explain_tags(&self, ctx: &Context<'_>) -> Result<()> {
+        let mut stderr = std::io::stderr();
+        for note in &self.meta.notes {
+            let tags = Tag::parse_str(note);
+            let explanation = if tags.is_empty() {
+                String::from("(description)")
+            } else {
+                tags.iter().map(|tag| tag.name(ctx)).join(", ")
+            };
+            writeln!(stderr, "explain-tags: {:?} -> {}", note, explanation)
+                .map_err(|why| Error::Io(why, "explaining tags"))?;
+        }
+        Ok(())
+    }
+
+    /// Compute the column widths needed to align the prices of all `meals`.
+    ///
+    /// If the aligned prices would not fit into the terminal,
+    /// no widths are returned and the prices are printed unaligned.
+    pub fn price_column_widths(conf: &Config, meals: &[Self]) -> Vec<usize> {
+        let (width, _height) = get_sane_terminal_dimensions();
+        let widths = meals
+            .iter()
+            .map(|meal| meal.meta.prices.column_widths(conf))
+            .fold(vec![], |mut max: Vec<usize>, widths| {
+                max.resize(max.len().max(widths.len()), 0);
+                max.iter_mut()
+                    .zip(widths)
+                    .for_each(|(max, width)| *max = (*max).max(width));
+                max
+            });
+        // Parentheses and slashes between the tiers
+        let decoration = "(  )".len() + " / ".len() * widths.len().saturating_sub(1);
+        let line_width =
+            PRE.width() + PRICES_PRE.width() + decoration + widths.iter().sum::<usize>();
+        if line_width > width {
+            vec![]
+        } else {
+            widths
+        }
+    }
+
+    /// Build a table of the `meals` with one row per meal.
+    ///
+    /// Long names are wrapped to fit the table into `width` columns.
+    /// Favourites and `matches` are highlighted as in [`Self::print`].
+    pub fn table(
+        ctx: &Context<'_>,
+        meals: &[Self],
+        favs: &Rule,
+        matches: &[Regex],
+        width: usize,
+    ) -> Table {
+        let arrangement = if ctx.conf.args.no_wrap {
+            ContentArrangement::Disabled
+        } else {
+            ContentArrangement::Dynamic
+        };
+        let mut table = Table::new();
+        table
+            .load_preset(if_plain!(UTF8_FULL, ASCII_FULL))
+            .set_content_arrangement(arrangement)
+            .set_width(width.try_into().unwrap_or(u16::MAX))
+            .set_header(
+                ["Name", "Category", "Price", "Tags"]
+                    .iter()
+                    .map(|title| color!(title; bold))
+                    .collect_vec(),
+            );
+        for meal in meals {
+            let highlight = favs.is_non_empty_match(meal);
+            let prices = meal.meta.prices.to_plain_string(ctx.conf);
+            let tags = meal.meta.tags.iter().map(|tag| tag.as_id(ctx)).join(" ");
+            table.add_row(vec![
+                colored_name(&meal.meta.name, highlight, matches),
+                color!(meal.meta.category; bright_blue),
+                color!(prices; bright_green),
+                color!(tags; muted),
+            ]);
+        }
+        table
+    }
+
+    fn print_name_to_terminal(
+        &self,
+        conf: &Config,
+        width: usize,
+        highlight: bool,
+        matches: &[Regex],
+    ) -> Result<()> {
+        let id = if conf.args.show_ids {
+            format!("#{} ", self.id)
+        } else {
+            String::new()
+        };
+        let max_name_width = (width - NAME_PRE.width() - PRE.width()).saturating_sub(id.width());
+        // Matches spanning a line break are not highlighted
+        let name_part = |part: &str| colored_name(part, highlight, matches);
+        let mut name_parts = wrap(conf, &self.meta.name, max_name_width).into_iter();
         // There will always be a first part of the splitted string
         let first_name_part = name_parts.next().unwrap();
         try_println!(
-            "{}{}{}",
+            "{}{}{}{}",
             *PRE,
             hl_if(highlight, *NAME_PRE),
-            color!(hl_if(highlight, first_name_part); bold),
+            color!(id; dimmed),
+            name_part(&first_name_part),
         )?;
-        for name_part in name_parts {
-            let name_part = hl_if(highlight, name_part);
+        for part in name_parts {
             try_println!(
                 "{}{}{}",
                 *PRE,
                 hl_if(highlight, *NAME_CONTINUE_PRE),
-                color!(name_part; bold),
+                name_part(&part),
             )?;
         }
         Ok(())
     }
 
-    fn print_category_and_primary_tags(&self, highlight: bool) -> Result<()> {
-        let mut tag_str = self
+    fn print_category_and_primary_tags(&self, ctx: &Context<'_>, highlight: bool) -> Result<()> {
+        let tags: Vec<_> = self
             .meta
             .tags
             .iter()
             .filter(|tag| tag.is_primary())
-            .map(|tag| tag.as_id());
-        let tag_str_colored =
-            if_plain!(color!(tag_str.join(" "); bright_black), tag_str.join(", "));
+            .collect();
+        // Names need a clearer separation than emojis
+        let sep = if ctx.conf.args.no_emoji { ", " } else { " " };
+        let tag_str_colored = if_plain!(
+            tags.iter()
+                .map(|tag| colored_primary_tag(ctx, tag))
+                .join(sep),
+            tags.iter().map(|tag| tag.as_id(ctx)).join(", ")
+        );
         let comma_if_plain = if_plain!("", ",");
+        let icon = if_plain!(
+            ctx.conf
+                .category_icon(&self.meta.category)
+                .map(|icon| format!("{} ", icon))
+                .unwrap_or_default(),
+            String::new()
+        );
         try_println!(
-            "{}{}{}{} {}",
+            "{}{}{}{}{} {}",
             *PRE,
             hl_if(highlight, *CATEGORY_PRE),
+            icon,
             color!(self.meta.category; bright_blue),
-            color!(comma_if_plain; bright_black),
+            color!(comma_if_plain; muted),
             tag_str_colored
         )
     }
 
-    fn print_descriptions(&self, width: usize, highlight: bool) -> Result<()> {
+    fn print_descriptions(&self, conf: &Config, width: usize, highlight: bool) -> Result<()> {
         let max_note_width = width - OTHER_NOTE_PRE.width() - PRE.width();
         for note in &self.meta.descs {
-            let mut note_parts = textwrap::wrap(note, max_note_width).into_iter();
+            let mut note_parts = wrap(conf, note, max_note_width).into_iter();
             // There will always be a first part in the splitted string
             try_println!(
                 "{}{}{}",
@@ -103,24 +329,154 @@ impl<'c> MealComplete<'c> {
         Ok(())
     }
 
-    fn print_price_and_secondary_tags(&self, highlight: bool) -> Result<()> {
-        let prices = self.meta.prices.to_terminal_string();
-        let mut secondary: Vec<_> = self
+    /// The secondary tags are truncated to fit the line into `width` columns.
+    fn print_price_and_secondary_tags(
+        &self,
+        ctx: &Context<'_>,
+        width: usize,
+        highlight: bool,
+        price_widths: &[usize],
+    ) -> Result<()> {
+        let prices = self.meta.prices.to_terminal_string(ctx.conf, price_widths);
+        let secondary: Vec<_> = self
             .meta
             .tags
             .iter()
             .filter(|tag| tag.is_secondary())
             .collect();
-        secondary.sort_unstable();
-        let secondary_str = secondary.iter().map(|tag| tag.as_id()).join(" ");
-        try_println!(
-            "{}{}{}  {}",
-            *PRE,
-            hl_if(highlight, *PRICES_PRE),
-            prices,
-            color!(secondary_str; bright_black),
-        )
+        let secondary_str = secondary.iter().map(|tag| tag.as_id(ctx)).join(" ");
+        let pre = format!("{}{}{}  ", *PRE, hl_if(highlight, *PRICES_PRE), prices);
+        let secondary_str = if ctx.conf.args.no_wrap {
+            Cow::Borrowed(secondary_str.as_str())
+        } else {
+            truncate(&secondary_str, width.saturating_sub(plain_width(&pre)))
+        };
+        try_println!("{}{}", pre, color!(secondary_str; muted))
+    }
+}
+
+/// Group `meals` by their `category`, keeping the order of first appearance.
+pub(super) fn group_by_category<T, F>(meals: Vec<T>, category: F) -> Vec<(String, Vec<T>)>
+where
+    F: Fn(&T) -> &str,
+{
+    let mut groups: Vec<(String, Vec<T>)> = vec![];
+    for meal in meals {
+        match groups.iter_mut().find(|(name, _)| name == category(&meal)) {
+            Some((_, group)) => group.push(meal),
+            None => groups.push((category(&meal).to_owned(), vec![meal])),
+        }
+    }
+    groups
+}
+
+/// Wrap `text` into lines of at most `width` columns.
+///
+/// With `--no-wrap`, the text is returned as a single line.
+fn wrap<'t>(conf: &Config, text: &'t str, width: usize) -> Vec<Cow<'t, str>> {
+    if conf.args.no_wrap {
+        vec![Cow::Borrowed(text)]
+    } else {
+        textwrap::wrap(text, width)
+    }
+}
+
+/// Shorten `text` to at most `width` columns, ending it with an ellipsis.
+pub(super) fn truncate(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        return Cow::Borrowed(text);
+    }
+    let ellipsis = if_plain!("…", "...");
+    let mut truncated = String::new();
+    let mut used = ellipsis.width();
+    if used > width {
+        return Cow::Owned(truncated);
+    }
+    for c in text.chars() {
+        used += c.to_string().width();
+        if used > width {
+            break;
+        }
+        truncated.push(c);
+    }
+    // Don't leave a dangling separator in front of the ellipsis
+    let mut truncated = truncated.trim_end().to_owned();
+    truncated.push_str(ellipsis);
+    Cow::Owned(truncated)
+}
+
+/// Width of `text` in the terminal, ignoring ANSI escape sequences.
+pub(super) fn plain_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip the sequence up to and including its final letter
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            width += c.to_string().width();
+        }
+    }
+    width
+}
+
+/// Color a meal name, underlining parts matching any of the `matches`.
+fn colored_name(name: &str, highlight: bool, matches: &[Regex]) -> String {
+    split_matches(name, if_plain!(matches, &[]))
+        .into_iter()
+        .map(|(text, matched)| {
+            if matched {
+                color!(hl_if(highlight, text); bold, underline)
+            } else {
+                color!(hl_if(highlight, text); bold)
+            }
+        })
+        .collect()
+}
+
+/// Color a primary tag, tinted by diet with `--tag-colors`.
+fn colored_primary_tag(ctx: &Context<'_>, tag: &Tag) -> String {
+    let id = tag.as_id(ctx);
+    if ctx.conf.args.tag_colors && tag.is_veggie() {
+        color!(id; green)
+    } else if ctx.conf.args.tag_colors && tag.is_meat() {
+        color!(id; red)
+    } else {
+        color!(id; muted)
+    }
+}
+
+/// Split `text` into segments, telling whether any of the `regexes` matched them.
+///
+/// Overlapping matches are merged.
+pub(super) fn split_matches<'t>(text: &'t str, regexes: &[Regex]) -> Vec<(&'t str, bool)> {
+    let mut ranges: Vec<_> = regexes
+        .iter()
+        .flat_map(|re| re.find_iter(text))
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    let mut segments = vec![];
+    let mut pos = 0;
+    for range in merged {
+        if pos < range.start {
+            segments.push((&text[pos..range.start], false));
+        }
+        segments.push((&text[range.clone()], true));
+        pos = range.end;
+    }
+    if pos < text.len() {
+        segments.push((&text[pos..], false));
     }
+    segments
 }
 
 fn hl_if<S>(highlight: bool, text: S) -> String
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 use crate::{cache::Fetchable, tag::Tag};
 
@@ -18,13 +18,13 @@ pub struct Meal {
 
 impl Meal {
     /// Parse notes and return them split into [`Tag`]s and descriptions.
-    fn parse_and_split_notes(&self) -> (HashSet<Tag>, HashSet<String>) {
+    fn parse_and_split_notes(&self) -> (BTreeSet<Tag>, BTreeSet<String>) {
         self.notes
             .iter()
             .cloned()
             .flat_map(|raw| Note::parse_str(&raw))
             .fold(
-                (HashSet::new(), HashSet::new()),
+                (BTreeSet::new(), BTreeSet::new()),
                 |(mut tags, mut descs), note| {
                     match note {
                         Note::Tag(tag) => {
@@ -51,6 +51,7 @@ impl From<Meal> for super::Meal {
                 category: raw.category,
                 tags,
                 descs,
+                notes: raw.notes,
             }),
         }
     }
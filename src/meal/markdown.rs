@@ -0,0 +1,79 @@
+//! Markdown output of meals, see `--markdown`.
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+use crate::{canteen::Canteen, context::Context, error::Result};
+
+use super::{closed_label, day_label, select, MealComplete};
+
+/// Characters with a special meaning in GitHub-flavored Markdown tables.
+const SPECIAL: &[char] = &['\\', '`', '*', '_', '[', ']', '<', '>', '|', '~', '#', '!'];
+
+/// Print the meals of all `canteens` as Markdown tables.
+///
+/// Every canteen gets a `##` heading, multiple dates a `#` heading.
+pub fn print_markdown(
+    ctx: &Context<'_>,
+    canteens: &mut [Canteen],
+    dates: &[NaiveDate],
+) -> Result<()> {
+    // Load the filter which is used to select which meals to print.
+    let filter = ctx.conf.get_filter_rule();
+    // Load the favourites which are used for ordering meals.
+    let favs = ctx.conf.get_favourites_rule();
+    for (idx, day) in dates.iter().enumerate() {
+        if dates.len() > 1 {
+            let sep = if idx == 0 { "" } else { "\n" };
+            try_println!("{}# {}", sep, day_label(day))?;
+        }
+        for canteen in canteens.iter_mut() {
+            let name = escape(canteen.name(ctx)?);
+            try_println!("\n## {}\n", name)?;
+            match canteen.meals_at_mut(ctx, day)? {
+                Some(meals) => {
+                    let meals = select(ctx, meals, &filter, &favs)?;
+                    if meals.is_empty() {
+                        try_println!("_{}_", escape(&ctx.conf.messages().no_meals))?;
+                    } else {
+                        try_println!("{}", table(ctx, &meals))?;
+                    }
+                }
+                None => try_println!("_{}_", escape(closed_label(ctx, canteen, day)?))?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Render the `meals` as a table with the columns Name, Category, Price and Tags.
+pub(super) fn table(ctx: &Context<'_>, meals: &[MealComplete<'_>]) -> String {
+    let header = "| Name | Category | Price | Tags |\n| --- | --- | --- | --- |";
+    let rows = meals.iter().map(|meal| {
+        format!(
+            "| {} | {} | {} | {} |",
+            escape(&meal.meta.name),
+            escape(&meal.meta.category),
+            escape(&meal.meta.prices.to_plain_string(ctx.conf)),
+            escape(&meal.meta.tags.iter().map(|tag| tag.name(ctx)).join(", ")),
+        )
+    });
+    std::iter::once(header.to_owned()).chain(rows).join("\n")
+}
+
+/// Escape characters with a special meaning in Markdown.
+///
+/// Line breaks would end the table row and are replaced by spaces.
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' | '\r' => escaped.push(' '),
+            c if SPECIAL.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
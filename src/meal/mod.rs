@@ -1,30 +1,52 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use ssri::Integrity;
+use tracing::info;
+use unicode_width::UnicodeWidthStr;
 
 use std::{
-    collections::{HashMap, HashSet},
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashSet},
     fmt,
+    io::{self, Write},
+    time::Duration as StdDuration,
 };
 
 mod complete;
 mod de;
+mod markdown;
+mod summary;
+#[cfg(test)]
+mod tests;
 
 use crate::{
-    cache::Fetchable,
+    cache::{Fetchable, Source},
     canteen::{Canteen, CanteenId},
-    config::{PriceTags, CONF},
-    error::Result,
-    print_json,
+    config::{rule::Rule, Config, Currency, PriceTags},
+    context::Context,
+    error::{Error, Result},
+    get_sane_terminal_dimensions, print_json,
+    state::State,
     tag::Tag,
 };
 
-pub use self::complete::MealComplete;
+pub use self::complete::{MealComplete, MealJson, MealList};
+
+use self::{
+    complete::group_by_category,
+    summary::{CanteenWithMeals, MealsWithSummary, Summary},
+};
 
 pub type MealId = usize;
 
+/// Canteens and dates whose meals did not arrive within `--timeout-per-canteen`.
+type TimedOut = HashSet<(CanteenId, NaiveDate)>;
+
 lazy_static! {
-    static ref PRE: String = color!(if_plain!(" ┊", " |"); bright_black);
+    static ref PRE: String = color!(if_plain!(" ┊", " |"); muted);
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -37,10 +59,15 @@ pub struct Meal {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Meta {
     pub name: String,
-    pub tags: HashSet<Tag>,
-    pub descs: HashSet<String>,
+    /// Sorted by number to keep the output reproducible.
+    pub tags: BTreeSet<Tag>,
+    /// Sorted lexicographically to keep the output reproducible.
+    pub descs: BTreeSet<String>,
     pub prices: Prices,
     pub category: String,
+    /// The raw notes the `tags` and `descs` were parsed from.
+    #[serde(skip)]
+    pub notes: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -59,8 +86,10 @@ enum Note {
 }
 
 impl Meta {
-    fn fetch(_id: MealId) -> Result<Meta> {
-        todo!()
+    /// OpenMensa has no endpoint for single meals,
+    /// their meta is only available from the meals of a day.
+    fn fetch(id: MealId) -> Result<Meta> {
+        Err(Error::MealMetaUnavailable(id))
     }
 }
 
@@ -79,71 +108,526 @@ impl Meal {
     /// Print the given meals.
     ///
     /// This will respect passed cli arguments and the configuration.
-    pub fn print_for_all_canteens(canteens: &mut [Canteen]) -> Result<()> {
-        if CONF.args.json {
-            Self::print_for_all_canteens_json(canteens)
+    ///
+    /// The context is `'static`, since fetches that timed out with
+    /// `--timeout-per-canteen` continue in the background.
+    pub fn print_for_all_canteens(ctx: &Context<'static>, canteens: &mut [Canteen]) -> Result<()> {
+        let dates = ctx.conf.dates();
+        if ctx.conf.raw() {
+            for day in &dates {
+                for canteen in canteens.iter() {
+                    try_println!("{}", canteen.raw_meals_at(ctx, day)?)?;
+                }
+            }
+            return Ok(());
+        }
+        // With a timeout, the terminal output fetches one canteen after another
+        let timeout = ctx
+            .conf
+            .args
+            .timeout_per_canteen
+            .filter(|_| !ctx.conf.args.json && !ctx.conf.markdown())
+            .map(StdDuration::from_secs);
+        let timed_out = match timeout {
+            Some(timeout) => Self::fetch_within(ctx, canteens, &dates, timeout)?,
+            None => {
+                if canteens.len() > 1 {
+                    Canteen::prefetch_meals(ctx, canteens, &dates);
+                }
+                HashSet::new()
+            }
+        };
+        let changed = if ctx.conf.only_if_changed() {
+            match Self::changed_meal_hash(ctx, canteens, &dates, &timed_out)? {
+                Some(changed) => Some(changed),
+                None => {
+                    info!("Meals did not change since the last run");
+                    return Ok(());
+                }
+            }
+        } else {
+            None
+        };
+        Self::print_for_all_days(ctx, canteens, &dates, &timed_out)?;
+        // Only remember meals that were actually printed
+        if let Some((key, hash)) = changed {
+            State::store_meal_hash(&State::path(), key, hash)?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the meals of `canteens` at `dates` one after another,
+    /// waiting at most `timeout` for each of them.
+    ///
+    /// Returns the canteens and dates whose meals did not arrive in time.
+    fn fetch_within(
+        ctx: &Context<'static>,
+        canteens: &mut [Canteen],
+        dates: &[NaiveDate],
+        timeout: StdDuration,
+    ) -> Result<TimedOut> {
+        let mut timed_out = HashSet::new();
+        for day in dates {
+            for canteen in canteens.iter_mut() {
+                if !canteen.fetch_meals_within(ctx, day, timeout)? {
+                    timed_out.insert((canteen.id(), *day));
+                }
+            }
+        }
+        Ok(timed_out)
+    }
+
+    fn print_for_all_days(
+        ctx: &Context<'_>,
+        canteens: &mut [Canteen],
+        dates: &[NaiveDate],
+        timed_out: &TimedOut,
+    ) -> Result<()> {
+        if ctx.conf.args.json {
+            return Self::print_for_all_canteens_json(ctx, canteens, dates);
+        }
+        if ctx.conf.markdown() {
+            return markdown::print_markdown(ctx, canteens, dates);
+        }
+        if ctx.conf.suggest_open() {
+            for day in dates {
+                if let Some(open) = next_weekday_if_weekend(day) {
+                    let hint = format!(
+                        "{} is a {}, canteens are usually closed on weekends. Try --date {}",
+                        day,
+                        day.weekday(),
+                        open
+                    );
+                    try_println!("{}", color!(hint; bright_yellow))?;
+                }
+            }
+        }
+        if let [day] = dates[..] {
+            Self::print_for_all_canteens_no_json(ctx, canteens, &day, timed_out)
         } else {
-            Self::print_for_all_canteens_no_json(canteens)
+            for day in dates {
+                try_println!("\n{}", color!(format!("{}:", day_label(day)); bold))?;
+                Self::print_for_all_canteens_no_json(ctx, canteens, day, timed_out)?;
+            }
+            Ok(())
         }
     }
 
-    fn print_for_all_canteens_no_json(canteens: &mut [Canteen]) -> Result<()> {
+    /// Did the selected meals change since the last run with `--only-if-changed`?
+    ///
+    /// The meals are hashed and compared with the hash stored in the
+    /// [`State`]. If they changed, the key and hash to store once
+    /// the meals are printed are returned. Meals that `timed_out`
+    /// are not fetched again.
+    fn changed_meal_hash(
+        ctx: &Context<'_>,
+        canteens: &mut [Canteen],
+        dates: &[NaiveDate],
+        timed_out: &TimedOut,
+    ) -> Result<Option<(String, String)>> {
+        let filter = ctx.conf.get_filter_rule();
+        let favs = ctx.conf.get_favourites_rule();
+        let mut serialized = BTreeMap::new();
+        for day in dates {
+            for canteen in canteens.iter_mut() {
+                let id = canteen.id();
+                if timed_out.contains(&(id, *day)) {
+                    serialized.insert(format!("{}@{}", id, day), String::from("timed out"));
+                    continue;
+                }
+                // Explanations are printed once the meals are printed
+                let meals = match canteen.meals_at_mut(ctx, day)? {
+                    Some(meals) => select_quietly(ctx, meals, &filter, &favs)?,
+                    None => vec![],
+                };
+                let json = serde_json::to_string(&meals)
+                    .map_err(|why| Error::Serializing(why, "hashing meals"))?;
+                serialized.insert(format!("{}@{}", id, day), json);
+            }
+        }
+        let json = serde_json::to_string(&serialized)
+            .map_err(|why| Error::Serializing(why, "hashing meals"))?;
+        let hash = Integrity::from(json).to_string();
+        let key = canteens.iter().map(Canteen::id).join(",");
+        if State::has_meal_hash(&State::path(), &key, &hash) {
+            Ok(None)
+        } else {
+            Ok(Some((key, hash)))
+        }
+    }
+
+    fn print_for_all_canteens_no_json(
+        ctx: &Context<'_>,
+        canteens: &mut [Canteen],
+        day: &NaiveDate,
+        timed_out: &TimedOut,
+    ) -> Result<()> {
         // Load the filter which is used to select which meals to print.
-        let filter = CONF.get_filter_rule();
+        let filter = ctx.conf.get_filter_rule();
         // Load the favourites which will be used for marking meals.
-        let favs = CONF.get_favourites_rule();
-        // The day for which to print meals
-        let day = CONF.date();
-        for canteen in canteens {
-            let name = canteen.name()?;
-            try_println!("\n {}", color!(name; bright_black))?;
-            match canteen.meals_at_mut(day)? {
-                Some(meals) => {
-                    let mut printed_at_least_one_meal = false;
-                    for meal in meals {
-                        let complete = meal.complete()?;
-                        if filter.is_match(&complete) {
-                            let is_fav = favs.is_non_empty_match(&complete);
-                            try_println!("{}", *PRE)?;
-                            complete.print(is_fav)?;
-                            printed_at_least_one_meal = true;
-                        }
-                    }
-                    if !printed_at_least_one_meal {
-                        try_println!("{} {}", *PRE, color!("no matching meals found"; dimmed))?
-                    }
-                }
-                None => try_println!("{} {}", *PRE, color!("closed"; dimmed))?,
+        let favs = ctx.conf.get_favourites_rule();
+        // Matches of the name filter are highlighted in the meal names
+        let matches = filter.name.add_regexes();
+        for canteen in canteens.iter_mut() {
+            let timed_out = timed_out.contains(&(canteen.id(), *day));
+            // The source is only known once the meals are fetched
+            if ctx.conf.args.show_source && !timed_out {
+                canteen.meals_at_mut(ctx, day)?;
+            }
+            let source = canteen
+                .meal_source(day)
+                .filter(|_| ctx.conf.args.show_source);
+            let name = canteen.name(ctx)?;
+            try_println!("\n {}{}", color!(name; muted), source_marker(source))?;
+            if timed_out {
+                try_println!("{} {}", *PRE, color!("timed out"; dimmed))?;
+                continue;
+            }
+            match canteen.meals_at_mut(ctx, day)? {
+                Some(meals) => Self::print_selected(ctx, meals, &filter, &favs, &matches)?,
+                None => try_println!(
+                    "{} {}",
+                    *PRE,
+                    color!(closed_label(ctx, canteen, day)?; dimmed)
+                )?,
             }
         }
         Ok(())
     }
 
-    fn print_for_all_canteens_json(canteens: &mut [Canteen]) -> Result<()> {
+    /// Print the `meals` of a single canteen which are selected by the `filter`.
+    ///
+    /// Parts of the meal names matching any of `matches` are highlighted.
+    fn print_selected(
+        ctx: &Context<'_>,
+        meals: &mut [Meal],
+        filter: &Rule,
+        favs: &Rule,
+        matches: &[Regex],
+    ) -> Result<()> {
+        let meals = select(ctx, meals, filter, favs)?;
+        // Tags are explained below every meal of the tree, but after
+        // tables and formatted lines, to keep those contiguous
+        let explain = |complete: &MealComplete<'_>| {
+            if ctx.conf.args.explain_tags {
+                complete.explain_tags(ctx)
+            } else {
+                Ok(())
+            }
+        };
+        if let Some(format) = ctx.conf.meal_format() {
+            for complete in &meals {
+                try_println!("{}", complete.format(ctx, &format.0)?)?;
+            }
+            meals.iter().try_for_each(explain)?;
+        } else if ctx.conf.table() && !meals.is_empty() {
+            let (width, _height) = get_sane_terminal_dimensions();
+            try_println!("{}", MealComplete::table(ctx, &meals, favs, matches, width))?;
+            meals.iter().try_for_each(explain)?;
+        } else {
+            let price_widths = if ctx.conf.args.align_prices {
+                MealComplete::price_column_widths(ctx.conf, &meals)
+            } else {
+                vec![]
+            };
+            let print = |complete: &MealComplete<'_>| {
+                let is_fav = favs.is_non_empty_match(complete);
+                try_println!("{}", *PRE)?;
+                complete.print(ctx, is_fav, &price_widths, matches)?;
+                explain(complete)
+            };
+            if ctx.conf.group_by_category() {
+                let meals = meals.iter().collect();
+                for (category, group) in group_by_category(meals, |meal| &meal.meta.category) {
+                    try_println!("{}", *PRE)?;
+                    try_println!("{} {}", *PRE, color!(category; bright_blue, bold))?;
+                    group.into_iter().try_for_each(print)?;
+                }
+            } else {
+                meals.iter().try_for_each(print)?;
+            }
+        }
+        if meals.is_empty() {
+            try_println!("{} {}", *PRE, color!(ctx.conf.messages().no_meals; dimmed))
+        } else if ctx.conf.args.summary {
+            Summary::from_meals(&meals).print(ctx.conf)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Print the meals for all `dates` as json.
+    ///
+    /// With a single date, meals are keyed by canteen id, or listed
+    /// together with their canteen if `--json-full` is given.
+    /// Multiple dates are keyed by the ISO date.
+    fn print_for_all_canteens_json(
+        ctx: &Context<'_>,
+        canteens: &mut [Canteen],
+        dates: &[NaiveDate],
+    ) -> Result<()> {
+        if let [day] = dates {
+            print_json(&Self::json_for_day(ctx, canteens, day, false)?)
+        } else {
+            let days: BTreeMap<_, _> = dates
+                .iter()
+                .map(|day| {
+                    let value = Self::json_for_day(ctx, canteens, day, true)?;
+                    Ok((day.to_string(), value))
+                })
+                .collect::<Result<_>>()?;
+            print_json(&days)
+        }
+    }
+
+    /// The selected meals of all `canteens` at `day` as json.
+    ///
+    /// With `with_date`, every meal carries the `day` it is served on.
+    fn json_for_day(
+        ctx: &Context<'_>,
+        canteens: &mut [Canteen],
+        day: &NaiveDate,
+        with_date: bool,
+    ) -> Result<serde_json::Value> {
         // Load the filter which is used to select which meals to print.
-        let filter = CONF.get_filter_rule();
-        // The day for which to print meals
-        let day = CONF.date();
+        let filter = ctx.conf.get_filter_rule();
+        // Load the favourites which are used for marking and ordering meals.
+        let favs = ctx.conf.get_favourites_rule();
+        // Serialize the canteens first, meals borrow them later on
+        let canteen_values: Vec<_> = if ctx.conf.args.json_full {
+            canteens
+                .iter_mut()
+                .map(|canteen| {
+                    serde_json::to_value(canteen.complete_without_meals(ctx)?)
+                        .map_err(|why| Error::Serializing(why, "converting canteens to json"))
+                })
+                .try_collect()?
+        } else {
+            vec![]
+        };
         // Filter all meals
-        let meals: HashMap<CanteenId, Vec<_>> = canteens
+        let meals: Vec<(CanteenId, Vec<_>, _)> = canteens
             .iter_mut()
             .map(|canteen| {
                 let id = canteen.id();
-                let meals: Vec<_> = match canteen.meals_at_mut(day)? {
-                    Some(meals) => meals
-                        .iter_mut()
-                        .map(|meal| meal.complete())
-                        .filter_ok(|meal| filter.is_match(meal))
-                        .try_collect()?,
+                // Fetch first, the meals borrow the canteen later on
+                canteen.meals_at_mut(ctx, day)?;
+                let source = canteen
+                    .meal_source(day)
+                    .filter(|_| ctx.conf.args.show_source);
+                let meals = match canteen.meals_at_mut(ctx, day)? {
+                    Some(meals) => select(ctx, meals, &filter, &favs)?,
                     None => vec![],
                 };
-                Result::Ok((id, meals))
+                Result::Ok((id, meals, source))
             })
             .try_collect()?;
-        print_json(&meals)
+        // Prices are only converted on request
+        let currency = Some(ctx.conf.currency()).filter(|_| ctx.conf.args.convert);
+        let date = Some(*day).filter(|_| with_date);
+        let by_category = ctx.conf.group_by_category();
+        let to_list = |meals, source| {
+            MealList::new(to_json(meals, &favs, currency, source, date), by_category)
+        };
+        let summarize = |meals: &[MealComplete<'_>]| {
+            let summary = Summary::from_meals(meals);
+            match currency {
+                Some(currency) => summary.converted(currency),
+                None => summary,
+            }
+        };
+        let value = if ctx.conf.args.json_full {
+            let canteens: Vec<_> = canteen_values
+                .into_iter()
+                .zip(meals)
+                .map(|(canteen, (_, meals, source))| {
+                    let summary = ctx.conf.args.summary.then(|| summarize(&meals));
+                    let meals = to_list(meals, source);
+                    CanteenWithMeals {
+                        canteen,
+                        meals,
+                        summary,
+                    }
+                })
+                .collect();
+            serde_json::to_value(&canteens)
+        } else if ctx.conf.args.summary {
+            let meals: BTreeMap<_, _> = meals
+                .into_iter()
+                .map(|(id, meals, source)| {
+                    let summary = summarize(&meals);
+                    let meals = to_list(meals, source);
+                    (id, MealsWithSummary { meals, summary })
+                })
+                .collect();
+            serde_json::to_value(&meals)
+        } else {
+            let meals: BTreeMap<_, _> = meals
+                .into_iter()
+                .map(|(id, meals, source)| (id, to_list(meals, source)))
+                .collect();
+            serde_json::to_value(&meals)
+        };
+        value.map_err(|why| Error::Serializing(why, "converting meals to json"))
+    }
+}
+
+/// The next weekday after `day`, if `day` is on a weekend.
+fn next_weekday_if_weekend(day: &NaiveDate) -> Option<NaiveDate> {
+    match day.weekday() {
+        Weekday::Sat => Some(*day + Duration::days(2)),
+        Weekday::Sun => Some(*day + Duration::days(1)),
+        _ => None,
+    }
+}
+
+/// Human readable name of `day`, relative to today if possible.
+fn day_label(day: &NaiveDate) -> String {
+    let today = Local::now().date_naive();
+    if *day == today {
+        String::from("Today")
+    } else if *day == today + Duration::days(1) {
+        String::from("Tomorrow")
+    } else {
+        day.to_string()
+    }
+}
+
+/// Why `canteen` has no meals at `day`, see [`Messages`](crate::config::Messages).
+///
+/// Days outside of the published schedule are not necessarily closed.
+fn closed_label<'c>(ctx: &Context<'c>, canteen: &mut Canteen, day: &NaiveDate) -> Result<&'c str> {
+    let messages = ctx.conf.messages();
+    if canteen.is_scheduled_at(ctx, day)? {
+        Ok(&messages.closed)
+    } else {
+        Ok(&messages.not_scheduled)
     }
 }
 
+/// Marker following the canteen name for `--show-source`.
+fn source_marker(source: Option<Source>) -> String {
+    match source {
+        Some(Source::Cache) => format!(" {}", color!("(cached)"; dimmed)),
+        Some(Source::Network) | None => String::new(),
+    }
+}
+
+/// Complete the `meals` selected by the `filter`, ready for printing.
+///
+/// Favourites are moved first and the meals are truncated to `--limit` on request.
+/// With `--explain-filter`, every decision is explained on stderr.
+fn select<'m>(
+    ctx: &Context<'_>,
+    meals: &'m mut [Meal],
+    filter: &Rule,
+    favs: &Rule,
+) -> Result<Vec<MealComplete<'m>>> {
+    if ctx.conf.args.explain_filter {
+        for meal in meals.iter_mut() {
+            explain_selection(ctx.conf, &meal.complete()?, filter)?;
+        }
+    }
+    select_quietly(ctx, meals, filter, favs)
+}
+
+/// Like [`select`], but never explains the selection.
+fn select_quietly<'m>(
+    ctx: &Context<'_>,
+    meals: &'m mut [Meal],
+    filter: &Rule,
+    favs: &Rule,
+) -> Result<Vec<MealComplete<'m>>> {
+    let mut meals: Vec<_> = meals
+        .iter_mut()
+        .map(Meal::complete)
+        .filter_ok(|meal| is_selected(ctx.conf, meal, filter))
+        .try_collect()?;
+    if ctx.conf.favs_first() {
+        sort_favourites_first(&mut meals, favs);
+    }
+    if let Some(limit) = ctx.conf.args.limit {
+        meals.truncate(limit);
+    }
+    Ok(meals)
+}
+
+/// Is `meal` selected by the `filter`, `--only-priced`, `--min-price` and `--max-price`?
+fn is_selected(conf: &Config, meal: &MealComplete<'_>, filter: &Rule) -> bool {
+    let prices = &meal.meta.prices;
+    let tiers = conf.price_tags();
+    let (min, max) = (conf.min_price(), conf.max_price());
+    filter.is_match(meal)
+        && (!conf.only_priced() || prices.is_priced(&tiers))
+        && (min.is_none() && max.is_none() || prices.is_within(&tiers, min, max, conf.currency()))
+}
+
+/// Print why `meal` was selected by [`is_selected`] or not to stderr.
+fn explain_selection(conf: &Config, meal: &MealComplete<'_>, filter: &Rule) -> Result<()> {
+    let prices = &meal.meta.prices;
+    let tiers = conf.price_tags();
+    let decision = filter.decide(meal);
+    let mut explanation = decision.to_string();
+    let (min, max) = (conf.min_price(), conf.max_price());
+    let within = |min, max| prices.is_within(&tiers, min, max, conf.currency());
+    if decision.is_match && conf.only_priced() && !prices.is_priced(&tiers) {
+        explanation += ", but dropped by --only-priced";
+    } else if decision.is_match && min.is_some() && !within(min, None) {
+        explanation += ", but dropped by --min-price";
+    } else if decision.is_match && (min.is_some() || max.is_some()) && !within(min, max) {
+        explanation += ", but dropped by --max-price";
+    }
+    writeln!(
+        io::stderr(),
+        "explain-filter: {:?} -> {}",
+        meal.meta.name,
+        explanation
+    )
+    .map_err(|why| Error::Io(why, "explaining the filter"))
+}
+
+/// Move all meals matching `favs` to the front.
+///
+/// The sort is stable, the order within favourites and
+/// other meals is preserved.
+fn sort_favourites_first(meals: &mut [MealComplete<'_>], favs: &Rule) {
+    meals.sort_by_key(|meal| !favs.is_non_empty_match(meal));
+}
+
+/// Prepare the meals for json output.
+///
+/// Every meal is marked with its favourite status. If a `currency`
+/// is given, prices are converted to it. The `date` is only
+/// given if meals of several days are printed.
+fn to_json<'c>(
+    meals: Vec<MealComplete<'c>>,
+    favs: &Rule,
+    currency: Option<&Currency>,
+    source: Option<Source>,
+    date: Option<NaiveDate>,
+) -> Vec<MealJson<'c>> {
+    meals
+        .into_iter()
+        .map(|meal| {
+            let is_favourite = favs.is_non_empty_match(&meal);
+            let meta = match currency {
+                Some(currency) => Cow::Owned(Meta {
+                    prices: meal.meta.prices.converted(currency),
+                    ..meal.meta.clone()
+                }),
+                None => Cow::Borrowed(meal.meta),
+            };
+            MealJson {
+                id: meal.id,
+                meta,
+                is_favourite,
+                date: date.map(|date| date.to_string()),
+                source,
+            }
+        })
+        .collect()
+}
+
 impl Note {
     fn parse_str(raw: &str) -> Vec<Self> {
         let tags: Vec<_> = Tag::parse_str(raw).into_iter().map(Note::Tag).collect();
@@ -156,44 +640,134 @@ impl Note {
 }
 
 impl Prices {
-    fn to_terminal_string(&self) -> String {
-        let price_tags = CONF.price_tags();
-        let price_tags = if price_tags.is_empty() {
-            // Print all of them
-            vec![self.students, self.employees, self.pupils, self.others]
-        } else {
-            let mut values = vec![];
-            if price_tags.contains(&PriceTags::Student) {
-                values.push(self.students);
-            }
-            if price_tags.contains(&PriceTags::Employee) {
-                values.push(self.employees);
-            }
-            if price_tags.contains(&PriceTags::Pupil) {
-                values.push(self.pupils);
-            }
-            if price_tags.contains(&PriceTags::Other) {
-                values.push(self.others);
-            }
-            values
-        };
-        let price_tags: Vec<_> = price_tags
+    /// The price of the given tier.
+    pub fn get(&self, tier: PriceTags) -> Option<f32> {
+        match tier {
+            PriceTags::Student => self.students,
+            PriceTags::Employee => self.employees,
+            PriceTags::Pupil => self.pupils,
+            PriceTags::Other => self.others,
+        }
+    }
+
+    /// Select the prices of the given tiers.
+    ///
+    /// Prices are always ordered as in [`PriceTags::ORDER`].
+    /// If no tiers are given, all prices are selected.
+    pub fn select(&self, tiers: &HashSet<PriceTags>) -> Vec<Option<f32>> {
+        PriceTags::ORDER
+            .iter()
+            .filter(|tier| tiers.is_empty() || tiers.contains(tier))
+            .map(|tier| self.get(*tier))
+            .collect()
+    }
+
+    /// Is any of the given tiers priced?
+    ///
+    /// If no tiers are given, all tiers are considered.
+    pub fn is_priced(&self, tiers: &HashSet<PriceTags>) -> bool {
+        self.select(tiers).iter().any(Option::is_some)
+    }
+
+    /// Is any of the given tiers priced between `min` and `max` in `currency`?
+    ///
+    /// Both bounds are inclusive and optional.
+    /// If no tiers are given, all tiers are considered.
+    pub fn is_within(
+        &self,
+        tiers: &HashSet<PriceTags>,
+        min: Option<f32>,
+        max: Option<f32>,
+        currency: &Currency,
+    ) -> bool {
+        self.select(tiers)
             .into_iter()
-            .map(|tag| match tag {
-                Some(tag) => color!(format!("{:.2}€", tag); bright_green),
-                None => color!(String::from("-.--€"); bright_black),
+            .flatten()
+            .map(|price| currency.convert(price))
+            .any(|price| {
+                min.map_or(true, |min| price >= min) && max.map_or(true, |max| price <= max)
+            })
+    }
+
+    /// These prices converted to `currency`.
+    pub fn converted(&self, currency: &Currency) -> Self {
+        let convert = |price: Option<f32>| price.map(|price| currency.convert(price));
+        Self {
+            students: convert(self.students),
+            employees: convert(self.employees),
+            pupils: convert(self.pupils),
+            others: convert(self.others),
+        }
+    }
+
+    /// Format the price tiers selected in `conf` without any color.
+    ///
+    /// Prices are converted and formatted using the configured [`Currency`].
+    fn to_plain_strings(&self, conf: &Config) -> Vec<Option<String>> {
+        self.to_plain_strings_in(&conf.price_tags(), conf.currency())
+    }
+
+    /// Like [`Self::to_plain_strings`], for the given `tiers` and `currency`.
+    fn to_plain_strings_in(
+        &self,
+        tiers: &HashSet<PriceTags>,
+        currency: &Currency,
+    ) -> Vec<Option<String>> {
+        self.select(tiers)
+            .into_iter()
+            .map(|price| price.map(|price| currency.format(price)))
+            .collect()
+    }
+
+    /// Format the selected price tiers as a single line without any color.
+    ///
+    /// Missing prices are replaced by the currency's placeholder.
+    fn to_plain_string(&self, conf: &Config) -> String {
+        let missing = conf.currency().missing();
+        self.to_plain_strings(conf)
+            .into_iter()
+            .map(|price| price.unwrap_or_else(|| missing.clone()))
+            .join(" / ")
+    }
+
+    /// Width of every selected price tier in the terminal.
+    fn column_widths(&self, conf: &Config) -> Vec<usize> {
+        let missing = conf.currency().missing();
+        self.to_plain_strings(conf)
+            .iter()
+            .map(|tag| tag.as_deref().unwrap_or(&missing).width())
+            .collect()
+    }
+
+    /// Format the selected price tiers for the terminal.
+    ///
+    /// Every tier is right-aligned to the matching entry in `widths`,
+    /// tiers without an entry are not padded.
+    fn to_terminal_string(&self, conf: &Config, widths: &[usize]) -> String {
+        let missing = conf.currency().missing();
+        let price_tags: Vec<_> = self
+            .to_plain_strings(conf)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, tag)| {
+                let width = widths.get(idx).copied().unwrap_or_default();
+                match tag {
+                    Some(tag) => color!(format!("{:>width$}", tag, width = width); bright_green),
+                    None => {
+                        color!(format!("{:>width$}", missing, width = width); muted)
+                    }
+                }
             })
-            .map(|tag| color!(tag; bright_green))
             .collect();
         match price_tags.len() {
             0 => String::new(),
             _ => {
-                let slash = color!(" / "; bright_black);
+                let slash = color!(" / "; muted);
                 format!(
                     "{} {} {}",
-                    color!("("; bright_black),
+                    color!("("; muted),
                     price_tags.join(&slash),
-                    color!(")"; bright_black),
+                    color!(")"; muted),
                 )
             }
         }
@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+use crate::{
+    config::{Config, Currency},
+    error::Result,
+};
+
+use super::{MealComplete, MealList, PRE};
+
+/// Short statistics over a list of meals.
+///
+/// Prices refer to the student tier, meals without a student price
+/// are counted but not included in the price statistics.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub avg: Option<f32>,
+}
+
+/// Meals with an attached [`Summary`], used for json output.
+#[derive(Debug, Serialize)]
+pub struct MealsWithSummary<'c> {
+    pub meals: MealList<'c>,
+    pub summary: Summary,
+}
+
+/// Meals together with their serialized canteen, used for `--json-full`.
+#[derive(Debug, Serialize)]
+pub struct CanteenWithMeals<'c> {
+    pub canteen: serde_json::Value,
+    pub meals: MealList<'c>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+}
+
+impl Summary {
+    pub fn from_meals(meals: &[MealComplete<'_>]) -> Self {
+        let prices: Vec<f32> = meals
+            .iter()
+            .filter_map(|meal| meal.meta.prices.students)
+            .collect();
+        let avg = if prices.is_empty() {
+            None
+        } else {
+            Some(prices.iter().sum::<f32>() / prices.len() as f32)
+        };
+        Self {
+            count: meals.len(),
+            min: prices.iter().copied().reduce(f32::min),
+            max: prices.iter().copied().reduce(f32::max),
+            avg,
+        }
+    }
+
+    /// This summary with all prices converted to `currency`.
+    pub fn converted(&self, currency: &Currency) -> Self {
+        let convert = |price: Option<f32>| price.map(|price| currency.convert(price));
+        Self {
+            count: self.count,
+            min: convert(self.min),
+            max: convert(self.max),
+            avg: convert(self.avg),
+        }
+    }
+
+    /// Print this summary as footer below the meals of a canteen.
+    pub fn print(&self, conf: &Config) -> Result<()> {
+        let meals = match self.count {
+            1 => String::from("1 meal"),
+            count => format!("{} meals", count),
+        };
+        let currency = conf.currency();
+        let prices = match (self.min, self.max, self.avg) {
+            (Some(min), Some(max), Some(avg)) => format!(
+                ", students pay {} - {}, {} on average",
+                currency.format(min),
+                currency.format(max),
+                currency.format(avg)
+            ),
+            _ => String::new(),
+        };
+        try_println!("{}", *PRE)?;
+        try_println!(
+            "{} {}",
+            *PRE,
+            color!(format!("{}{}", meals, prices); dimmed)
+        )
+    }
+}
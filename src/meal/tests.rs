@@ -0,0 +1,569 @@
+use std::collections::{BTreeSet, HashSet};
+
+use pretty_assertions::assert_eq;
+use structopt::StructOpt;
+
+use crate::{
+    cache::{API, CACHE},
+    config::{args::Args, rule::TagRule, PriceTags},
+    OPEN_MENSA_API,
+};
+
+use super::*;
+
+fn prices() -> Prices {
+    Prices {
+        students: Some(1.0),
+        employees: Some(2.0),
+        pupils: Some(3.0),
+        others: None,
+    }
+}
+
+#[test]
+fn it_selects_all_prices_by_default() {
+    assert_eq!(
+        prices().select(&HashSet::new()),
+        vec![Some(1.0), Some(2.0), Some(3.0), None]
+    );
+}
+
+#[test]
+fn it_selects_exactly_the_given_tiers() {
+    let tiers = |tiers: &[PriceTags]| tiers.iter().copied().collect::<HashSet<_>>();
+    assert_eq!(
+        prices().select(&tiers(&[PriceTags::Pupil])),
+        vec![Some(3.0)]
+    );
+    assert_eq!(
+        prices().select(&tiers(&[PriceTags::Other, PriceTags::Student])),
+        vec![Some(1.0), None]
+    );
+    assert_eq!(
+        prices().select(&tiers(&[
+            PriceTags::Pupil,
+            PriceTags::Employee,
+            PriceTags::Student
+        ])),
+        vec![Some(1.0), Some(2.0), Some(3.0)]
+    );
+}
+
+#[test]
+fn it_formats_the_selected_prices() {
+    let tiers: HashSet<_> = [PriceTags::Other, PriceTags::Student].into();
+    assert_eq!(
+        prices().to_plain_strings_in(&tiers, &Currency::default()),
+        vec![Some(String::from("1.00€")), None]
+    );
+    let currency = Currency {
+        symbol: String::from("£"),
+        decimals: 1,
+        conversion_rate: Some(2.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        prices().to_plain_strings_in(&HashSet::new(), &currency),
+        vec![
+            Some(String::from("2.0£")),
+            Some(String::from("4.0£")),
+            Some(String::from("6.0£")),
+            None
+        ]
+    );
+    assert_eq!(currency.missing(), "-.-£");
+}
+
+#[test]
+fn it_formats_prices_for_the_terminal() {
+    // Without configuration, all tiers are selected
+    let conf = Config::from_args(Args::from_iter(&["mensa"]));
+    assert_eq!(
+        prices().to_plain_string(&conf),
+        "1.00€ / 2.00€ / 3.00€ / -.--€"
+    );
+    let ansi = Regex::new("\x1b\\[[0-9;]*m").unwrap();
+    let terminal = prices().to_terminal_string(&conf, &[6, 6]);
+    assert_eq!(
+        ansi.replace_all(&terminal, ""),
+        "(  1.00€ /  2.00€ / 3.00€ / -.--€ )"
+    );
+}
+
+#[test]
+fn prices_are_formatted_with_the_given_config() {
+    let toml = "[currency]\nsymbol = \"£\"\nconversion-rate = 2.0";
+    let conf = Config::from_args(Args::from_iter(&[
+        "mensa",
+        "--config-toml",
+        toml,
+        "meals",
+        "--price",
+        "student",
+    ]));
+    assert_eq!(prices().to_plain_string(&conf), "2.00£");
+    assert_eq!(prices().column_widths(&conf), vec![5]);
+}
+
+fn meta(name: &str, tags: &[Tag]) -> Meta {
+    Meta {
+        name: name.into(),
+        tags: tags.iter().copied().collect(),
+        descs: BTreeSet::new(),
+        prices: prices(),
+        category: String::from("Hauptgericht"),
+        notes: vec![],
+    }
+}
+
+#[test]
+fn it_moves_favourites_first_stably() {
+    let metas = [
+        meta("A", &[]),
+        meta("B", &[Tag::Fish]),
+        meta("C", &[]),
+        meta("D", &[Tag::Fish]),
+    ];
+    let mut meals: Vec<_> = metas
+        .iter()
+        .enumerate()
+        .map(|(id, meta)| MealComplete { id, meta })
+        .collect();
+    let favs = Rule {
+        tag: TagRule {
+            add: vec![Tag::Fish],
+            sub: vec![],
+        },
+        ..Default::default()
+    };
+    sort_favourites_first(&mut meals, &favs);
+    let names: Vec<_> = meals.iter().map(|meal| meal.meta.name.as_str()).collect();
+    assert_eq!(names, vec!["B", "D", "A", "C"]);
+}
+
+#[test]
+fn it_marks_favourites_in_json() {
+    let fish = meta("Fish", &[Tag::Fish]);
+    let other = meta("Other", &[]);
+    let meals = vec![
+        MealComplete { id: 0, meta: &fish },
+        MealComplete {
+            id: 1,
+            meta: &other,
+        },
+    ];
+    let favs = Rule {
+        tag: TagRule {
+            add: vec![Tag::Fish],
+            sub: vec![],
+        },
+        ..Default::default()
+    };
+    let json = serde_json::to_value(to_json(meals, &favs, None, None, None)).unwrap();
+    assert_eq!(json[0]["name"], "Fish");
+    assert_eq!(json[0]["is_favourite"], true);
+    assert_eq!(json[1]["is_favourite"], false);
+}
+
+#[test]
+fn it_converts_prices() {
+    let currency = Currency {
+        symbol: String::from("£"),
+        conversion_rate: Some(0.855),
+        ..Default::default()
+    };
+    let expected = Prices {
+        students: Some(0.86),
+        employees: Some(1.71),
+        pupils: Some(2.57),
+        others: None,
+    };
+    assert_eq!(prices().converted(&currency), expected);
+    assert_eq!(currency.format(1.0), "0.86£");
+    assert_eq!(Currency::default().format(1.0), "1.00€");
+}
+
+#[test]
+fn it_converts_prices_in_json_on_request() {
+    let meta = meta("Fish", &[]);
+    let currency = Currency {
+        conversion_rate: Some(2.0),
+        ..Default::default()
+    };
+    let json = |currency| {
+        let meals = vec![MealComplete { id: 0, meta: &meta }];
+        serde_json::to_value(to_json(meals, &Rule::default(), currency, None, None)).unwrap()
+    };
+    assert_eq!(json(None)[0]["prices"]["students"], 1.0);
+    assert_eq!(json(Some(&currency))[0]["prices"]["students"], 2.0);
+}
+
+#[test]
+fn completing_a_meal_without_meta_fails() {
+    let mut meal = Meal {
+        id: 42,
+        meta: Fetchable::None,
+    };
+    let why = meal.complete().unwrap_err();
+    assert!(matches!(why, Error::MealMetaUnavailable(42)));
+}
+
+#[test]
+fn days_are_labeled_relative_to_today() {
+    let today = Local::now().date_naive();
+    assert_eq!(day_label(&today), "Today");
+    assert_eq!(day_label(&(today + Duration::days(1))), "Tomorrow");
+    let later = today + Duration::days(2);
+    assert_eq!(day_label(&later), later.format("%Y-%m-%d").to_string());
+}
+
+#[test]
+fn weekends_suggest_the_next_weekday() {
+    let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    // 2024-01-06 is a saturday
+    assert_eq!(next_weekday_if_weekend(&date(6)), Some(date(8)));
+    assert_eq!(next_weekday_if_weekend(&date(7)), Some(date(8)));
+    assert_eq!(next_weekday_if_weekend(&date(5)), None);
+    assert_eq!(next_weekday_if_weekend(&date(8)), None);
+}
+
+#[test]
+fn summaries_cover_student_prices_only() {
+    let priced = |students| Meta {
+        prices: Prices {
+            students,
+            ..prices()
+        },
+        ..meta("Essen", &[])
+    };
+    let metas = [priced(Some(2.5)), priced(None), priced(Some(1.5))];
+    let meals: Vec<_> = metas
+        .iter()
+        .map(|meta| MealComplete { id: 0, meta })
+        .collect();
+    let summary = Summary::from_meals(&meals);
+    assert_eq!(summary.count, 3);
+    assert_eq!(summary.min, Some(1.5));
+    assert_eq!(summary.max, Some(2.5));
+    assert_eq!(summary.avg, Some(2.0));
+    // Meals without a student price are still counted
+    let summary = Summary::from_meals(&meals[1..2]);
+    assert_eq!(summary.count, 1);
+    assert_eq!(summary.min, None);
+    assert_eq!(summary.max, None);
+    assert_eq!(summary.avg, None);
+}
+
+#[test]
+fn full_json_pairs_canteens_with_meals() {
+    let meta = meta("Fish", &[]);
+    let meals = vec![MealComplete { id: 0, meta: &meta }];
+    let canteen = CanteenWithMeals {
+        canteen: serde_json::json!({"id": 1, "name": "Mensa"}),
+        meals: MealList::Flat(to_json(meals, &Rule::default(), None, None, None)),
+        summary: None,
+    };
+    let json = serde_json::to_value(&canteen).unwrap();
+    assert_eq!(json["canteen"]["name"], "Mensa");
+    assert_eq!(json["meals"][0]["name"], "Fish");
+    assert!(json.get("summary").is_none());
+}
+
+#[test]
+fn json_meals_can_be_grouped_by_category() {
+    let in_category = |name, category: &str| Meta {
+        category: category.into(),
+        ..meta(name, &[])
+    };
+    let metas = [
+        in_category("Soup", "Suppen"),
+        in_category("Pasta", "Hauptgericht"),
+        in_category("Stew", "Suppen"),
+    ];
+    let meals: Vec<_> = metas
+        .iter()
+        .map(|meta| MealComplete { id: 0, meta })
+        .collect();
+    let meals = MealList::new(to_json(meals, &Rule::default(), None, None, None), true);
+    let json = serde_json::to_value(&meals).unwrap();
+    let categories: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(categories, vec!["Suppen", "Hauptgericht"]);
+    assert_eq!(json["Suppen"][1]["name"], "Stew");
+    assert!(matches!(
+        "category,canteen".parse::<crate::config::args::GroupBy>(),
+        Err(Error::InvalidGroupBy(_))
+    ));
+}
+
+#[test]
+fn meals_without_the_selected_price_are_unpriced() {
+    let tiers = |tiers: &[PriceTags]| tiers.iter().copied().collect::<HashSet<_>>();
+    let prices = Prices {
+        students: None,
+        ..prices()
+    };
+    assert!(!prices.is_priced(&tiers(&[PriceTags::Student])));
+    assert!(prices.is_priced(&tiers(&[PriceTags::Student, PriceTags::Employee])));
+    assert!(prices.is_priced(&HashSet::new()));
+    let unpriced = Prices {
+        students: None,
+        employees: None,
+        pupils: None,
+        others: None,
+    };
+    assert!(!unpriced.is_priced(&HashSet::new()));
+}
+
+#[test]
+fn it_serializes_tags_and_descriptions_sorted() {
+    let mut meta = meta(
+        "Eintopf",
+        &[Tag::Vegan, Tag::Gluten, Tag::Cow, Tag::Acidifier],
+    );
+    meta.descs = ["Zwiebeln", "Apfel", "Mais"]
+        .iter()
+        .map(|desc| String::from(*desc))
+        .collect();
+    let json = serde_json::to_value(&meta).unwrap();
+    assert_eq!(
+        json["tags"],
+        serde_json::json!(["Acidifier", "Cow", "Gluten", "Vegan"])
+    );
+    assert_eq!(
+        json["descs"],
+        serde_json::json!(["Apfel", "Mais", "Zwiebeln"])
+    );
+}
+
+#[test]
+fn json_lists_canteens_by_ascending_id() {
+    // Sorting these as strings would reverse them
+    let ids = [100_000_000_851, 90_000_000_851];
+    let date = NaiveDate::from_ymd_opt(2021, 11, 3).unwrap();
+    for id in ids {
+        let days = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+        API.register_single(
+            &days,
+            r#"[{ "date": "2021-11-03", "closed": false }]"#,
+            None,
+        );
+        let meals = format!("{}/canteens/{}/days/2021-11-03/meals", OPEN_MENSA_API, id);
+        API.register_single(&meals, "[]", None);
+    }
+    let mut canteens: Vec<_> = ids.iter().copied().map(Canteen::from).collect();
+    let json = Meal::json_for_day(&Context::global(), &mut canteens, &date, false).unwrap();
+    let keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, vec!["90000000851", "100000000851"]);
+}
+
+#[test]
+fn json_is_truncated_to_the_limit() {
+    let id = 90_000_000_860;
+    let date = NaiveDate::from_ymd_opt(2021, 11, 3).unwrap();
+    let days = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+    API.register_single(
+        &days,
+        r#"[{ "date": "2021-11-03", "closed": false }]"#,
+        None,
+    );
+    let meals = format!("{}/canteens/{}/days/2021-11-03/meals", OPEN_MENSA_API, id);
+    let meal = |id, name| {
+        format!(
+            r#"{{ "id": {}, "name": "{}", "category": "Essen", "prices": {{}}, "notes": [] }}"#,
+            id, name
+        )
+    };
+    let value = format!("[{}, {}]", meal(1, "Erstes"), meal(2, "Zweites"));
+    API.register_single(&meals, &value, None);
+    let conf = Config::from_args(Args::from_iter(&["mensa", "--limit", "1"]));
+    let ctx = Context {
+        cache: &CACHE,
+        conf: &conf,
+    };
+    let mut canteens = vec![Canteen::from(id)];
+    let json = Meal::json_for_day(&ctx, &mut canteens, &date, false).unwrap();
+    let meals = json[id.to_string()].as_array().unwrap();
+    assert_eq!(meals.len(), 1);
+    assert_eq!(meals[0]["name"], "Erstes");
+    assert_eq!(meals[0].get("date"), None);
+    let json = Meal::json_for_day(&ctx, &mut canteens, &date, true).unwrap();
+    assert_eq!(json[id.to_string()][0]["date"], "2021-11-03");
+}
+
+#[test]
+fn json_summaries_are_converted_with_the_prices() {
+    let id = 90_000_000_821;
+    let date = NaiveDate::from_ymd_opt(2021, 11, 3).unwrap();
+    let days = format!("{}/canteens/{}/days", OPEN_MENSA_API, id);
+    API.register_single(
+        &days,
+        r#"[{ "date": "2021-11-03", "closed": false }]"#,
+        None,
+    );
+    let meals = format!("{}/canteens/{}/days/2021-11-03/meals", OPEN_MENSA_API, id);
+    let value = r#"[{ "id": 1, "name": "Essen", "category": "Essen",
+                      "prices": { "students": 1.5 }, "notes": [] }]"#;
+    API.register_single(&meals, value, None);
+    let toml = "[currency]\nconversion-rate = 2.0";
+    let conf = Config::from_args(Args::from_iter(&[
+        "mensa",
+        "--json",
+        "--summary",
+        "--convert",
+        "--config-toml",
+        toml,
+    ]));
+    let ctx = Context {
+        cache: &CACHE,
+        conf: &conf,
+    };
+    let mut canteens = vec![Canteen::from(id)];
+    let json = Meal::json_for_day(&ctx, &mut canteens, &date, false).unwrap();
+    let canteen = &json[id.to_string()];
+    assert_eq!(canteen["meals"][0]["prices"]["students"], 3.0);
+    assert_eq!(canteen["summary"]["min"], 3.0);
+    assert_eq!(canteen["summary"]["max"], 3.0);
+    assert_eq!(canteen["summary"]["avg"], 3.0);
+}
+
+#[test]
+fn it_splits_names_at_filter_matches() {
+    let regexes = [
+        Regex::new("(?i)curry").unwrap(),
+        Regex::new("rry w").unwrap(),
+    ];
+    assert_eq!(
+        complete::split_matches("Currywurst mit Curry", &regexes),
+        vec![("Curry", true), ("wurst mit ", false), ("Curry", true)]
+    );
+    assert_eq!(
+        complete::split_matches("Curry wurst", &regexes),
+        vec![("Curry w", true), ("urst", false)]
+    );
+    assert_eq!(
+        complete::split_matches("Salat", &regexes),
+        vec![("Salat", false)]
+    );
+}
+
+#[test]
+fn tables_have_a_row_per_meal() {
+    let metas = [meta("Bohnengemüse", &[Tag::Vegan]), meta("Fischfilet", &[])];
+    let meals: Vec<_> = metas
+        .iter()
+        .enumerate()
+        .map(|(id, meta)| MealComplete { id, meta })
+        .collect();
+    let table = MealComplete::table(&Context::global(), &meals, &Rule::default(), &[], 80);
+    assert_eq!(table.row_count(), 2);
+    let rendered = table.to_string();
+    for text in [
+        "Name",
+        "Category",
+        "Price",
+        "Tags",
+        "Bohnengemüse",
+        "Hauptgericht",
+    ] {
+        assert!(
+            rendered.contains(text),
+            "{:?} missing in {}",
+            text,
+            rendered
+        );
+    }
+}
+
+#[test]
+fn markdown_escapes_special_characters() {
+    assert_eq!(markdown::escape("Fish | Chips"), "Fish \\| Chips");
+    assert_eq!(markdown::escape("*hot*_dog_"), "\\*hot\\*\\_dog\\_");
+    assert_eq!(markdown::escape("a\nb"), "a b");
+    assert_eq!(
+        markdown::escape("Bohnen-Gemüse (vegan)"),
+        "Bohnen-Gemüse (vegan)"
+    );
+}
+
+#[test]
+fn markdown_tables_have_a_row_per_meal() {
+    let metas = [meta("Pommes | Mayo", &[Tag::Vegan])];
+    let meals: Vec<_> = metas
+        .iter()
+        .enumerate()
+        .map(|(id, meta)| MealComplete { id, meta })
+        .collect();
+    let table = markdown::table(&Context::global(), &meals);
+    let rows: Vec<_> = table.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], "| Name | Category | Price | Tags |");
+    assert!(rows[2].starts_with("| Pommes \\| Mayo | Hauptgericht | "));
+    assert!(rows[2].ends_with(&format!(" | {} |", Tag::Vegan)));
+}
+
+#[test]
+fn the_price_floor_is_inclusive() {
+    let tiers = |tiers: &[PriceTags]| tiers.iter().copied().collect::<HashSet<_>>();
+    let euro = Currency::default();
+    let students = tiers(&[PriceTags::Student]);
+    assert!(prices().is_within(&students, Some(1.0), None, &euro));
+    assert!(!prices().is_within(&students, Some(1.01), None, &euro));
+    // Any selected tier may reach the floor
+    assert!(prices().is_within(&HashSet::new(), Some(3.0), None, &euro));
+    // Missing prices never reach it
+    assert!(!prices().is_within(&tiers(&[PriceTags::Other]), Some(0.0), None, &euro));
+}
+
+#[test]
+fn the_price_band_is_inclusive() {
+    let tiers = |tiers: &[PriceTags]| tiers.iter().copied().collect::<HashSet<_>>();
+    let euro = Currency::default();
+    let students = tiers(&[PriceTags::Student]);
+    assert!(prices().is_within(&students, None, Some(1.0), &euro));
+    assert!(!prices().is_within(&students, None, Some(0.99), &euro));
+    assert!(prices().is_within(&students, Some(1.0), Some(1.0), &euro));
+    // Some selected tier must lie within both bounds
+    let all = HashSet::new();
+    assert!(prices().is_within(&all, Some(1.5), Some(2.5), &euro));
+    assert!(!prices().is_within(&all, Some(1.5), Some(1.9), &euro));
+    // The bounds are compared to converted prices
+    let doubled = Currency {
+        conversion_rate: Some(2.0),
+        ..Default::default()
+    };
+    assert!(!prices().is_within(&students, None, Some(1.5), &doubled));
+    assert!(prices().is_within(&students, None, Some(2.0), &doubled));
+    assert!(!prices().is_within(&tiers(&[PriceTags::Other]), None, Some(10.0), &euro));
+}
+
+#[test]
+fn secondary_tags_are_truncated_to_fit() {
+    use super::complete::{plain_width, truncate};
+    let tags = (1..=20).map(|nr| nr.to_string()).join(" ");
+    let line = truncate(&tags, 20);
+    assert!(line.width() <= 20);
+    assert!(line.starts_with("1 2 3"));
+    assert!(line.ends_with("…") || line.ends_with("..."));
+    assert_eq!(truncate("1 2 3", 20), "1 2 3");
+    assert_eq!(truncate(&tags, 0), "");
+    assert_eq!(plain_width("\x1b[90m1 2\x1b[0m"), 3);
+}
+
+#[test]
+fn meals_can_be_formatted() {
+    use crate::config::args::MealFormat;
+    let meta = meta("Soup {hot}", &[Tag::Vegan, Tag::Gluten]);
+    let meal = MealComplete { id: 7, meta: &meta };
+    let format: MealFormat = "{id} {name} [{category}] {tags} {{x}}".parse().unwrap();
+    assert_eq!(
+        meal.format(&Context::global(), &format.0).unwrap(),
+        "7 Soup {hot} [Hauptgericht] Gluten, Vegan {x}"
+    );
+    let format: MealFormat = "{price.student}/{price.pupil}".parse().unwrap();
+    let currency = Currency::default();
+    assert_eq!(
+        meal.format(&Context::global(), &format.0).unwrap(),
+        format!("{}/{}", currency.format(1.0), currency.format(3.0))
+    );
+    assert!("{name} {price.students}".parse::<MealFormat>().is_err());
+}
@@ -9,7 +9,7 @@ use serde::de::DeserializeOwned;
 use std::marker::PhantomData;
 
 use crate::{
-    cache::{Cache, CACHE},
+    cache::{from_json_str, Cache, Source},
     error::{Error, Result},
 };
 
@@ -30,67 +30,111 @@ use crate::{
 /// [ { "id": 3 },
 ///   { "id": 4 } ]
 /// ```
-pub struct PaginatedList<T>
+pub struct PaginatedList<'c, T, C>
 where
     T: DeserializeOwned,
+    C: Cache,
 {
+    cache: &'c C,
     next_page: Option<String>,
     ttl: Duration,
+    refresh: bool,
+    /// [`Source::Network`] once any page has been requested.
+    source: Source,
     __item: PhantomData<T>,
 }
 
-impl<T> PaginatedList<T>
+impl<'c, T, C> PaginatedList<'c, T, C>
 where
     T: DeserializeOwned,
+    C: Cache,
 {
     /// Create a new page iterator
     ///
     /// Takes the `url` for the first page and a
-    /// `local_ttl` for the cached values.
-    pub fn new<S: AsRef<str>>(url: S, ttl: Duration) -> Self {
+    /// `local_ttl` for the values cached in `cache`.
+    /// With `refresh`, cached pages are ignored, but updated.
+    pub fn new<S: AsRef<str>>(cache: &'c C, url: S, ttl: Duration, refresh: bool) -> Self {
         PaginatedList {
+            cache,
             ttl,
+            refresh,
+            source: Source::Cache,
             next_page: Some(url.as_ref().into()),
             __item: PhantomData,
         }
     }
 }
 
-impl<T> PaginatedList<T>
+impl<T, C> PaginatedList<'_, T, C>
 where
     T: DeserializeOwned,
+    C: Cache,
 {
     /// Consumes this iterator, flattening the collected pages.
     pub fn consume(self) -> Result<Vec<T>> {
         self.flatten_ok().try_collect()
     }
+
+    /// Like [`Self::consume`], but also tell where the pages came from.
+    pub fn consume_with_source(mut self) -> Result<(Vec<T>, Source)> {
+        let items = self.by_ref().flatten_ok().try_collect()?;
+        Ok((items, self.source))
+    }
+
+    /// Consumes this iterator, keeping the items of all successful pages.
+    ///
+    /// Errors of failed pages are returned alongside the items.
+    pub fn consume_partial(self) -> (Vec<T>, Vec<Error>) {
+        let mut items = vec![];
+        let mut errors = vec![];
+        for page in self {
+            match page {
+                Ok(page) => items.extend(page),
+                Err(why) => errors.push(why),
+            }
+        }
+        (items, errors)
+    }
 }
 
-impl<T> Iterator for PaginatedList<T>
+impl<T, C> Iterator for PaginatedList<'_, T, C>
 where
     T: DeserializeOwned,
+    C: Cache,
 {
     type Item = Result<Vec<T>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // This will yield until no next_page is available
         let curr_page = self.next_page.take()?;
-        let res = CACHE.fetch(curr_page, self.ttl, |text, headers| {
-            let val = serde_json::from_str::<Vec<_>>(&text)
-                .map_err(|why| Error::Deserializing(why, "fetching json in pagination iterator"))?;
-            Ok((val, headers.this_page, headers.next_page, headers.last_page))
-        });
+        let res = self.cache.fetch(
+            curr_page.clone(),
+            self.ttl,
+            self.refresh,
+            |text, headers, source| {
+                let verbose = self.cache.options().verbose;
+                let val = from_json_str::<Vec<_>>(
+                    &curr_page,
+                    &text,
+                    "fetching json in pagination iterator",
+                    verbose,
+                )?;
+                Ok((val, headers, source))
+            },
+        );
         match res {
-            Ok((val, this_page, next_page, last_page)) => {
+            Ok((val, headers, source)) => {
+                self.source = self.source.max(source);
                 // Only update next_page, if we're not on the last page!
-                // This should be safe for all cases
-                if this_page.unwrap_or_default() < last_page.unwrap_or_default() {
-                    // OpenMensa returns empty lists for large pages
-                    // this is just to keep me sane
-                    if !val.is_empty() {
-                        self.next_page = next_page;
-                    }
-                }
+                // Empty pages are not necessarily the last one,
+                // the headers decide when to stop. Without page counters,
+                // follow the links until there is none left.
+                self.next_page = match (headers.this_page, headers.last_page) {
+                    (Some(this_page), Some(last_page)) if this_page >= last_page => None,
+                    // A page linking to itself would never end
+                    _ => headers.next_page.filter(|next| *next != curr_page),
+                };
                 Some(Ok(val))
             }
             Err(why) => {
@@ -0,0 +1,62 @@
+//! Bounded parallel execution for fetching many resources at once.
+
+use std::{
+    collections::VecDeque,
+    sync::{mpsc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Map `f` over `items` using at most `max` threads at once.
+///
+/// Results are returned in the order of `items`.
+///
+/// The workers are scoped, `f` may borrow from the caller.
+pub fn map_bounded<T, R, F>(items: Vec<T>, max: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = max.max(1).min(items.len());
+    let queue: VecDeque<_> = items.into_iter().enumerate().collect();
+    let queue = Mutex::new(queue);
+    let (tx, rx) = mpsc::channel();
+    let mut results: Vec<_> = thread::scope(|scope| {
+        for _ in 0..workers {
+            let (queue, f, tx) = (&queue, &f, tx.clone());
+            scope.spawn(move || loop {
+                let next = queue.lock().expect("Locking work queue failed").pop_front();
+                match next {
+                    // The receiver outlives all workers
+                    Some((idx, item)) => tx.send((idx, f(item))).unwrap(),
+                    None => break,
+                }
+            });
+        }
+        drop(tx);
+        rx.into_iter().collect()
+    });
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Run `f` on a detached thread and wait at most `timeout` for its result.
+///
+/// The thread is not stopped on timeout, it keeps running in the
+/// background until it finishes or the program exits.
+pub fn within<R, F>(timeout: Duration, f: F) -> Option<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone if we timed out
+        let _ = tx.send(f());
+    });
+    rx.recv_timeout(timeout).ok()
+}
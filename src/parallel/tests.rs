@@ -0,0 +1,40 @@
+use pretty_assertions::assert_eq;
+
+use std::{collections::HashSet, sync::Barrier, time::Duration};
+
+use super::*;
+
+#[test]
+fn it_keeps_the_order_of_items() {
+    let doubled = map_bounded((0..20).collect(), 3, |nr: usize| nr * 2);
+    assert_eq!(doubled, (0..20).map(|nr| nr * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn it_bounds_the_number_of_threads() {
+    // Every item waits for a second one, keeping both workers busy at once
+    let barrier = Barrier::new(2);
+    let threads = map_bounded((0..16).collect(), 2, |_: usize| {
+        barrier.wait();
+        thread::current().id()
+    });
+    let threads: HashSet<_> = threads.into_iter().collect();
+    assert_eq!(threads.len(), 2);
+}
+
+#[test]
+fn it_handles_empty_input() {
+    let empty: Vec<usize> = map_bounded(vec![], 4, |nr: usize| nr);
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn it_gives_up_waiting_after_the_timeout() {
+    let fast = within(Duration::from_secs(10), || 42);
+    assert_eq!(fast, Some(42));
+    let (release, blocked) = mpsc::channel::<()>();
+    let slow = within(Duration::from_millis(5), move || blocked.recv());
+    assert_eq!(slow, None);
+    // The thread was not joined and is still waiting
+    release.send(()).unwrap();
+}
@@ -1,51 +1,73 @@
 //! This contains the [`DummyApi`] used for testing purposes.
 use std::{collections::HashMap, sync::RwLock};
 
+use chrono::{DateTime, Utc};
 use reqwest::StatusCode;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 use super::{Api, Headers, Response};
 
 #[derive(Debug, Clone)]
 struct KnownResp {
     etag: Option<String>,
+    last_modified: Option<DateTime<Utc>>,
     value: String,
     this_page: Option<usize>,
     next_page: Option<String>,
     last_page: Option<usize>,
+    not_found: bool,
+    unreachable: bool,
 }
 
 /// A dummy API, serving local, deterministic Responses
 #[derive(Debug)]
 pub struct DummyApi {
     known: RwLock<HashMap<String, KnownResp>>,
+    /// Number of requests per url.
+    requests: RwLock<HashMap<String, usize>>,
 }
 
 impl Api for DummyApi {
     fn create() -> Result<Self> {
         Ok(DummyApi {
             known: RwLock::new(HashMap::new()),
+            requests: RwLock::new(HashMap::new()),
         })
     }
 
-    fn get<'url, S>(&self, url: &'url str, etag: Option<S>) -> Result<Response<'url>>
-    where
-        S: AsRef<str>,
-    {
+    fn get<'url>(&self, url: &'url str, old: Option<&Headers>) -> Result<Response<'url>> {
+        *self
+            .requests
+            .write()
+            .expect("Writing request counts failed")
+            .entry(url.to_owned())
+            .or_default() += 1;
         let read = self.known.read().expect("Reading known urls failed");
-        let etag = etag.map(|etag| etag.as_ref().to_owned());
+        let etag = old.and_then(|old| old.etag.clone());
+        let since = old.and_then(|old| old.last_modified.as_deref());
         match read.get(url) {
+            Some(resp) if resp.unreachable => Err(network_error()),
             Some(resp) => {
                 let resp = resp.clone();
+                // Like real servers, ignore If-Modified-Since if If-None-Match is present
+                let status = if resp.not_found {
+                    StatusCode::NOT_FOUND
+                } else if etag.is_some() {
+                    status_from_etags(&resp.etag, &etag)
+                } else {
+                    status_from_last_modified(&resp.last_modified, since)
+                };
                 Ok(Response {
                     url,
-                    status: status_from_etags(&resp.etag, &etag),
+                    status,
                     headers: Headers {
                         etag: resp.etag,
+                        last_modified: resp.last_modified.map(|time| format_http_date(&time)),
                         this_page: resp.this_page,
                         next_page: resp.next_page,
                         last_page: resp.last_page,
+                        not_found: false,
                     },
                     body: resp.value,
                 })
@@ -61,6 +83,37 @@ impl DummyApi {
         self.register(url, value, etag, Some(1), None, Some(1))
     }
 
+    /// Register a single page without an etag, but last modified at `time`.
+    pub fn register_with_last_modified(&self, url: &str, value: &str, time: DateTime<Utc>) {
+        self.register(url, value, None, Some(1), None, Some(1));
+        let mut write = self.known.write().expect("Writing known urls failed");
+        let resp = write.get_mut(url).expect("BUG: Registered url vanished");
+        resp.last_modified = Some(time);
+    }
+
+    /// Register a page that answers with 404 NOT FOUND.
+    pub fn register_not_found(&self, url: &str) {
+        self.register(url, "Not Found", None, None, None, None);
+        let mut write = self.known.write().expect("Writing known urls failed");
+        let resp = write.get_mut(url).expect("BUG: Registered url vanished");
+        resp.not_found = true;
+    }
+
+    /// Let every further request to the registered `url` fail, like without network.
+    pub fn make_unreachable(&self, url: &str) {
+        let mut write = self.known.write().expect("Writing known urls failed");
+        let resp = write
+            .get_mut(url)
+            .expect("BUG: Unregistered url made unreachable");
+        resp.unreachable = true;
+    }
+
+    /// Number of requests sent to `url` so far.
+    pub fn request_count(&self, url: &str) -> usize {
+        let read = self.requests.read().expect("Reading request counts failed");
+        read.get(url).copied().unwrap_or_default()
+    }
+
     /// Register multiple subsequent pages.
     ///
     /// `pages` maps urls to pairs of values and optional etags.
@@ -75,6 +128,17 @@ impl DummyApi {
         }
     }
 
+    /// Register multiple subsequent pages, linked without page counters.
+    ///
+    /// `pages` maps urls to values.
+    pub fn register_linked_pages(&self, pages: &[(&str, &str)]) {
+        let mut pages = pages.iter().peekable();
+        while let Some((url, value)) = pages.next() {
+            let next = pages.peek().map(|(url, _)| *url);
+            self.register(url, value, None, None, next, None);
+        }
+    }
+
     fn register(
         &self,
         url: &str,
@@ -91,10 +155,13 @@ impl DummyApi {
             url.to_owned(),
             KnownResp {
                 etag,
+                last_modified: None,
                 value: value.to_owned(),
                 this_page,
                 next_page,
                 last_page,
+                not_found: false,
+                unreachable: false,
             },
         );
         if old.is_some() {
@@ -103,9 +170,32 @@ impl DummyApi {
     }
 }
 
+/// An error like the one returned by reqwest if a request could not be sent.
+fn network_error() -> Error {
+    // Building a request for an invalid url fails without touching the network
+    let why = reqwest::Client::new()
+        .get("unreachable")
+        .build()
+        .expect_err("BUG: Invalid url was accepted");
+    Error::Reqwest(why)
+}
+
 fn status_from_etags(old: &Option<String>, new: &Option<String>) -> StatusCode {
     match (old, new) {
         (Some(old), Some(new)) if old == new => StatusCode::NOT_MODIFIED,
         _ => StatusCode::OK,
     }
 }
+
+fn status_from_last_modified(modified: &Option<DateTime<Utc>>, since: Option<&str>) -> StatusCode {
+    let since = since.and_then(|since| DateTime::parse_from_rfc2822(since).ok());
+    match (modified, since) {
+        (Some(modified), Some(since)) if *modified <= since => StatusCode::NOT_MODIFIED,
+        _ => StatusCode::OK,
+    }
+}
+
+/// Format `time` like a `Last-Modified` header value.
+fn format_http_date(time: &DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
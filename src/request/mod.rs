@@ -14,13 +14,19 @@ mod dummy;
 pub use self::dummy::DummyApi as DefaultApi;
 
 /// Assortment of headers relevant to the program.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Headers {
     pub etag: Option<String>,
+    /// Raw value of the `Last-Modified` header, sent back verbatim.
+    #[serde(default)]
+    pub last_modified: Option<String>,
     pub this_page: Option<usize>,
     pub next_page: Option<String>,
     pub last_page: Option<usize>,
+    /// Marks cache entries of resources that were not found.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub not_found: bool,
 }
 
 /// A subset of a Response, derived from [`::reqwest::Response`].
@@ -44,8 +50,7 @@ where
 
     /// Send a get request.
     ///
-    /// Optionally attach an `If-None-Match` header, if `etag` is `Some`.
-    fn get<'url, S>(&self, url: &'url str, etag: Option<S>) -> Result<Response<'url>>
-    where
-        S: AsRef<str>;
+    /// If the `old` headers of a cached response are given, attach
+    /// `If-None-Match` and `If-Modified-Since` headers derived from them.
+    fn get<'url>(&self, url: &'url str, old: Option<&Headers>) -> Result<Response<'url>>;
 }
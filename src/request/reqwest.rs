@@ -8,6 +8,9 @@ use crate::error::{Error, Result};
 
 use super::{Api, Headers, Response};
 
+/// Some services, i.e. Nominatim, reject requests without a user agent.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
 lazy_static! {
     /// Regex to find the next page in a link header
     /// Probably only applicable to the current version of the openmensa API.
@@ -26,19 +29,21 @@ impl Api for ReqwestApi {
     fn create() -> Result<Self> {
         let client = Client::builder()
             .timeout(*REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT)
             .build()
             .map_err(Error::Reqwest)?;
         Ok(ReqwestApi { client })
     }
 
-    fn get<'url, S>(&self, url: &'url str, etag: Option<S>) -> Result<super::Response<'url>>
-    where
-        S: AsRef<str>,
-    {
+    fn get<'url>(&self, url: &'url str, old: Option<&Headers>) -> Result<super::Response<'url>> {
         let mut builder = self.client.get(url);
-        if let Some(etag) = etag {
+        if let Some(etag) = old.and_then(|old| old.etag.as_ref()) {
             let etag_key = reqwest::header::IF_NONE_MATCH;
-            builder = builder.header(etag_key, etag.as_ref());
+            builder = builder.header(etag_key, etag);
+        }
+        if let Some(last_modified) = old.and_then(|old| old.last_modified.as_ref()) {
+            let last_modified_key = reqwest::header::IF_MODIFIED_SINCE;
+            builder = builder.header(last_modified_key, last_modified);
         }
         let resp = builder.send().map_err(Error::Reqwest)?;
         Ok(Response {
@@ -57,6 +62,10 @@ impl From<reqwest::header::HeaderMap> for Headers {
             let utf8 = raw.to_str().ok()?;
             Some(utf8.to_string())
         });
+        let last_modified = map.get(LAST_MODIFIED).and_then(|raw| {
+            let utf8 = raw.to_str().ok()?;
+            Some(utf8.to_string())
+        });
         let this_page = map.get("x-current-page").and_then(|raw| {
             let utf8 = raw.to_str().ok()?;
             utf8.parse().ok()
@@ -72,9 +81,11 @@ impl From<reqwest::header::HeaderMap> for Headers {
         });
         Self {
             etag,
+            last_modified,
             this_page,
             last_page,
             next_page,
+            not_found: false,
         }
     }
 }
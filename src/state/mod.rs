@@ -0,0 +1,88 @@
+//! State remembered between invocations, kept apart from the configuration.
+//!
+//! The state is stored as json in `state.json` inside the data directory,
+//! see [`State::path`].
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    canteen::CanteenId,
+    error::{Error, Result, ResultExt},
+    DIR,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct State {
+    /// The canteen of the last successful `mensa meals --id`.
+    #[serde(default)]
+    pub last_canteen_id: Option<CanteenId>,
+    /// Hashes of the meals printed last, see `--only-if-changed`.
+    ///
+    /// Keyed by the list of canteen ids the meals belong to.
+    #[serde(default)]
+    pub meal_hashes: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Location of the state file.
+    pub fn path() -> PathBuf {
+        DIR.data_dir().join("state.json")
+    }
+
+    /// Load the state from `path`, a missing file is an empty state.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(raw) => {
+                serde_json::from_str(&raw).map_err(|why| Error::Deserializing(why, "reading state"))
+            }
+            Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(why) => Err(Error::Io(why, "reading state")),
+        }
+    }
+
+    /// Save the state to `path`, creating missing directories.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|why| Error::Io(why, "creating state directory"))?;
+        }
+        let raw = serde_json::to_string_pretty(self)
+            .map_err(|why| Error::Serializing(why, "writing state"))?;
+        fs::write(path, raw).map_err(|why| Error::Io(why, "writing state"))
+    }
+
+    /// Remember `id` as the last used canteen in the state at `path`.
+    ///
+    /// A broken state file is replaced.
+    pub fn remember_canteen(path: &Path, id: CanteenId) -> Result<()> {
+        let mut state = Self::load(path).log_warn().unwrap_or_default();
+        state.last_canteen_id = Some(id);
+        state.save(path)
+    }
+
+    /// Is `hash` the stored hash of the meals of the canteens `key` in the state at `path`?
+    ///
+    /// A broken state file has no hashes.
+    pub fn has_meal_hash(path: &Path, key: &str, hash: &str) -> bool {
+        let state = Self::load(path).log_warn().unwrap_or_default();
+        state.meal_hashes.get(key).map(String::as_str) == Some(hash)
+    }
+
+    /// Store the `hash` of the meals of the canteens `key` in the state at `path`.
+    ///
+    /// A broken state file is replaced.
+    pub fn store_meal_hash(path: &Path, key: String, hash: String) -> Result<()> {
+        let mut state = Self::load(path).log_warn().unwrap_or_default();
+        state.meal_hashes.insert(key, hash);
+        state.save(path)
+    }
+}
@@ -0,0 +1,37 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+#[test]
+fn it_remembers_the_last_canteen() {
+    let dir = std::env::temp_dir().join(format!("mensa-state-{}", std::process::id()));
+    let path = dir.join("nested").join("state.json");
+    assert_eq!(State::load(&path).unwrap(), State::default());
+    State::remember_canteen(&path, 63).unwrap();
+    assert_eq!(State::load(&path).unwrap().last_canteen_id, Some(63));
+    // Broken files are replaced
+    fs::write(&path, "{").unwrap();
+    assert!(State::load(&path).is_err());
+    State::remember_canteen(&path, 70).unwrap();
+    assert_eq!(State::load(&path).unwrap().last_canteen_id, Some(70));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn it_detects_changed_meal_hashes() {
+    let dir = std::env::temp_dir().join(format!("mensa-hashes-{}", std::process::id()));
+    let path = dir.join("state.json");
+    let store = |key: &str, hash: &str| {
+        State::store_meal_hash(&path, key.to_owned(), hash.to_owned()).unwrap()
+    };
+    assert!(!State::has_meal_hash(&path, "63", "a"));
+    // Checking alone does not store the hash
+    assert!(!State::has_meal_hash(&path, "63", "a"));
+    store("63", "a");
+    assert!(State::has_meal_hash(&path, "63", "a"));
+    assert!(!State::has_meal_hash(&path, "63,70", "a"));
+    store("63", "b");
+    assert!(!State::has_meal_hash(&path, "63", "a"));
+    assert!(State::has_meal_hash(&path, "63", "b"));
+    fs::remove_dir_all(dir).unwrap();
+}
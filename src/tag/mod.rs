@@ -0,0 +1,457 @@
+use std::{collections::BTreeMap, fmt};
+
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
+use unicode_width::UnicodeWidthStr;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    canteen::Canteen, config::args::TagSort, context::Context, error::Result,
+    get_sane_terminal_dimensions, meal::MealComplete, print_json,
+};
+
+const ID_WIDTH: usize = 4;
+const TEXT_INDENT: &str = "     ";
+
+lazy_static! {
+    /// These must have the same order as the variants in the [`Tag`] enum.
+    static ref TAG_RE: RegexSet = RegexSet::new([
+        r"(?i)säue?rungsmittel",
+        r"(?i)alkohol",
+        r"(?i)antiox[iy]dation",
+        r"(?i)geschwärzt",
+        r"(?i)kakaohaltige fettglasur",
+        r"(?i)farbstoff",
+        r"(?i)rind",
+        r"(?i)hühnerei|eier|^ei$|enthält ei\*",
+        r"(?i)fisch",
+        r"(?i)geschmacksverstärker",
+        r"(?i)knoblauch",
+        r"(?i)gluten|weizen|hafer|gerste|roggen",
+        r"(?i)milch|laktose",
+        r"(?i)lupine?",
+        r"(?i)senf",
+        r"(?i)schalenfrüchte|nüsse|walnuss|pistazie|mandeln",
+        r"(?i)phosphat",
+        r"(?i)schwein",
+        r"(?i)geflügel",
+        r"(?i)konservierung|konserviert",
+        r"(?i)sellerie",
+        r"(?i)sesam",
+        r"(?i)soja",
+        r"(?i)sulfit|schwefel",
+        r"(?i)süßungsmittel",
+        r"(?i)vegan",
+        r"(?i)fleischlos|vegetarisch|ohne fleisch",
+        r"(?i)(ge)?wachs(t)?",
+    ])
+    .unwrap();
+}
+
+/// A [`Tag`] with the number of meals carrying it, see [`Tag::print_present`].
+#[derive(Debug, Serialize)]
+struct TagCountJson {
+    tag: TagJson,
+    count: usize,
+}
+
+/// Json representation of a [`Tag`], see [`Tag::print_all_json`].
+#[derive(Debug, Serialize)]
+struct TagJson {
+    id: String,
+    number: u8,
+    name: String,
+    desc: &'static str,
+}
+
+/// Value of the json object keyed by tag numbers, see [`Tag::json_map`].
+#[derive(Debug, Serialize)]
+struct TagMapJson {
+    name: String,
+    desc: &'static str,
+    primary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emoji: Option<&'static str>,
+}
+
+/// A tag describing a meal.
+///
+/// Contains allergy information, descriptions and categories.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    Ord,
+    PartialOrd,
+    IntoPrimitive,
+    TryFromPrimitive,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    IntoStaticStr,
+)]
+#[repr(u8)]
+#[remain::sorted]
+pub enum Tag {
+    Acidifier,
+    Alcohol,
+    Antioxidant,
+    Blackened,
+    #[strum(to_string = "Cacao Containing Fat Glaze")]
+    CacaoContainingFatGlaze,
+    Coloring,
+    Cow,
+    Egg,
+    Fish,
+    #[strum(to_string = "Flavor Enhancer")]
+    FlavorEnhancer,
+    Garlic,
+    Gluten,
+    Lactose,
+    Lupin,
+    Mustard,
+    Nuts,
+    Phosphate,
+    Pig,
+    Poultry,
+    Preservative,
+    /// Misspelled, but renaming the variant would change the tag numbers.
+    /// Serialized as `Sellery` to keep the json output stable.
+    #[serde(alias = "Celery")]
+    #[strum(to_string = "Celery")]
+    Sellery,
+    Sesame,
+    Soy,
+    Sulfite,
+    Sweetener,
+    Vegan,
+    Vegetarian,
+    Waxed,
+}
+
+impl Tag {
+    /// Try deriving [`Tag`]s from the `raw` tag.
+    pub fn parse_str(raw: &str) -> Vec<Self> {
+        TAG_RE
+            .matches(raw)
+            .iter()
+            .map(|idx| Tag::try_from_primitive(idx as u8).unwrap())
+            .collect()
+    }
+
+    /// Is this a primary tag?
+    ///
+    /// Primary tags have an associated emoji and are not allergy information.
+    pub fn is_primary(&self) -> bool {
+        use Tag::*;
+        match self {
+            Cow | Fish | Pig | Poultry | Vegan | Vegetarian => true,
+            Acidifier
+            | Alcohol
+            | Antioxidant
+            | Blackened
+            | CacaoContainingFatGlaze
+            | Coloring
+            | Egg
+            | FlavorEnhancer
+            | Garlic
+            | Gluten
+            | Lupin
+            | Lactose
+            | Mustard
+            | Nuts
+            | Phosphate
+            | Preservative
+            | Sellery
+            | Sesame
+            | Soy
+            | Sulfite
+            | Sweetener
+            | Waxed => false,
+        }
+    }
+
+    /// Is this **not** a primary tag?
+    pub fn is_secondary(&self) -> bool {
+        !self.is_primary()
+    }
+
+    /// Does this tag mark a meal as vegan or vegetarian?
+    pub fn is_veggie(&self) -> bool {
+        matches!(self, Self::Vegan | Self::Vegetarian)
+    }
+
+    /// Does this tag mark a meal as containing meat or fish?
+    pub fn is_meat(&self) -> bool {
+        matches!(self, Self::Cow | Self::Fish | Self::Pig | Self::Poultry)
+    }
+
+    /// Describe this [`Tag`] with english words.
+    ///
+    /// This should add information where the enum variant itself
+    /// does not suffice.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Acidifier => "Contains artificial acidifier",
+            Self::Alcohol => "Contains alcohol",
+            Self::Antioxidant => "Contains an antioxidant",
+            Self::Blackened => {
+                "Contains ingredients that have been blackened, i.e. blackened olives"
+            }
+            Self::CacaoContainingFatGlaze => {
+                "Contains a cheap oil-based glazing instead of real chocolate glazing"
+            }
+            Self::Coloring => "Contains food coloring",
+            Self::Cow => "Contains meat from cattle",
+            Self::Egg => "Contains egg",
+            Self::Fish => "Contains fish",
+            Self::FlavorEnhancer => "Contains artificial flavor enhancer",
+            Self::Garlic => "Contains garlic",
+            Self::Gluten => "Contains gluten",
+            Self::Lactose => "Contains milk",
+            Self::Lupin => "Contains lupin",
+            Self::Mustard => "Contains mustard",
+            Self::Nuts => "Contains nuts",
+            Self::Phosphate => "Contains phosphate",
+            Self::Pig => "Contains meat from pig",
+            Self::Poultry => "Contains poultry meat",
+            Self::Preservative => "Contains artificial preservatives",
+            Self::Sellery => "Contains celery",
+            Self::Sesame => "Contains sesame",
+            Self::Soy => "Contains soy",
+            Self::Sulfite => "Contains sulfite",
+            Self::Sweetener => "Contains artificial sweetener",
+            Self::Vegan => "Does not contain any animal produce",
+            Self::Vegetarian => "Does not contain any meat",
+            Self::Waxed => "Some ingredients have been waxed",
+        }
+    }
+
+    /// This formats an identifier for this tag.
+    ///
+    /// Will respect any settings given, i.e. emojis will be used
+    /// unless the output should be plain or `--no-emoji` is given.
+    /// Textual identifiers respect the configured aliases.
+    pub fn as_id(&self, ctx: &Context<'_>) -> String {
+        self.as_id_with(Self::use_emoji(ctx), self.name(ctx))
+    }
+
+    /// The name of this tag in terminal output.
+    ///
+    /// This is the configured alias, if any, and the english name otherwise.
+    pub fn name<'c>(&self, ctx: &Context<'c>) -> &'c str {
+        match ctx.conf.tag_alias(*self) {
+            Some(alias) => alias,
+            None => self.into(),
+        }
+    }
+
+    /// Whether primary tags are identified by their emoji.
+    fn use_emoji(ctx: &Context<'_>) -> bool {
+        if_plain!(!ctx.conf.args.no_emoji, false)
+    }
+
+    /// Like [`Self::as_id`], using the `name` of primary tags without `emoji`.
+    fn as_id_with(&self, emoji: bool, name: &str) -> String {
+        match self.emoji() {
+            Some(icon) if emoji => icon.into(),
+            Some(_) => name.to_owned(),
+            None => {
+                // If no special emoji is available, just use the id
+                let number: u8 = (*self).into();
+                format!("{}", number)
+            }
+        }
+    }
+
+    /// The emoji representing this tag, only primary tags have one.
+    pub fn emoji(&self) -> Option<&'static str> {
+        match self {
+            Self::Vegan => Some("🌱"),
+            Self::Vegetarian => Some("🧀"),
+            Self::Pig => Some("🐖"),
+            Self::Fish => Some("🐟"),
+            Self::Cow => Some("🐄"),
+            Self::Poultry => Some("🐓"),
+            _ => None,
+        }
+    }
+
+    /// Print this tag.
+    ///
+    /// Does **not** respect `--json`, use [`Self::print_all`].
+    pub fn print(&self, ctx: &Context<'_>) -> Result<()> {
+        self.print_with_count(ctx, None)
+    }
+
+    /// Print this tag, followed by the number of meals carrying it.
+    fn print_with_count(&self, ctx: &Context<'_>, count: Option<usize>) -> Result<()> {
+        let emoji = if ctx.conf.args.plain && self.is_primary() {
+            format!("{:>width$}", "-", width = ID_WIDTH)
+        } else {
+            let emoji = self.as_id(ctx);
+            let emoji_len = emoji.width();
+            format!(
+                "{}{}",
+                " ".repeat(ID_WIDTH.saturating_sub(emoji_len)),
+                emoji
+            )
+        };
+        let description_width = get_sane_terminal_dimensions().0;
+        let description = textwrap::fill(
+            self.describe(),
+            textwrap::Options::new(description_width)
+                .initial_indent(TEXT_INDENT)
+                .subsequent_indent(TEXT_INDENT),
+        );
+        let count = match count {
+            Some(1) => String::from(" (1 meal)"),
+            Some(count) => format!(" ({} meals)", count),
+            None => String::new(),
+        };
+        try_println!(
+            "{} {}{}\n{}",
+            color!(emoji; bright_yellow, bold),
+            color!(self.name(ctx); bold),
+            color!(count; muted),
+            color!(description; muted),
+        )
+    }
+
+    /// All tags, ordered by `sort`.
+    ///
+    /// Sorting by group lists primary tags first, ordered by number.
+    pub fn sorted(sort: TagSort) -> Vec<Self> {
+        let mut tags: Vec<_> = Tag::iter().collect();
+        match sort {
+            TagSort::Name => tags.sort_by_key(|tag| tag.to_string()),
+            TagSort::Number => tags.sort(),
+            TagSort::Group => tags.sort_by_key(|tag| (tag.is_secondary(), *tag)),
+        }
+        tags
+    }
+
+    /// Print all tags in the order given by `sort`.
+    ///
+    /// With `map`, json output is an object keyed by the tag numbers instead.
+    pub fn print_all(ctx: &Context<'_>, sort: TagSort, map: bool) -> Result<()> {
+        let tags = Self::sorted(sort);
+        if ctx.conf.args.json && map {
+            print_json(&Self::json_map(tags))
+        } else if ctx.conf.args.json {
+            Self::print_all_json(ctx, tags)
+        } else {
+            for tag in tags {
+                try_println!()?;
+                tag.print(ctx)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Print the tags of the meals served by `canteen` at the configured date,
+    /// in the order given by `sort`.
+    ///
+    /// Json output lists objects with the `tag` and its `count`.
+    pub fn print_present(ctx: &Context<'_>, canteen: &mut Canteen, sort: TagSort) -> Result<()> {
+        let counts = match canteen.meals_at_mut(ctx, ctx.conf.date())? {
+            Some(meals) => {
+                let meals: Vec<_> = meals.iter_mut().map(|meal| meal.complete()).try_collect()?;
+                Self::count(&meals)
+            }
+            None => BTreeMap::new(),
+        };
+        let present = Self::sorted(sort)
+            .into_iter()
+            .filter_map(|tag| Some((tag, *counts.get(&tag)?)));
+        if ctx.conf.args.json {
+            let tags: Vec<_> = present
+                .map(|(tag, count)| TagCountJson {
+                    tag: TagJson::new(ctx, tag),
+                    count,
+                })
+                .collect();
+            print_json(&tags)
+        } else if counts.is_empty() {
+            try_println!("{}", color!(ctx.conf.messages().no_meals; dimmed))
+        } else {
+            for (tag, count) in present {
+                try_println!()?;
+                tag.print_with_count(ctx, Some(count))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// How many of the `meals` carry each tag.
+    fn count(meals: &[MealComplete<'_>]) -> BTreeMap<Self, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in meals.iter().flat_map(|meal| &meal.meta.tags) {
+            *counts.entry(*tag).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Print all tags as json.
+    ///
+    /// This will result in a list of objects containing the following keys:
+    /// - id: An identifier, like 'Vegan' or '22'
+    /// - number: The stable numeric id, i.e. 25 for 'Vegan'.
+    /// - name: The name of the tag.
+    /// - desc: A simple description.
+    ///
+    /// Configured aliases are not applied.
+    fn print_all_json(ctx: &Context<'_>, tags: Vec<Self>) -> Result<()> {
+        let tags: Vec<_> = tags.into_iter().map(|tag| TagJson::new(ctx, tag)).collect();
+        print_json(&tags)
+    }
+
+    /// Map the numbers of all `tags` to their json representation, see `--map`.
+    ///
+    /// Every value contains the following keys:
+    /// - name: The name of the tag.
+    /// - desc: A simple description.
+    /// - primary: Whether this is a primary tag, like 'Vegan'.
+    /// - emoji: The emoji of a primary tag, missing for other tags.
+    fn json_map(tags: Vec<Self>) -> BTreeMap<u8, TagMapJson> {
+        tags.into_iter()
+            .map(|tag| {
+                let json = TagMapJson {
+                    name: tag.to_string(),
+                    desc: tag.describe(),
+                    primary: tag.is_primary(),
+                    emoji: tag.emoji(),
+                };
+                (tag.into(), json)
+            })
+            .collect()
+    }
+}
+
+impl TagJson {
+    /// The canonical representation of `tag`, ignoring configured aliases.
+    fn new(ctx: &Context<'_>, tag: Tag) -> Self {
+        Self {
+            id: tag.as_id_with(Tag::use_emoji(ctx), tag.into()),
+            number: tag.into(),
+            name: tag.to_string(),
+            desc: tag.describe(),
+        }
+    }
+}
+
+/// The english name of this tag, see [`Tag::name`] for terminal output.
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.into())
+    }
+}
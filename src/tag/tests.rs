@@ -0,0 +1,85 @@
+use pretty_assertions::assert_eq;
+
+use structopt::StructOpt;
+
+use crate::{
+    cache::CACHE,
+    config::{args::Args, Config},
+    meal::Meal,
+};
+
+use super::*;
+
+#[test]
+fn it_sorts_tags() {
+    let by_number = Tag::sorted(TagSort::Number);
+    assert_eq!(by_number.first(), Some(&Tag::Acidifier));
+    assert_eq!(by_number.last(), Some(&Tag::Waxed));
+
+    let by_name = Tag::sorted(TagSort::Name);
+    let names: Vec<_> = by_name.iter().map(Tag::to_string).collect();
+    let mut sorted_names = names.clone();
+    sorted_names.sort();
+    assert_eq!(names, sorted_names);
+
+    let by_group = Tag::sorted(TagSort::Group);
+    let primaries = by_group.iter().take_while(|tag| tag.is_primary()).count();
+    assert_eq!(primaries, 6);
+    assert_eq!(by_group.first(), Some(&Tag::Cow));
+}
+
+#[test]
+fn primary_tags_can_be_written_as_text() {
+    assert_eq!(Tag::Vegan.as_id_with(true, "Vegan"), "🌱");
+    assert_eq!(Tag::Vegan.as_id_with(false, "Vegan"), "Vegan");
+    assert_eq!(
+        Tag::Gluten.as_id_with(true, "Gluten"),
+        Tag::Gluten.as_id_with(false, "Gluten")
+    );
+}
+
+#[test]
+fn the_json_map_is_keyed_by_number() {
+    let map = Tag::json_map(Tag::sorted(TagSort::Number));
+    assert_eq!(map.len(), Tag::iter().count());
+    let vegan = &map[&u8::from(Tag::Vegan)];
+    assert!(vegan.primary);
+    assert_eq!(vegan.emoji, Some("🌱"));
+    let gluten = &map[&u8::from(Tag::Gluten)];
+    assert!(!gluten.primary);
+    assert_eq!(gluten.emoji, None);
+    let json = serde_json::to_value(&map).unwrap();
+    assert_eq!(json["25"]["name"], "Vegan");
+}
+
+#[test]
+fn it_counts_the_tags_of_meals() {
+    let raw = r#"[
+        { "id": 1, "name": "A", "category": "", "prices": {}, "notes": ["Vegan", "Gluten"] },
+        { "id": 2, "name": "B", "category": "", "prices": {}, "notes": ["Gluten"] }
+    ]"#;
+    let mut meals: Vec<Meal> = serde_json::from_str(raw).unwrap();
+    let meals: Vec<_> = meals
+        .iter_mut()
+        .map(|meal| meal.complete().unwrap())
+        .collect();
+    let counts: Vec<_> = Tag::count(&meals).into_iter().collect();
+    assert_eq!(counts, vec![(Tag::Gluten, 2), (Tag::Vegan, 1)]);
+}
+
+#[test]
+fn aliases_only_apply_to_terminal_output() {
+    let toml = "[tag-aliases]\nVegan = \"Pflanzlich\"";
+    let args = Args::from_iter(&["mensa", "--no-emoji", "--config-toml", toml]);
+    let conf = Config::from_args(args);
+    let ctx = Context {
+        cache: &CACHE,
+        conf: &conf,
+    };
+    assert_eq!(Tag::Vegan.name(&ctx), "Pflanzlich");
+    assert_eq!(Tag::Vegan.as_id(&ctx), "Pflanzlich");
+    assert_eq!(Tag::Vegan.to_string(), "Vegan");
+    let json = serde_json::to_value(TagJson::new(&ctx, Tag::Vegan)).unwrap();
+    assert_eq!(json["id"], "Vegan");
+    assert_eq!(json["name"], "Vegan");
+}
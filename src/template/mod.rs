@@ -0,0 +1,83 @@
+//! Tiny templates for user-defined output, see `--canteen-format` and `--meal-format`.
+//!
+//! Placeholders are written like `{name}`, literal braces as `{{` and `}}`.
+#[cfg(test)]
+mod tests;
+
+use crate::error::{Error, Result};
+
+/// A parsed template, only containing known placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(String),
+}
+
+impl Template {
+    /// Parse `raw`, allowing only placeholders listed in `known`.
+    pub fn parse(raw: &str, known: &[&str]) -> Result<Self> {
+        let invalid = || Error::InvalidTemplate(raw.to_owned());
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = raw.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed || name.contains('{') {
+                        return Err(invalid());
+                    }
+                    if !known.contains(&name.as_str()) {
+                        return Err(Error::UnknownPlaceholder(name, known.join(", ")));
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Placeholder(name));
+                }
+                '}' => return Err(invalid()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Fill in the placeholders with the values returned by `value`.
+    pub fn render<F>(&self, mut value: F) -> Result<String>
+    where
+        F: FnMut(&str) -> Result<String>,
+    {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Placeholder(name) => rendered.push_str(&value(name)?),
+            }
+        }
+        Ok(rendered)
+    }
+}
@@ -0,0 +1,26 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+#[test]
+fn placeholders_are_replaced() {
+    let template = Template::parse("{id}: {name} {{{name}}}", &["id", "name"]).unwrap();
+    let rendered = template.render(|name| Ok(name.to_uppercase())).unwrap();
+    assert_eq!(rendered, "ID: NAME {NAME}");
+}
+
+#[test]
+fn invalid_templates_are_rejected() {
+    assert!(matches!(
+        Template::parse("{id} {nope}", &["id"]),
+        Err(Error::UnknownPlaceholder(name, _)) if name == "nope"
+    ));
+    assert!(matches!(
+        Template::parse("{id", &["id"]),
+        Err(Error::InvalidTemplate(_))
+    ));
+    assert!(matches!(
+        Template::parse("id}", &["id"]),
+        Err(Error::InvalidTemplate(_))
+    ));
+}
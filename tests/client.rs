@@ -0,0 +1,50 @@
+use std::{env, process::Command};
+
+use chrono::NaiveDate;
+use mensa::{
+    cache::{Cache, Headers, MemoryCache},
+    Client,
+};
+
+/// Set in the child process spawned by [`client_does_not_read_the_command_line`].
+const CHILD: &str = "MENSA_TEST_CLIENT_CHILD";
+
+/// Use a client on a prefilled cache, without touching the network.
+fn use_client() {
+    let client = Client::<MemoryCache>::in_memory().unwrap();
+    let date = NaiveDate::from_ymd_opt(2021, 11, 3).unwrap();
+    let canteens = r#"[ { "id": 63, "name": "Mensa", "city": "Leipzig", "address": "Straße 1" } ]"#;
+    let meals =
+        r#"[ { "id": 1, "name": "Nudeln", "category": "Essen", "prices": {}, "notes": [] } ]"#;
+    let cache = client.cache();
+    let headers = Headers::default();
+    let url = "https://openmensa.org/api/v2/canteens?near[lat]=51.34&near[lng]=12.37&near[dist]=2";
+    cache.write(&headers, url, canteens).unwrap();
+    let url = "https://openmensa.org/api/v2/canteens/63/days/2021-11-03/meals";
+    cache.write(&headers, url, meals).unwrap();
+
+    let canteens = client.canteens_near(51.34, 12.37, 2.0).unwrap();
+    assert_eq!(canteens.len(), 1);
+    let meta = canteens[0].fetched_meta().unwrap();
+    assert_eq!(meta.name(), "Mensa");
+    assert_eq!(meta.city(), "Leipzig");
+    assert_eq!(meta.address(), "Straße 1");
+    let mut meals = client.meals(canteens[0].id(), &date).unwrap();
+    assert_eq!(meals.len(), 1);
+    assert_eq!(meals[0].meta().unwrap().name, "Nudeln");
+}
+
+#[test]
+fn client_does_not_read_the_command_line() {
+    if env::var_os(CHILD).is_some() {
+        return use_client();
+    }
+    // The arguments are valid for the test harness, but not for mensa.
+    // Parsing them as mensa's arguments exits the child with an error
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["client_does_not_read_the_command_line", "--exact"])
+        .env(CHILD, "1")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,65 @@
+// The state file is only redirected via `XDG_DATA_HOME` on Linux
+#![cfg(target_os = "linux")]
+
+use std::{env, fs, path::Path, process::Command};
+
+use mensa::cache::{Cacache, Cache, Headers};
+
+const API: &str = "https://openmensa.org/api/v2/canteens/63";
+
+/// Fill a disk cache at `dir`, so that mensa never touches the network.
+fn prefill_cache(dir: &Path) {
+    let cache = Cacache::open(dir).unwrap();
+    let headers = Headers::default();
+    let responses = [
+        (
+            "",
+            r#"{ "id": 63, "name": "Mensa", "city": "", "address": "" }"#,
+        ),
+        ("/days", r#"[ { "date": "2021-11-03", "closed": false } ]"#),
+        (
+            "/days/2021-11-03/meals",
+            r#"[ { "id": 1, "name": "Nudeln", "category": "Essen", "prices": {}, "notes": [] } ]"#,
+        ),
+    ];
+    for (path, json) in responses {
+        cache
+            .write(&headers, &format!("{}{}", API, path), json)
+            .unwrap();
+    }
+}
+
+/// Run mensa with `--only-if-changed`, returning stdout and stderr.
+fn run(dir: &Path) -> (String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mensa"))
+        .args(["meals", "--id", "63", "--date", "2021-11-03"])
+        .args(["--only-if-changed", "--explain-filter"])
+        .args(["--timeout-per-canteen", "5", "--config-toml", ""])
+        .env("MENSA_CACHE_DIR", dir.join("cache"))
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("HOME", dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let text = |bytes| String::from_utf8(bytes).unwrap();
+    (text(output.stdout), text(output.stderr))
+}
+
+#[test]
+fn filter_decisions_are_explained_once() {
+    let dir = env::temp_dir().join(format!("mensa-only-if-changed-{}", std::process::id()));
+    prefill_cache(&dir.join("cache"));
+
+    let (stdout, stderr) = run(&dir);
+    assert!(stdout.contains("Nudeln"), "{}", stdout);
+    assert_eq!(
+        stderr.matches("explain-filter: \"Nudeln\"").count(),
+        1,
+        "{}",
+        stderr
+    );
+    // Nothing changed, so nothing is printed
+    let (stdout, _) = run(&dir);
+    assert!(!stdout.contains("Nudeln"), "{}", stdout);
+    fs::remove_dir_all(dir).unwrap();
+}